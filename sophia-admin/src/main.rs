@@ -0,0 +1,121 @@
+use chrono::{Local, TimeZone};
+use clap::{Parser, Subcommand};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::UnixStream;
+
+use sophia_core::admin::{AdminCommand, AdminResponse, AdminResult};
+use sophia_core::errno_new;
+use sophia_core::errors::Result;
+use sophia_core::model::UserInfo;
+
+#[derive(Parser, Debug)] // requires `derive` feature
+#[clap(name = "sophia-admin")]
+pub struct Args {
+    /// admin socket of the running sophia-server
+    #[arg(short = 's', long = "socket", default_value = "./sophia-data/admin.sock")]
+    socket: String,
+    #[command(subcommand)]
+    command: Cmd,
+}
+
+#[derive(Subcommand, Debug)]
+enum Cmd {
+    /// list chat rooms with online users
+    Chats,
+    /// list the online users of a chat room
+    Users { chat_id: i64 },
+    /// list all login sessions
+    Sessions,
+    /// disconnect a user from a chat room
+    Kick { chat_id: i64, user_name: String },
+    /// send a system announcement to a chat room, or every room if no --chat is given
+    Announce {
+        #[arg(short = 'c', long = "chat")]
+        chat_id: Option<i64>,
+        #[arg(required = true)]
+        content: Vec<String>,
+    },
+    /// change the server log filter, e.g. `debug` or `info,sophia_net=trace`
+    LogLevel { level: String },
+    /// show a message with its edit history, including deleted messages
+    History { chat_id: i64, id: i64 },
+}
+
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let cmd = match args.command {
+        Cmd::Chats => AdminCommand::ListChats,
+        Cmd::Users { chat_id } => AdminCommand::ListUsers { chat_id },
+        Cmd::Sessions => AdminCommand::ListSessions,
+        Cmd::Kick { chat_id, user_name } => AdminCommand::Kick { chat_id, user_name },
+        Cmd::Announce { chat_id, content } => AdminCommand::Announce { chat_id, content: content.join(" ") },
+        Cmd::LogLevel { level } => AdminCommand::SetLogLevel { level },
+        Cmd::History { chat_id, id } => AdminCommand::MessageHistory { chat_id, id },
+    };
+
+    let resp = send(&args.socket, &cmd).await?;
+    if resp.code != 0 {
+        eprintln!("error code : {}, msg = {}", resp.code, resp.msg);
+        std::process::exit(1);
+    }
+
+    match resp.data {
+        Some(AdminResult::Chats(chats)) => {
+            println!("{:<12} USERS", "CHAT_ID");
+            for chat in chats {
+                println!("{:<12} {}", chat.chat_id, chat.user_count);
+            }
+        }
+        Some(AdminResult::Users(users)) => print_users(&users),
+        Some(AdminResult::MessageHistory(msg, history)) => {
+            println!("{:<20} CONTENT", "REPLACED_AT");
+            for edit in history {
+                println!("{}  {}", format_time(edit.time), edit.content);
+            }
+            match msg {
+                Some(msg) => println!("{}  {}  (current, by {})",
+                                      format_time(msg.edited_at.unwrap_or(msg.time)), msg.content, msg.user.user_name),
+                None => println!("(deleted)"),
+            }
+        }
+        None => println!("{}", resp.msg),
+    }
+
+    Ok(())
+}
+
+
+async fn send(socket: &str, cmd: &AdminCommand) -> Result<AdminResponse> {
+    let stream = UnixStream::connect(socket).await
+        .map_err(|e| errno_new!("connect admin socket {} failed, is sophia-server running? err = {}", socket, e))?;
+    let (reader, mut writer) = stream.into_split();
+
+    let mut data = serde_json::to_vec(cmd)?;
+    data.push(b'\n');
+    writer.write_all(&data).await?;
+
+    let line = BufReader::new(reader).lines().next_line().await?
+        .ok_or(errno_new!("admin socket closed without response"))?;
+
+    Ok(serde_json::from_str(&line)?)
+}
+
+
+fn print_users(users: &[UserInfo]) {
+    println!("{:<10} {:<20} {:<10} {:<24} {:<20} SESSION", "CHAT_ID", "NAME", "ROLE", "ADDRESS", "LOGIN_TIME");
+    for u in users {
+        let login_time = format_time(u.login_time);
+
+        println!("{:<10} {:<20} {:<10} {:<24} {:<20} {}",
+                 u.chat_id, u.name, u.role.to_string(), u.address, login_time, u.redacted_session());
+    }
+}
+
+fn format_time(timestamp: i64) -> String {
+    Local.timestamp_opt(timestamp, 0).single()
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default()
+}
@@ -0,0 +1,52 @@
+//! Admin control channel protocol, spoken over the server's local unix socket.
+//! Each request and response is a single line of json.
+
+use serde::{Deserialize, Serialize};
+
+use crate::consts;
+use crate::model::{Message, MessageEdit, UserInfo};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub enum AdminCommand {
+    ListChats,
+    ListUsers { chat_id: i64 },
+    ListSessions,
+    Kick { chat_id: i64, user_name: String },
+    /// system announcement to one chat, or to every chat if `chat_id` is `None`
+    Announce { chat_id: Option<i64>, content: String },
+    /// `RUST_LOG` style filter, e.g. `debug` or `info,sophia_net=trace`
+    SetLogLevel { level: String },
+    /// a message and its previous versions
+    MessageHistory { chat_id: i64, id: i64 },
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ChatSummary {
+    pub chat_id: i64,
+    pub user_count: usize,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub enum AdminResult {
+    Chats(Vec<ChatSummary>),
+    Users(Vec<UserInfo>),
+    /// the message as it is now (it may have been deleted) and its previous versions, oldest first
+    MessageHistory(Option<Box<Message>>, Vec<MessageEdit>),
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AdminResponse {
+    pub code: usize,
+    pub msg: String,
+    pub data: Option<AdminResult>,
+}
+
+impl AdminResponse {
+    pub fn new(code: usize, msg: String) -> Self {
+        AdminResponse { code, msg, data: None }
+    }
+
+    pub fn success(data: Option<AdminResult>) -> Self {
+        AdminResponse { code: consts::code::SUCCESS, msg: "ok".to_string(), data }
+    }
+}
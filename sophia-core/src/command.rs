@@ -1,13 +1,36 @@
+use std::collections::HashMap;
+use std::fmt;
+
 use derive_more::Display;
 use serde::{Deserialize, Serialize};
 
-use crate::model::{Message, User};
+use crate::model::{InboxItem, Message, MessageKind, ModerationEvent, Reaction, Role, Status, User};
 
 #[derive(Debug, Clone, Copy, PartialEq, Hash, Eq, Display, Deserialize, Serialize)]
 pub enum CommandType {
     // server handle cmd
     Login,
+    Register,
     SendMessage,
+    Kick,
+    Ban,
+    Unban,
+    Mute,
+    SetRole,
+    Typing,
+    SetStatus,
+    EditMessage,
+    DeleteMessage,
+    React,
+    Unreact,
+    MarkRead,
+    SendDirectMessage,
+    SearchMessages,
+    OfferFile,
+    DownloadFile,
+    JoinChat,
+    SetTopic,
+    ChangeNick,
 
     // client handler cmd
     ChatMessageList,
@@ -15,14 +38,132 @@ pub enum CommandType {
     UserOffline,
     ChatUserList,
     NewMessage,
+    ModerationEvent,
+    SystemNotice,
+    UserTyping,
+    MessageEdited,
+    MessageDeleted,
+    ReactionsChanged,
+    ReadState,
+    ReadStates,
+    DirectMessage,
+    Inbox,
+    ChatTopic,
+    NickChanged,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum Command {
     Login(Login),
+    /// create an account, its name can then only be used with its password
+    Register(Registration),
     SendTextMessage {
         msg: String,
         chat_id: i64,
+        /// id of the message this one replies to
+        #[serde(default)]
+        reply_to: Option<i64>,
+        /// anything but `Notice`
+        #[serde(default)]
+        kind: MessageKind,
+    },
+    Kick {
+        chat_id: i64,
+        user_name: String,
+    },
+    /// ban user from the chat, `duration` in seconds, `None` bans forever
+    Ban {
+        chat_id: i64,
+        user_name: String,
+        duration: Option<i64>,
+    },
+    Unban {
+        chat_id: i64,
+        user_name: String,
+    },
+    /// mute user in the chat for `duration` seconds
+    Mute {
+        chat_id: i64,
+        user_name: String,
+        duration: i64,
+    },
+    SetRole {
+        chat_id: i64,
+        user_name: String,
+        role: Role,
+    },
+    SetStatus {
+        chat_id: i64,
+        status: Status,
+        text: String,
+    },
+    /// the author may edit a message
+    EditMessage {
+        id: i64,
+        content: String,
+    },
+    /// the author, or a moderator outranking the author, may delete a message
+    DeleteMessage {
+        id: i64,
+    },
+    React {
+        message_id: i64,
+        emoji: String,
+    },
+    Unreact {
+        message_id: i64,
+        emoji: String,
+    },
+    /// everything up to message `seq` of the chat has been read
+    MarkRead {
+        chat_id: i64,
+        seq: i64,
+    },
+    /// full text search of the chat, newest hits first. `query` takes words, "quoted phrases",
+    /// `from:<user>`, `since:<yyyy-mm-dd>` and `until:<yyyy-mm-dd>`; `before` is the id of the
+    /// last hit of the previous page
+    SearchMessages {
+        chat_id: i64,
+        query: String,
+        limit: usize,
+        #[serde(default)]
+        before: Option<i64>,
+    },
+    /// announce a file upload, the response carries `CommandResult::Upload` with the
+    /// offset to stream the content from, which is past 0 when resuming an upload
+    OfferFile {
+        chat_id: i64,
+        name: String,
+        size: u64,
+        hash: String,
+    },
+    /// ask the server to stream a shared file from `offset` on over a unidirectional stream,
+    /// the stream may arrive before the response
+    DownloadFile {
+        id: i64,
+        offset: u64,
+    },
+    /// private message to a user in any chat, queued in the inbox if a registered user is offline
+    SendDirectMessage {
+        to: String,
+        msg: String,
+    },
+    /// move the session to another chat, pushing its user list, messages and topic like a login
+    JoinChat {
+        chat_id: i64,
+    },
+    /// moderators and the owner may set the topic, empty clears it
+    SetTopic {
+        chat_id: i64,
+        topic: String,
+    },
+    /// rename the session, the name is checked like at login
+    ChangeNick {
+        user_name: String,
+    },
+    /// sent unreliably as a QUIC datagram while the user is typing
+    Typing {
+        chat_id: i64,
     },
     UserOnline {
         time: i64,
@@ -39,27 +180,170 @@ pub enum Command {
     ChatMessageList {
         message_list: Vec<Message>,
     },
+    ModerationEvent(ModerationEvent),
+    /// text of the server shown as a `MessageKind::Notice`, e.g. a user joined or was kicked
+    SystemNotice {
+        time: i64,
+        content: String,
+    },
+    UserTyping {
+        chat_id: i64,
+        user_name: String,
+    },
+    MessageEdited(Message),
+    MessageDeleted {
+        chat_id: i64,
+        id: i64,
+    },
+    ReactionsChanged {
+        chat_id: i64,
+        message_id: i64,
+        reactions: Vec<Reaction>,
+    },
+    /// a member of the chat read up to `seq`
+    ReadState {
+        chat_id: i64,
+        user_name: String,
+        seq: i64,
+    },
+    /// sent at login: the read positions of the chat's members (including our own,
+    /// as it was before this login) and the unread counts of the other chats we read before
+    ReadStates {
+        chat_id: i64,
+        read: HashMap<String, i64>,
+        unread: HashMap<i64, i64>,
+    },
+    /// pushed to both the sender and the recipient
+    DirectMessage {
+        to: String,
+        message: Message,
+    },
+    /// direct messages and mentions received while offline, pushed at login
+    Inbox {
+        items: Vec<InboxItem>,
+    },
+    /// topic of the chat, pushed when joining it and whenever it changes
+    ChatTopic {
+        chat_id: i64,
+        topic: String,
+    },
+    /// a member of the chat was renamed, the new user list follows
+    NickChanged {
+        time: i64,
+        old: String,
+        new: String,
+    },
 }
 
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Clone, Deserialize, Serialize)]
 pub struct Login {
     pub user_name: String,
     pub password: String,
     pub chat_id: i64,
 }
 
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Registration {
+    pub user_name: String,
+    pub password: String,
+}
+
+impl fmt::Debug for Registration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Registration")
+            .field("user_name", &self.user_name)
+            .field("password", &"***")
+            .finish()
+    }
+}
+
+/// keep the password out of logs
+impl fmt::Debug for Login {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Login")
+            .field("user_name", &self.user_name)
+            .field("password", &"***")
+            .field("chat_id", &self.chat_id)
+            .finish()
+    }
+}
+
 
 impl Command {
     pub fn command_type(&self) -> CommandType {
         match self {
             Command::Login { 0: _ } => CommandType::Login,
-            Command::SendTextMessage { msg: _, chat_id: _ } => CommandType::SendMessage,
+            Command::Register(_) => CommandType::Register,
+            Command::SendTextMessage { .. } => CommandType::SendMessage,
+            Command::Kick { .. } => CommandType::Kick,
+            Command::Ban { .. } => CommandType::Ban,
+            Command::Unban { .. } => CommandType::Unban,
+            Command::Mute { .. } => CommandType::Mute,
+            Command::SetRole { .. } => CommandType::SetRole,
+            Command::Typing { .. } => CommandType::Typing,
+            Command::SetStatus { .. } => CommandType::SetStatus,
+            Command::EditMessage { .. } => CommandType::EditMessage,
+            Command::DeleteMessage { .. } => CommandType::DeleteMessage,
+            Command::React { .. } => CommandType::React,
+            Command::Unreact { .. } => CommandType::Unreact,
+            Command::MarkRead { .. } => CommandType::MarkRead,
+            Command::SendDirectMessage { .. } => CommandType::SendDirectMessage,
+            Command::SearchMessages { .. } => CommandType::SearchMessages,
+            Command::OfferFile { .. } => CommandType::OfferFile,
+            Command::DownloadFile { .. } => CommandType::DownloadFile,
+            Command::JoinChat { .. } => CommandType::JoinChat,
+            Command::SetTopic { .. } => CommandType::SetTopic,
+            Command::ChangeNick { .. } => CommandType::ChangeNick,
             Command::UserOffline { time: _, user: _, } => CommandType::UserOffline,
             Command::UserOnline { time: _, user: _, } => CommandType::UserOnline,
             Command::ChatUserList { user_list: _ } => CommandType::ChatUserList,
             Command::NewMessage { 0: _ } => CommandType::NewMessage,
             Command::ChatMessageList { message_list: _ } => CommandType::ChatMessageList,
+            Command::ModerationEvent { 0: _ } => CommandType::ModerationEvent,
+            Command::SystemNotice { .. } => CommandType::SystemNotice,
+            Command::UserTyping { .. } => CommandType::UserTyping,
+            Command::MessageEdited(_) => CommandType::MessageEdited,
+            Command::MessageDeleted { .. } => CommandType::MessageDeleted,
+            Command::ReactionsChanged { .. } => CommandType::ReactionsChanged,
+            Command::ReadState { .. } => CommandType::ReadState,
+            Command::ReadStates { .. } => CommandType::ReadStates,
+            Command::DirectMessage { .. } => CommandType::DirectMessage,
+            Command::Inbox { .. } => CommandType::Inbox,
+            Command::ChatTopic { .. } => CommandType::ChatTopic,
+            Command::NickChanged { .. } => CommandType::NickChanged,
+        }
+    }
+}
+
+/// first frame of a unidirectional stream, the raw file content follows it
+#[derive(Clone, Deserialize, Serialize)]
+pub enum StreamHeader {
+    /// client -> server, content of `OfferFile` from `offset` on
+    Upload {
+        session_id: String,
+        id: i64,
+        offset: u64,
+    },
+    /// server -> client, content of `DownloadFile` from `offset` on
+    Download {
+        id: i64,
+        offset: u64,
+    },
+}
+
+/// keep the session id out of logs
+impl fmt::Debug for StreamHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamHeader::Upload { id, offset, .. } => f.debug_struct("Upload")
+                .field("id", id)
+                .field("offset", offset)
+                .finish(),
+            StreamHeader::Download { id, offset } => f.debug_struct("Download")
+                .field("id", id)
+                .field("offset", offset)
+                .finish(),
         }
     }
 }
@@ -67,5 +351,11 @@ impl Command {
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum CommandResult {
     DataStr(String),
+    /// milliseconds the client should wait before retrying a rate limited request
+    RetryAfter(u64),
+    /// hits of `SearchMessages`
+    Messages(Vec<Message>),
+    /// answer to `OfferFile`, stream the content from `offset` on
+    Upload { id: i64, offset: u64 },
     Abc,
 }
\ No newline at end of file
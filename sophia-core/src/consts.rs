@@ -1,3 +1,30 @@
+/// max chars of a single chat message
+pub const MAX_MSG_LEN: usize = 1024;
+/// max chars of a user name
+pub const MAX_USER_NAME_LEN: usize = 32;
+/// max chars of a custom status text
+pub const MAX_STATUS_TEXT_LEN: usize = 64;
+/// max chars of a reaction, enough for emoji joined by ZWJ
+pub const MAX_EMOJI_LEN: usize = 8;
+/// max distinct reactions on one message
+pub const MAX_REACTIONS: usize = 20;
+/// min chars of an account password
+pub const MIN_PASSWORD_LEN: usize = 6;
+/// max items queued in the inbox of an offline user, the oldest are dropped
+pub const MAX_INBOX_ITEMS: usize = 100;
+/// max hits returned by one search
+pub const MAX_SEARCH_HITS: usize = 50;
+/// max chars of a chat topic
+pub const MAX_TOPIC_LEN: usize = 128;
+/// max bytes of a shared file
+pub const MAX_FILE_SIZE: u64 = 64 * 1024 * 1024;
+/// max chars of a shared file name
+pub const MAX_FILE_NAME_LEN: usize = 128;
+/// longest ban or mute with a duration, in seconds
+pub const MAX_MODERATION_SECS: i64 = 365 * 24 * 3600;
+/// bytes written to a file stream at once
+pub const FILE_CHUNK_SIZE: usize = 64 * 1024;
+
 pub mod code {
     pub const SUCCESS: usize = 0;
 
@@ -6,5 +33,32 @@ pub mod code {
     pub const SESSION_ID_INVALID: usize = 1001;
     pub const CHAT_ID_INVALID: usize = 1002;
     pub const USER_NAME_DUPLICATE_ERROR: usize = 1003;
+    pub const RATE_LIMITED: usize = 1004;
+    pub const MESSAGE_EMPTY: usize = 1005;
+    pub const MESSAGE_TOO_LONG: usize = 1006;
+    pub const MESSAGE_INVALID_CHAR: usize = 1007;
+    pub const USER_NAME_EMPTY: usize = 1008;
+    pub const USER_NAME_TOO_LONG: usize = 1009;
+    pub const USER_NAME_INVALID_CHAR: usize = 1010;
+    pub const PERMISSION_DENIED: usize = 1011;
+    pub const USER_NOT_FOUND: usize = 1012;
+    pub const USER_BANNED: usize = 1013;
+    pub const USER_MUTED: usize = 1014;
+    pub const STATUS_TEXT_TOO_LONG: usize = 1015;
+    pub const STATUS_TEXT_INVALID_CHAR: usize = 1016;
+    pub const MESSAGE_NOT_FOUND: usize = 1017;
+    pub const REACTION_INVALID: usize = 1018;
+    pub const TOO_MANY_REACTIONS: usize = 1019;
+    pub const ACCOUNT_EXISTS: usize = 1020;
+    pub const PASSWORD_TOO_SHORT: usize = 1021;
+    pub const SEARCH_QUERY_INVALID: usize = 1022;
+    pub const FILE_TOO_LARGE: usize = 1023;
+    pub const FILE_INVALID: usize = 1024;
+    pub const QUOTA_EXCEEDED: usize = 1025;
+    pub const FILE_NOT_FOUND: usize = 1026;
+    pub const TOPIC_TOO_LONG: usize = 1027;
+    pub const TOPIC_INVALID_CHAR: usize = 1028;
+    pub const MESSAGE_KIND_INVALID: usize = 1029;
+    pub const DURATION_INVALID: usize = 1030;
     pub const INTERNAL_ERROR: usize = 5000;
 }
\ No newline at end of file
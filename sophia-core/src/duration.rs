@@ -0,0 +1,33 @@
+/// short human readable duration in at most two units, e.g. `90` -> `1m30s`, `7200` -> `2h`
+pub fn format_duration(secs: i64) -> String {
+    let secs = secs.max(0);
+    let (major, major_unit, minor, minor_unit) = match secs {
+        0..=59 => return format!("{}s", secs),
+        60..=3599 => (secs / 60, "m", secs % 60, "s"),
+        3600..=86399 => (secs / 3600, "h", secs % 3600 / 60, "m"),
+        _ => (secs / 86400, "d", secs % 86400 / 3600, "h"),
+    };
+
+    match minor {
+        0 => format!("{}{}", major, major_unit),
+        _ => format!("{}{}{}{}", major, major_unit, minor, minor_unit),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::format_duration;
+
+    #[test]
+    fn format_duration_units() {
+        assert_eq!(format_duration(-5), "0s");
+        assert_eq!(format_duration(59), "59s");
+        assert_eq!(format_duration(60), "1m");
+        assert_eq!(format_duration(90), "1m30s");
+        assert_eq!(format_duration(3600), "1h");
+        assert_eq!(format_duration(5400), "1h30m");
+        assert_eq!(format_duration(86400 * 7), "7d");
+        assert_eq!(format_duration(86400 + 3 * 3600 + 59), "1d3h");
+    }
+}
@@ -0,0 +1,33 @@
+use std::io::Read;
+use std::path::Path;
+
+use ring::digest;
+
+use crate::consts::FILE_CHUNK_SIZE;
+use crate::errors::Result;
+
+/// hex sha256 of the file at `path`, the hash files are offered and checked with.
+/// Reads the whole file, so async callers run it with `spawn_blocking`
+pub fn sha256_hex(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut ctx = digest::Context::new(&digest::SHA256);
+    let mut buf = vec![0u8; FILE_CHUNK_SIZE];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        ctx.update(&buf[..n]);
+    }
+
+    Ok(hex(ctx.finish()))
+}
+
+/// hex sha256 of `bytes`
+pub fn sha256_hex_bytes(bytes: &[u8]) -> String {
+    hex(digest::digest(&digest::SHA256, bytes))
+}
+
+fn hex(digest: digest::Digest) -> String {
+    digest.as_ref().iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -3,4 +3,7 @@ pub mod model;
 pub mod command;
 pub mod macros;
 pub mod consts;
+pub mod admin;
+pub mod hash;
+pub mod duration;
 
@@ -1,15 +1,31 @@
+use std::fmt;
+
+use derive_more::Display;
 use serde::{Deserialize, Serialize};
 
 use crate::command::{Command, CommandResult, CommandType};
 use crate::consts;
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Deserialize, Serialize)]
 pub struct UserInfo {
     pub name: String,
     pub session_id: String,
     pub address: String,
     pub chat_id: i64,
     pub login_time: i64,
+    #[serde(default)]
+    pub role: Role,
+    #[serde(default)]
+    pub status: Status,
+    /// custom status text set by the user, may be empty
+    #[serde(default)]
+    pub status_text: String,
+    /// unix timestamp of the last message or status change
+    #[serde(default)]
+    pub last_active: i64,
+    /// registered account the session logged in with, `None` for guests, roles belong to it
+    #[serde(default)]
+    pub account: Option<String>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -18,17 +34,146 @@ pub struct User {
     pub address: String,
     pub chat_id: i64,
     pub login_time: i64,
+    #[serde(default)]
+    pub role: Role,
+    #[serde(default)]
+    pub status: Status,
+    #[serde(default)]
+    pub status_text: String,
+    #[serde(default)]
+    pub last_active: i64,
+}
+
+/// user role inside a chat room, ordered from the most to the least privileged
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Display, Deserialize, Serialize)]
+pub enum Role {
+    Owner,
+    Moderator,
+    #[default]
+    Member,
+}
+
+/// presence status, set by the user or automatically by the client when idle
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Display, Deserialize, Serialize)]
+pub enum Status {
+    #[default]
+    Online,
+    Away,
+    Busy,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Ban {
+    pub user_name: String,
+    /// remote ip of the user when banned, empty if the user was offline
+    pub ip: String,
+    pub operator: String,
+    pub time: i64,
+    /// unix timestamp the ban expires at, `None` for a permanent ban
+    pub until: Option<i64>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum ModerationAction {
+    Kick,
+    Ban { until: Option<i64> },
+    Unban,
+    Mute { until: i64 },
+    SetRole(Role),
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ModerationEvent {
+    pub time: i64,
+    pub chat_id: i64,
+    pub action: ModerationAction,
+    pub target: String,
+    pub operator: String,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Message {
+    /// sequence number inside the chat, assigned by the server when the message is saved
+    #[serde(default)]
+    pub id: i64,
     pub user: User,
     pub time: i64,
     pub content: String,
+    /// id of the message this one replies to
+    #[serde(default)]
+    pub reply_to: Option<i64>,
+    /// unix timestamp of the last edit
+    #[serde(default)]
+    pub edited_at: Option<i64>,
+    #[serde(default)]
+    pub reactions: Vec<Reaction>,
+    /// user names mentioned as `@name` in the content
+    #[serde(default)]
+    pub mentions: Vec<String>,
+    /// file shared with the chat, the content is its name
+    #[serde(default)]
+    pub file: Option<FileOffer>,
+    #[serde(default)]
+    pub kind: MessageKind,
+    /// who may edit the message, stays on the server
+    #[serde(skip)]
+    pub author: Option<Author>,
+}
+
+/// the sender of a message, which outlasts a rename: its account, or the session of a guest
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Author {
+    Account(String),
+    Session(String),
 }
 
+/// how a message is shown
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum MessageKind {
+    #[default]
+    Text,
+    /// `/me waves`, shown as `* alice waves`
+    Emote,
+    /// from the server itself, e.g. someone joined, never sent by a client
+    Notice,
+    /// sent by a program rather than a person
+    Bot,
+}
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// a file uploaded to the server, downloaded by `id`
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct FileOffer {
+    pub id: i64,
+    pub name: String,
+    pub size: u64,
+    /// sha256 of the content, lower case hex
+    pub hash: String,
+}
+
+/// everyone who reacted to a message with the same emoji, in order of the first reaction
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Reaction {
+    pub emoji: String,
+    pub users: Vec<String>,
+}
+
+/// something that happened while a registered user was offline
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum InboxItem {
+    Mention { chat_id: i64, message: Message },
+    Direct { message: Message },
+}
+
+/// a previous version of an edited message
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MessageEdit {
+    /// when this version was replaced
+    pub time: i64,
+    pub content: String,
+}
+
+
+#[derive(Clone, Deserialize, Serialize)]
 pub struct Base {
     pub session_id: String,
     pub remote_add: String,
@@ -58,6 +203,47 @@ impl Base {
             user_info: None,
         }
     }
+
+    /// enough of the session id to correlate log lines without leaking the token
+    pub fn redacted_session(&self) -> String {
+        redact(&self.session_id)
+    }
+}
+
+/// the session id is a bearer token, keep it out of logs
+impl fmt::Debug for Base {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Base")
+            .field("session_id", &self.redacted_session())
+            .field("remote_add", &self.remote_add)
+            .field("user_info", &self.user_info)
+            .finish()
+    }
+}
+
+impl fmt::Debug for UserInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UserInfo")
+            .field("name", &self.name)
+            .field("session_id", &self.redacted_session())
+            .field("address", &self.address)
+            .field("chat_id", &self.chat_id)
+            .field("login_time", &self.login_time)
+            .field("role", &self.role)
+            .field("status", &self.status)
+            .field("status_text", &self.status_text)
+            .field("last_active", &self.last_active)
+            .finish()
+    }
+}
+
+fn redact(secret: &str) -> String {
+    if secret.is_empty() {
+        return String::new();
+    }
+
+    let prefix: String = secret.chars().take(6).collect();
+    format!("{}***", prefix)
 }
 
 impl Request {
@@ -82,7 +268,16 @@ impl Response {
 
 impl User {
     pub fn new(user_name: String, address: String, chat_id: i64, login_time: i64) -> Self {
-        User { user_name, address, chat_id, login_time }
+        User {
+            user_name,
+            address,
+            chat_id,
+            login_time,
+            role: Role::default(),
+            status: Status::default(),
+            status_text: String::new(),
+            last_active: login_time,
+        }
     }
 
     pub fn from_user_info(u: &UserInfo) -> Self {
@@ -91,6 +286,10 @@ impl User {
             address: u.address.to_string(),
             chat_id: u.chat_id,
             login_time: u.login_time,
+            role: u.role,
+            status: u.status,
+            status_text: u.status_text.to_string(),
+            last_active: u.last_active,
         }
     }
 }
@@ -103,6 +302,32 @@ impl UserInfo {
             session_id,
             chat_id,
             login_time,
+            role: Role::default(),
+            status: Status::default(),
+            status_text: String::new(),
+            last_active: login_time,
+            account: None,
+        }
+    }
+
+    pub fn author(&self) -> Author {
+        match &self.account {
+            Some(account) => Author::Account(account.to_string()),
+            None => Author::Session(self.session_id.to_string()),
+        }
+    }
+
+    /// the session id is a bearer token, this is what may be shown
+    pub fn redacted_session(&self) -> String {
+        redact(&self.session_id)
+    }
+}
+
+impl Ban {
+    pub fn is_active(&self, now: i64) -> bool {
+        match self.until {
+            Some(until) => until > now,
+            None => true,
         }
     }
 }
\ No newline at end of file
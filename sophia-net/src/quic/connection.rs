@@ -3,10 +3,11 @@ use std::sync::Arc;
 
 use async_trait::async_trait;
 use futures_util::future::BoxFuture;
-use log::error;
-use tokio::time::Duration;
+use tracing::{error, info_span, Instrument, Span};
+use tokio::time::{Duration, Instant};
 
 use sophia_core::{errno, errno_new};
+use sophia_core::command::{CommandType, StreamHeader};
 use sophia_core::consts::code;
 use sophia_core::errors::Errno::ConnectionClosed;
 use sophia_core::errors::Result;
@@ -15,6 +16,10 @@ use sophia_core::model::{Request, Response};
 const MAX_SIZE: usize = 1024 * 1024;
 const KEEP_ALIVE_INTERVAL: u64 = 1;
 const IDLE_TIMEOUT: u64 = 3;
+/// datagrams carry small best effort events (typing ...), old ones are dropped when the buffer is full
+const DATAGRAM_BUFFER_SIZE: usize = 64 * 1024;
+/// max bytes of the json header that starts a unidirectional stream
+const MAX_HEADER_SIZE: usize = 4 * 1024;
 
 pub(super) const ALPN_QUIC_HTTP: &[&'static str] = &["hq-29", "quic-demo"];
 
@@ -26,6 +31,49 @@ pub struct Connection {
 #[async_trait]
 pub trait RequestCallback: Send + Sync + Clone + 'static {
     async fn handle_request(&self, request: Request) -> Result<Response>;
+
+    /// called once a request stream has been read, handled and answered
+    fn stream_handled(&self, _cmd_type: CommandType, _elapsed: Duration, _success: bool) {}
+
+    /// unreliable request received as a datagram, there is no response
+    async fn handle_datagram(&self, _request: Request) {}
+
+    /// unidirectional stream opened by the peer, `recv` holds the content after the header
+    async fn handle_stream(&self, _remote: String, _header: StreamHeader, _recv: UniRecv) {}
+}
+
+
+/// sending half of a unidirectional stream, the header is already written
+pub struct UniSend {
+    send: quinn::SendStream,
+}
+
+impl UniSend {
+    pub async fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.send.write_all(data).await
+            .map_err(|e| errno_new!("write stream failed = {}", e))
+    }
+
+    pub async fn finish(mut self) -> Result<()> {
+        self.send.finish().await
+            .map_err(|e| errno_new!("failed to shutdown stream: {}", e))
+    }
+}
+
+
+/// receiving half of a unidirectional stream, positioned after the header
+pub struct UniRecv {
+    recv: quinn::RecvStream,
+}
+
+impl UniRecv {
+    /// next chunk of at most `max` bytes, `None` once the peer finished the stream
+    pub async fn read_chunk(&mut self, max: usize) -> Result<Option<Vec<u8>>> {
+        let chunk = self.recv.read_chunk(max, true).await
+            .map_err(|e| errno_new!("read stream failed = {}", e))?;
+
+        Ok(chunk.map(|c| c.bytes.to_vec()))
+    }
 }
 
 #[async_trait]
@@ -43,10 +91,20 @@ impl Connection {
         Connection { conn: Arc::new(conn) }
     }
 
+    /// id of the connection, unique for the lifetime of the endpoint
+    pub fn id(&self) -> usize {
+        self.conn.stable_id()
+    }
+
     pub fn remote_address(&self) -> String {
         self.conn.remote_address().to_string()
     }
 
+    /// transport statistics of the connection (rtt, lost packets ...)
+    pub fn stats(&self) -> quinn_proto::ConnectionStats {
+        self.conn.stats()
+    }
+
     pub async fn closed(&self) {
         let _ = self.conn.close(0u32.into(), b"");
     }
@@ -94,12 +152,14 @@ impl Connection {
             let stream = self.accept_stream().await?;
             let res = Self::handle(self.clone(), stream.0,
                                    stream.1, callback);
+            let span = info_span!("stream", conn = self.id(), remote = %self.remote_address(),
+                                  cmd = tracing::field::Empty);
 
             tokio::spawn(async move {
                 if let Err(e) = res.await {
                     error!("handle_request failed = {}", e);
                 }
-            });
+            }.instrument(span));
         }
     }
 
@@ -107,17 +167,99 @@ impl Connection {
     async fn handle(conn: Connection, send: quinn::SendStream,
                     recv: quinn::RecvStream, callback: impl RequestCallback) -> Result<()>
     {
+        let start = Instant::now();
         let request = conn.read_request(recv).await?;
+        let cmd_type = request.cmd_type;
+        Span::current().record("cmd", tracing::field::display(cmd_type));
         let resp = callback.handle_request(request).await;
+        let success = matches!(&resp, Ok(resp) if resp.code == code::SUCCESS);
         match resp {
             Err(e) => conn.write_response(Response::new(code::SESSION_ID_INVALID, e.to_string()), send).await?,
             Ok(resp) => conn.write_response(resp, send).await?,
         }
 
+        callback.stream_handled(cmd_type, start.elapsed(), success);
         Ok(())
     }
 
 
+    /// send a request as an unreliable datagram, it may be lost or dropped by the peer
+    pub fn send_datagram(&self, request: &Request) -> Result<()> {
+        let serialized = serde_json::to_vec(request)
+            .map_err(|e| errno_new!("encode req failed =  {}",e))?;
+
+        let max_size = self.conn.max_datagram_size()
+            .ok_or(errno_new!("peer does not accept datagrams"))?;
+        if serialized.len() > max_size {
+            return errno!("datagram too large {} > {}", serialized.len(), max_size);
+        }
+
+        self.conn.send_datagram(serialized.into())
+            .map_err(|e| errno_new!("send_datagram failed = {}", e))?;
+
+        Ok(())
+    }
+
+    pub async fn accept_datagrams(&self, callback: impl RequestCallback) -> Result<()> {
+        loop {
+            let data = match self.conn.read_datagram().await {
+                Err(quinn::ConnectionError::ApplicationClosed { .. }) => return Err(ConnectionClosed),
+                Err(e) => return errno!("read_datagram failed: {}", e),
+                Ok(data) => data,
+            };
+
+            let mut request: Request = match serde_json::from_slice(&data) {
+                Ok(request) => request,
+                Err(e) => {
+                    error!("decode datagram failed = {}", e);
+                    continue;
+                }
+            };
+            request.base.remote_add = self.remote_address();
+
+            let callback = callback.clone();
+            tokio::spawn(async move {
+                callback.handle_datagram(request).await;
+            });
+        }
+    }
+
+
+    /// open a unidirectional stream starting with `header`, framed as a big endian u32 length and json
+    pub async fn open_uni(&self, header: &StreamHeader) -> Result<UniSend> {
+        let mut send = self.conn.open_uni().await
+            .map_err(|e| errno_new!("conn.open_uni failed =  {}",e))?;
+
+        let serialized = serde_json::to_vec(header)
+            .map_err(|e| errno_new!("encode header failed =  {}",e))?;
+        send.write_all(&(serialized.len() as u32).to_be_bytes()).await
+            .map_err(|e| errno_new!("write header failed = {}", e))?;
+        send.write_all(&serialized).await
+            .map_err(|e| errno_new!("write header failed = {}", e))?;
+
+        Ok(UniSend { send })
+    }
+
+    pub async fn accept_uni_streams(&self, callback: impl RequestCallback) -> Result<()> {
+        loop {
+            let recv = match self.conn.accept_uni().await {
+                Err(quinn::ConnectionError::ApplicationClosed { .. }) => return Err(ConnectionClosed),
+                Err(e) => return errno!("accept_uni failed: {}", e),
+                Ok(recv) => recv,
+            };
+
+            let remote = self.remote_address();
+            let callback = callback.clone();
+            tokio::spawn(async move {
+                match read_header(recv).await {
+                    Ok((header, recv)) => callback.handle_stream(remote, header, recv).await,
+                    Err(e) => error!("read stream header from {} failed = {}", remote, e),
+                }
+            });
+        }
+    }
+
+
     pub async fn send(&self, request: Request) -> Result<Response> {
         let (mut send, mut recv) = self.conn.open_bi().await
             .map_err(|e| errno_new!("conn.open_bi failed =  {}",e))?;
@@ -146,10 +288,32 @@ impl Connection {
 }
 
 
+async fn read_header(mut recv: quinn::RecvStream) -> Result<(StreamHeader, UniRecv)> {
+    let mut len = [0u8; 4];
+    recv.read_exact(&mut len).await
+        .map_err(|e| errno_new!("read header failed = {}", e))?;
+
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_HEADER_SIZE {
+        return errno!("stream header too large {} > {}", len, MAX_HEADER_SIZE);
+    }
+
+    let mut data = vec![0u8; len];
+    recv.read_exact(&mut data).await
+        .map_err(|e| errno_new!("read header failed = {}", e))?;
+    let header = serde_json::from_slice(&data)
+        .map_err(|e| errno_new!("decode header failed = {}", e))?;
+
+    Ok((header, UniRecv { recv }))
+}
+
+
 pub(super) fn get_transport() -> quinn::TransportConfig {
     let mut transport = quinn::TransportConfig::default();
     transport.keep_alive_interval(Some(Duration::from_secs(KEEP_ALIVE_INTERVAL)))
-        .max_idle_timeout(Some(Duration::from_secs(IDLE_TIMEOUT).try_into().unwrap()));
+        .max_idle_timeout(Some(Duration::from_secs(IDLE_TIMEOUT).try_into().unwrap()))
+        .datagram_receive_buffer_size(Some(DATAGRAM_BUFFER_SIZE))
+        .datagram_send_buffer_size(DATAGRAM_BUFFER_SIZE);
 
     transport
 }
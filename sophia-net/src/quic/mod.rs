@@ -1,6 +1,7 @@
 pub use client::Client;
 pub use connection::Connection;
 pub use connection::RequestCallback;
+pub use connection::{UniRecv, UniSend};
 pub use server::Server;
 
 mod client;
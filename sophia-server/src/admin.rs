@@ -0,0 +1,159 @@
+use std::fs::{DirBuilder, Permissions};
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+use std::path::Path;
+
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tracing::{error, info};
+
+use sophia_core::admin::{AdminCommand, AdminResponse, AdminResult, ChatSummary};
+use sophia_core::consts::code;
+use sophia_core::{errno, errno_new};
+use sophia_core::errors::Result;
+
+use crate::controller::Server;
+use crate::logging::LogLevel;
+use crate::service::{moderation, push, user};
+
+const ADMIN_OPERATOR: &str = "admin";
+
+/// Serve the admin control channel on a unix socket, only the user
+/// running the server can connect to it.
+pub async fn run(server: Server, log_level: LogLevel, path: String) -> Result<()> {
+    let listener = bind(Path::new(&path))?;
+    info!("admin socket = {}", path);
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let server = server.clone();
+        let log_level = log_level.clone();
+
+        tokio::spawn(async move {
+            if let Err(e) = serve(server, log_level, stream).await {
+                error!("admin connection failed = {}", e);
+            }
+        });
+    }
+}
+
+
+/// bind in a directory only we can enter, so nobody can connect before the socket is restricted to us,
+/// then move the socket to `path`
+fn bind(path: &Path) -> Result<UnixListener> {
+    match std::fs::symlink_metadata(path) {
+        Ok(meta) if !meta.file_type().is_socket() => return errno!("{} exists and is not a socket", path.display()),
+        Ok(_) if std::os::unix::net::UnixStream::connect(path).is_ok() => {
+            return errno!("admin socket {} is used by another server", path.display());
+        }
+        // a stale socket is left behind if the server was not shut down cleanly, the rename replaces it
+        _ => (),
+    }
+
+    let parent = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let private = parent.join(format!(".admin-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&private);
+    DirBuilder::new().mode(0o700).create(&private)?;
+
+    let tmp = private.join("admin.sock");
+    let res = bind_private(&tmp, path);
+    let _ = std::fs::remove_file(&tmp);
+    let _ = std::fs::remove_dir(&private);
+
+    res
+}
+
+fn bind_private(tmp: &Path, path: &Path) -> Result<UnixListener> {
+    let listener = UnixListener::bind(tmp)
+        .map_err(|e| errno_new!("bind admin socket {} failed = {}", path.display(), e))?;
+    std::fs::set_permissions(tmp, Permissions::from_mode(0o600))?;
+    std::fs::rename(tmp, path)?;
+
+    Ok(listener)
+}
+
+
+async fn serve(server: Server, log_level: LogLevel, stream: UnixStream) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        let resp = match serde_json::from_str::<AdminCommand>(&line) {
+            Ok(cmd) => {
+                info!("admin command = {:?}", cmd);
+                handle(&server, &log_level, cmd).await
+                    .unwrap_or_else(|e| AdminResponse::new(code::INTERNAL_ERROR, e.to_string()))
+            }
+            Err(e) => AdminResponse::new(code::INTERNAL_ERROR, format!("invalid admin command: {}", e)),
+        };
+
+        let mut data = serde_json::to_vec(&resp)?;
+        data.push(b'\n');
+        writer.write_all(&data).await?;
+    }
+
+    Ok(())
+}
+
+
+async fn handle(s: &Server, log_level: &LogLevel, cmd: AdminCommand) -> Result<AdminResponse> {
+    let resp = match cmd {
+        AdminCommand::ListChats => {
+            let mut chats: Vec<ChatSummary> = s.repo.chat.list().await
+                .into_iter()
+                .map(|(chat_id, users)| ChatSummary { chat_id, user_count: users.len() })
+                .collect();
+            chats.sort_by_key(|c| c.chat_id);
+
+            AdminResponse::success(Some(AdminResult::Chats(chats)))
+        }
+        AdminCommand::ListUsers { chat_id } => {
+            let mut users: Vec<_> = s.repo.chat.get(chat_id).await?.into_values().collect();
+            users.sort_by_key(|u| u.login_time);
+
+            AdminResponse::success(Some(AdminResult::Users(users)))
+        }
+        AdminCommand::ListSessions => {
+            let mut users: Vec<_> = s.repo.session.list_sessions().await.into_values().collect();
+            users.sort_by_key(|u| u.login_time);
+
+            AdminResponse::success(Some(AdminResult::Users(users)))
+        }
+        AdminCommand::Kick { chat_id, user_name } => {
+            match user::find_by_name(s, chat_id, &user_name).await? {
+                Some(target) => {
+                    moderation::kick(s, ADMIN_OPERATOR, &target).await?;
+                    AdminResponse::success(None)
+                }
+                None => AdminResponse::new(code::USER_NOT_FOUND, format!("user {} not found", user_name)),
+            }
+        }
+        AdminCommand::Announce { chat_id, content } => {
+            let chat_ids = match chat_id {
+                Some(chat_id) => vec![chat_id],
+                None => s.repo.chat.list().await.into_iter().map(|(chat_id, _)| chat_id).collect(),
+            };
+
+            for chat_id in chat_ids {
+                push::system_notice(s, chat_id, &content).await?;
+            }
+            AdminResponse::success(None)
+        }
+        AdminCommand::MessageHistory { chat_id, id } => {
+            let msg = s.repo.message.find(chat_id, id).await?;
+            let history = s.repo.message.history(chat_id, id).await?;
+            if msg.is_none() && history.is_empty() {
+                return Ok(AdminResponse::new(code::MESSAGE_NOT_FOUND, format!("message {} not found", id)));
+            }
+
+            AdminResponse::success(Some(AdminResult::MessageHistory(msg.map(Box::new), history)))
+        }
+        AdminCommand::SetLogLevel { level } => {
+            match log_level.set(&level) {
+                Ok(()) => AdminResponse::success(None),
+                Err(e) => AdminResponse::new(code::INTERNAL_ERROR, e.to_string()),
+            }
+        }
+    };
+
+    Ok(resp)
+}
@@ -21,6 +21,11 @@ impl ConnectionManager {
         connections.get(remote_address).map(|conn| conn.clone())
     }
 
+    pub async fn list(&self) -> Vec<Connection> {
+        let connections = self.connections.read().await;
+        connections.values().cloned().collect()
+    }
+
     pub async fn put(&self, conn: Connection) {
         let mut connections = self.connections.write().await;
         connections.insert(conn.remote_address(), conn);
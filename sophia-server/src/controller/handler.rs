@@ -1,12 +1,15 @@
 use async_trait::async_trait;
+use tracing::error;
 
 use sophia_core::{errno, errno_new};
-use sophia_core::command::Command;
-use sophia_core::consts::code;
+use sophia_core::command::{Command, CommandResult};
+use sophia_core::consts::{code, MAX_REACTIONS, MAX_SEARCH_HITS};
 use sophia_core::errors::Result;
-use sophia_core::model::{Request, Response};
+use sophia_core::model::{MessageKind, Request, Response, Role, UserInfo};
 
-use crate::service::{message, push, user};
+use crate::service::{account, direct, file, inbox, message, moderation, push, read_state, topic, user, validate};
+use crate::service::file::{Reservation, MAX_PENDING_UPLOADS, OWNER_QUOTA};
+use crate::service::search::SearchQuery;
 
 use super::server::Server;
 
@@ -14,7 +17,25 @@ use super::server::Server;
 #[async_trait]
 pub trait Handler {
     async fn login_handler(s: Server, request: Request) -> Result<Response>;
+    async fn register_handler(s: Server, request: Request) -> Result<Response>;
     async fn send_message_handler(s: Server, request: Request) -> Result<Response>;
+    async fn kick_handler(s: Server, request: Request) -> Result<Response>;
+    async fn ban_handler(s: Server, request: Request) -> Result<Response>;
+    async fn unban_handler(s: Server, request: Request) -> Result<Response>;
+    async fn mute_handler(s: Server, request: Request) -> Result<Response>;
+    async fn set_role_handler(s: Server, request: Request) -> Result<Response>;
+    async fn set_status_handler(s: Server, request: Request) -> Result<Response>;
+    async fn edit_message_handler(s: Server, request: Request) -> Result<Response>;
+    async fn delete_message_handler(s: Server, request: Request) -> Result<Response>;
+    async fn react_handler(s: Server, request: Request) -> Result<Response>;
+    async fn mark_read_handler(s: Server, request: Request) -> Result<Response>;
+    async fn send_direct_message_handler(s: Server, request: Request) -> Result<Response>;
+    async fn search_messages_handler(s: Server, request: Request) -> Result<Response>;
+    async fn offer_file_handler(s: Server, request: Request) -> Result<Response>;
+    async fn download_file_handler(s: Server, request: Request) -> Result<Response>;
+    async fn join_chat_handler(s: Server, request: Request) -> Result<Response>;
+    async fn set_topic_handler(s: Server, request: Request) -> Result<Response>;
+    async fn change_nick_handler(s: Server, request: Request) -> Result<Response>;
 }
 
 
@@ -25,8 +46,11 @@ impl Handler for Server {
         let remote = request.base.remote_add;
 
         if let Command::Login(login) = request.cmd {
-            if login.user_name.len() == 0 {
-                let response = Response::new(code::LOGIN_FAILED, "user name invalid".to_string());
+            if let Some(response) = validate::user_name(&login.user_name) {
+                return Ok(response);
+            }
+
+            if let Some(response) = moderation::check_ban(&s, login.chat_id, &login.user_name, &remote).await? {
                 return Ok(response);
             }
 
@@ -37,7 +61,7 @@ impl Handler for Server {
             }
 
 
-            if !user::auth(&login).await? {
+            if !user::auth(&s, &login).await? {
                 let response = Response::new(code::LOGIN_FAILED, "password invalid".to_string());
                 return Ok(response);
             }
@@ -48,6 +72,9 @@ impl Handler for Server {
 
             push::user_online_event(&s, &user_info).await?;
             push::chat_message_list(&s, &user_info).await?;
+            read_state::push_read_states(&s, &user_info).await?;
+            topic::push_topic(&s, &user_info).await?;
+            inbox::deliver(&s, &user_info).await?;
 
             let resp = Response::success(session_id);
             return Ok(resp);
@@ -58,17 +85,60 @@ impl Handler for Server {
     }
 
 
+    /// create an account, no session is needed
+    async fn register_handler(s: Server, request: Request) -> Result<Response> {
+        if let Command::Register(registration) = request.cmd {
+            if let Some(response) = validate::user_name(&registration.user_name) {
+                return Ok(response);
+            }
+
+            if let Some(response) = validate::password(&registration.password) {
+                return Ok(response);
+            }
+
+            let user_name = registration.user_name.to_string();
+            if !account::register(&s, registration).await? {
+                let msg = format!("account {} already exists", user_name);
+                return Ok(Response::new(code::ACCOUNT_EXISTS, msg));
+            }
+
+            return Ok(Response::success("".to_string()));
+        }
+
+
+        errno!("cmd invalid!")
+    }
+
+
     /// handle client send text message request
     async fn send_message_handler(s: Server, request: Request) -> Result<Response> {
-        if let Command::SendTextMessage { msg, chat_id } = request.cmd {
-            let user = s.repo.session.get(&request.base.session_id).await?
-                .ok_or(errno_new!("session_id invalid"))?;
+        if let Command::SendTextMessage { msg, chat_id, reply_to, kind } = &request.cmd {
+            let mut user = current_user(&s, &request).await?;
 
-            if chat_id != user.chat_id {
+            if *chat_id != user.chat_id {
                 return Ok(Response::new(code::CHAT_ID_INVALID, "chat_id invalid".to_string()));
             }
 
-            message::send(&s, user, &msg).await?;
+            if !matches!(kind, MessageKind::Text | MessageKind::Emote) {
+                return Ok(Response::new(code::MESSAGE_KIND_INVALID, "clients can only send text and emotes".to_string()));
+            }
+
+            if let Some(response) = validate::message(msg) {
+                return Ok(response);
+            }
+
+            if let Some(response) = moderation::check_mute(&s, &user).await? {
+                return Ok(response);
+            }
+
+            if let Some(id) = reply_to {
+                if s.repo.message.find(user.chat_id, *id).await?.is_none() {
+                    return Ok(message_not_found(*id));
+                }
+            }
+
+            user::touch(&s, &mut user).await?;
+            message::send(&s, user, msg, *reply_to, *kind).await?;
 
 
             let resp = Response::success("".to_string());
@@ -78,5 +148,473 @@ impl Handler for Server {
 
         errno!("cmd invalid!")
     }
+
+
+    /// disconnect an online user from the chat
+    async fn kick_handler(s: Server, request: Request) -> Result<Response> {
+        if let Command::Kick { chat_id, user_name } = &request.cmd {
+            let operator = current_user(&s, &request).await?;
+            if let Some(response) = moderation::check_permission(&s, &operator, *chat_id, user_name).await? {
+                return Ok(response);
+            }
+
+            let target = user::find_by_name(&s, *chat_id, user_name).await?;
+            let target = match target {
+                Some(target) => target,
+                None => return Ok(user_not_found(user_name)),
+            };
+
+            moderation::kick(&s, &operator.name, &target).await?;
+            return Ok(Response::success("".to_string()));
+        }
+
+
+        errno!("cmd invalid!")
+    }
+
+
+    async fn ban_handler(s: Server, request: Request) -> Result<Response> {
+        if let Command::Ban { chat_id, user_name, duration } = &request.cmd {
+            let operator = current_user(&s, &request).await?;
+            if let Some(response) = moderation::check_permission(&s, &operator, *chat_id, user_name).await? {
+                return Ok(response);
+            }
+
+            if let Some(response) = duration.and_then(validate::duration) {
+                return Ok(response);
+            }
+
+            moderation::ban(&s, &operator, user_name, *duration).await?;
+            return Ok(Response::success("".to_string()));
+        }
+
+
+        errno!("cmd invalid!")
+    }
+
+
+    async fn unban_handler(s: Server, request: Request) -> Result<Response> {
+        if let Command::Unban { chat_id, user_name } = &request.cmd {
+            let operator = current_user(&s, &request).await?;
+            if let Some(response) = moderation::check_permission(&s, &operator, *chat_id, user_name).await? {
+                return Ok(response);
+            }
+
+            if !moderation::unban(&s, &operator, user_name).await? {
+                return Ok(user_not_found(user_name));
+            }
+            return Ok(Response::success("".to_string()));
+        }
+
+
+        errno!("cmd invalid!")
+    }
+
+
+    async fn mute_handler(s: Server, request: Request) -> Result<Response> {
+        if let Command::Mute { chat_id, user_name, duration } = &request.cmd {
+            let operator = current_user(&s, &request).await?;
+            if let Some(response) = moderation::check_permission(&s, &operator, *chat_id, user_name).await? {
+                return Ok(response);
+            }
+
+            if let Some(response) = validate::duration(*duration) {
+                return Ok(response);
+            }
+
+            moderation::mute(&s, &operator, user_name, *duration).await?;
+            return Ok(Response::success("".to_string()));
+        }
+
+
+        errno!("cmd invalid!")
+    }
+
+
+    /// only the owner may promote or demote moderators
+    async fn set_role_handler(s: Server, request: Request) -> Result<Response> {
+        if let Command::SetRole { chat_id, user_name, role } = &request.cmd {
+            let operator = current_user(&s, &request).await?;
+            if operator.role != Role::Owner || *role == Role::Owner {
+                return Ok(Response::new(code::PERMISSION_DENIED, "only the owner can change roles".to_string()));
+            }
+
+            if let Some(response) = moderation::check_permission(&s, &operator, *chat_id, user_name).await? {
+                return Ok(response);
+            }
+
+            let account = match user::account_of(&s, *chat_id, user_name).await? {
+                Some(account) => account,
+                None => {
+                    let msg = format!("{} is a guest, only registered accounts can have a role", user_name);
+                    return Ok(Response::new(code::PERMISSION_DENIED, msg));
+                }
+            };
+
+            moderation::set_role(&s, &operator, user_name, &account, *role).await?;
+            return Ok(Response::success("".to_string()));
+        }
+
+
+        errno!("cmd invalid!")
+    }
+
+
+    async fn set_status_handler(s: Server, request: Request) -> Result<Response> {
+        if let Command::SetStatus { chat_id, status, text } = &request.cmd {
+            let user = current_user(&s, &request).await?;
+            if *chat_id != user.chat_id {
+                return Ok(Response::new(code::CHAT_ID_INVALID, "chat_id invalid".to_string()));
+            }
+
+            if let Some(response) = validate::status_text(text) {
+                return Ok(response);
+            }
+
+            user::set_status(&s, user, *status, text).await?;
+            return Ok(Response::success("".to_string()));
+        }
+
+
+        errno!("cmd invalid!")
+    }
+
+
+    /// only the author may edit a message
+    async fn edit_message_handler(s: Server, request: Request) -> Result<Response> {
+        if let Command::EditMessage { id, content } = &request.cmd {
+            let user = current_user(&s, &request).await?;
+            let msg = match s.repo.message.find(user.chat_id, *id).await? {
+                Some(msg) => msg,
+                None => return Ok(message_not_found(*id)),
+            };
+
+            if msg.author != Some(user.author()) {
+                return Ok(Response::new(code::PERMISSION_DENIED, "you can only edit your own messages".to_string()));
+            }
+
+            if msg.file.is_some() {
+                return Ok(Response::new(code::PERMISSION_DENIED, "a shared file can not be edited".to_string()));
+            }
+
+            if let Some(response) = validate::message(content) {
+                return Ok(response);
+            }
+
+            if let Some(response) = moderation::check_mute(&s, &user).await? {
+                return Ok(response);
+            }
+
+            if !message::edit(&s, user.chat_id, *id, content).await? {
+                return Ok(message_not_found(*id));
+            }
+            return Ok(Response::success("".to_string()));
+        }
+
+
+        errno!("cmd invalid!")
+    }
+
+
+    /// the author, or a moderator outranking the author, may delete a message
+    async fn delete_message_handler(s: Server, request: Request) -> Result<Response> {
+        if let Command::DeleteMessage { id } = &request.cmd {
+            let user = current_user(&s, &request).await?;
+            let msg = match s.repo.message.find(user.chat_id, *id).await? {
+                Some(msg) => msg,
+                None => return Ok(message_not_found(*id)),
+            };
+
+            if msg.author != Some(user.author()) {
+                let author = &msg.user.user_name;
+                if let Some(response) = moderation::check_permission(&s, &user, user.chat_id, author).await? {
+                    return Ok(response);
+                }
+            }
+
+            if !message::delete(&s, user.chat_id, *id).await? {
+                return Ok(message_not_found(*id));
+            }
+            return Ok(Response::success("".to_string()));
+        }
+
+
+        errno!("cmd invalid!")
+    }
+
+
+    /// handles both `React` and `Unreact`
+    async fn react_handler(s: Server, request: Request) -> Result<Response> {
+        let (message_id, emoji, add) = match &request.cmd {
+            Command::React { message_id, emoji } => (*message_id, emoji, true),
+            Command::Unreact { message_id, emoji } => (*message_id, emoji, false),
+            _ => return errno!("cmd invalid!"),
+        };
+
+        let user = current_user(&s, &request).await?;
+        let msg = match s.repo.message.find(user.chat_id, message_id).await? {
+            Some(msg) => msg,
+            None => return Ok(message_not_found(message_id)),
+        };
+
+        if add {
+            if let Some(response) = validate::emoji(emoji) {
+                return Ok(response);
+            }
+
+            if let Some(response) = moderation::check_mute(&s, &user).await? {
+                return Ok(response);
+            }
+
+            let is_new = !msg.reactions.iter().any(|r| r.emoji == *emoji);
+            if is_new && msg.reactions.len() >= MAX_REACTIONS {
+                let resp = format!("a message can have at most {} different reactions", MAX_REACTIONS);
+                return Ok(Response::new(code::TOO_MANY_REACTIONS, resp));
+            }
+        }
+
+        if !message::react(&s, &user, message_id, emoji, add).await? {
+            return Ok(message_not_found(message_id));
+        }
+        Ok(Response::success("".to_string()))
+    }
+
+
+    async fn mark_read_handler(s: Server, request: Request) -> Result<Response> {
+        if let Command::MarkRead { chat_id, seq } = &request.cmd {
+            let user = current_user(&s, &request).await?;
+            if *chat_id != user.chat_id {
+                return Ok(Response::new(code::CHAT_ID_INVALID, "chat_id invalid".to_string()));
+            }
+
+            read_state::mark(&s, &user, *seq).await?;
+            return Ok(Response::success("".to_string()));
+        }
+
+
+        errno!("cmd invalid!")
+    }
+
+
+    async fn send_direct_message_handler(s: Server, request: Request) -> Result<Response> {
+        if let Command::SendDirectMessage { to, msg } = &request.cmd {
+            let user = current_user(&s, &request).await?;
+            if let Some(response) = validate::message(msg) {
+                return Ok(response);
+            }
+
+            if let Some(response) = moderation::check_mute(&s, &user).await? {
+                return Ok(response);
+            }
+
+            if !direct::send(&s, &user, to, msg).await? {
+                return Ok(user_not_found(to));
+            }
+            return Ok(Response::success("".to_string()));
+        }
+
+
+        errno!("cmd invalid!")
+    }
+
+
+    /// a `limit` of 0, or above `MAX_SEARCH_HITS`, returns `MAX_SEARCH_HITS` hits
+    async fn search_messages_handler(s: Server, request: Request) -> Result<Response> {
+        if let Command::SearchMessages { chat_id, query, limit, before } = &request.cmd {
+            let user = current_user(&s, &request).await?;
+            if *chat_id != user.chat_id {
+                return Ok(Response::new(code::CHAT_ID_INVALID, "chat_id invalid".to_string()));
+            }
+
+            let query = match SearchQuery::parse(query) {
+                Ok(query) => query,
+                Err(e) => return Ok(Response::new(code::SEARCH_QUERY_INVALID, e)),
+            };
+
+            let limit = match *limit {
+                0 => MAX_SEARCH_HITS,
+                limit => limit.min(MAX_SEARCH_HITS),
+            };
+            let hits = s.repo.message.search(*chat_id, &query, limit, *before).await?;
+
+            let mut resp = Response::success(format!("{} hits", hits.len()));
+            resp.data = Some(CommandResult::Messages(hits));
+            return Ok(resp);
+        }
+
+
+        errno!("cmd invalid!")
+    }
+
+
+    async fn offer_file_handler(s: Server, request: Request) -> Result<Response> {
+        if let Command::OfferFile { chat_id, name, size, hash } = &request.cmd {
+            let user = current_user(&s, &request).await?;
+            if *chat_id != user.chat_id {
+                return Ok(Response::new(code::CHAT_ID_INVALID, "chat_id invalid".to_string()));
+            }
+
+            if let Some(response) = validate::file(name, *size, hash) {
+                return Ok(response);
+            }
+
+            if let Some(response) = moderation::check_mute(&s, &user).await? {
+                return Ok(response);
+            }
+
+            let msg = match file::offer(&s, &user, name, *size, hash).await? {
+                Reservation::Accepted(id, offset) => {
+                    let mut resp = Response::success("".to_string());
+                    resp.data = Some(CommandResult::Upload { id, offset });
+                    return Ok(resp);
+                }
+                Reservation::ServerFull => "the server has no room for this file".to_string(),
+                Reservation::TooManyPending => format!("finish one of your {} unfinished uploads first", MAX_PENDING_UPLOADS),
+                Reservation::OwnerFull => format!("your files may take up at most {} MiB", OWNER_QUOTA / (1024 * 1024)),
+            };
+            return Ok(Response::new(code::QUOTA_EXCEEDED, msg));
+        }
+
+
+        errno!("cmd invalid!")
+    }
+
+
+    /// files can be downloaded by the members of the chat they were shared with
+    async fn download_file_handler(s: Server, request: Request) -> Result<Response> {
+        if let Command::DownloadFile { id, offset } = &request.cmd {
+            let user = current_user(&s, &request).await?;
+            let stored = s.repo.file.get(*id).await?
+                .filter(|f| f.complete && f.chat_id == user.chat_id);
+            let stored = match stored {
+                Some(stored) if *offset <= stored.offer.size => stored,
+                _ => return Ok(Response::new(code::FILE_NOT_FOUND, format!("file {} not found", id))),
+            };
+
+            let (s, offset) = (s.clone(), *offset);
+            tokio::spawn(async move {
+                if let Err(e) = file::send(&s, &user.address, stored, offset).await {
+                    error!("send file to {} failed = {}", user.address, e);
+                }
+            });
+            return Ok(Response::success("".to_string()));
+        }
+
+
+        errno!("cmd invalid!")
+    }
+
+
+    /// switch chats without a new login, the checks are the ones of the login
+    async fn join_chat_handler(s: Server, request: Request) -> Result<Response> {
+        if let Command::JoinChat { chat_id } = &request.cmd {
+            let user = current_user(&s, &request).await?;
+            if *chat_id == user.chat_id {
+                return Ok(Response::new(code::CHAT_ID_INVALID, format!("you are already in chat {}", chat_id)));
+            }
+
+            if let Some(response) = moderation::check_ban(&s, *chat_id, &user.name, &user.address).await? {
+                return Ok(response);
+            }
+
+            if !user::check_user_name(&s, &user.name, *chat_id).await? {
+                let msg = format!("username {} already exists in chat {}", &user.name, chat_id);
+                return Ok(Response::new(code::USER_NAME_DUPLICATE_ERROR, msg));
+            }
+
+            let user_info = user::join(&s, user, *chat_id).await?;
+
+            push::user_online_event(&s, &user_info).await?;
+            push::chat_message_list(&s, &user_info).await?;
+            read_state::push_read_states(&s, &user_info).await?;
+            topic::push_topic(&s, &user_info).await?;
+            inbox::deliver(&s, &user_info).await?;
+
+            return Ok(Response::success("".to_string()));
+        }
+
+
+        errno!("cmd invalid!")
+    }
+
+
+    /// moderators and the owner may set the topic
+    async fn set_topic_handler(s: Server, request: Request) -> Result<Response> {
+        if let Command::SetTopic { chat_id, topic } = &request.cmd {
+            let user = current_user(&s, &request).await?;
+            if *chat_id != user.chat_id {
+                return Ok(Response::new(code::CHAT_ID_INVALID, "chat_id invalid".to_string()));
+            }
+
+            if let Some(response) = validate::topic(topic) {
+                return Ok(response);
+            }
+
+            if user.role == Role::Member {
+                return Ok(Response::new(code::PERMISSION_DENIED, "only moderators can set the topic".to_string()));
+            }
+
+            topic::set(&s, &user, topic).await?;
+            return Ok(Response::success("".to_string()));
+        }
+
+
+        errno!("cmd invalid!")
+    }
+
+
+    /// the new name is checked like at login, registered names can only be taken by logging in
+    async fn change_nick_handler(s: Server, request: Request) -> Result<Response> {
+        if let Command::ChangeNick { user_name } = &request.cmd {
+            let user = current_user(&s, &request).await?;
+            if let Some(response) = validate::user_name(user_name) {
+                return Ok(response);
+            }
+
+            if *user_name == user.name {
+                return Ok(Response::new(code::USER_NAME_DUPLICATE_ERROR, format!("you are already {}", user_name)));
+            }
+
+            if let Some(response) = moderation::check_ban(&s, user.chat_id, user_name, &user.address).await? {
+                return Ok(response);
+            }
+
+            // a new name must not shake off a mute
+            if let Some(response) = moderation::check_mute(&s, &user).await? {
+                return Ok(response);
+            }
+
+            if !user::check_user_name(&s, user_name, user.chat_id).await? {
+                let msg = format!("username {} already exists, please choose a different username", user_name);
+                return Ok(Response::new(code::USER_NAME_DUPLICATE_ERROR, msg));
+            }
+
+            if account::is_registered(&s, user_name).await? {
+                let msg = format!("{} is a registered account, log in with its password to use it", user_name);
+                return Ok(Response::new(code::PERMISSION_DENIED, msg));
+            }
+
+            user::rename(&s, user, user_name).await?;
+            return Ok(Response::success("".to_string()));
+        }
+
+
+        errno!("cmd invalid!")
+    }
+}
+
+
+async fn current_user(s: &Server, request: &Request) -> Result<UserInfo> {
+    let user = s.repo.session.get(&request.base.session_id).await?
+        .ok_or(errno_new!("session_id invalid"))?;
+
+    Ok(user)
+}
+
+fn user_not_found(user_name: &str) -> Response {
+    Response::new(code::USER_NOT_FOUND, format!("user {} not found", user_name))
 }
 
+fn message_not_found(id: i64) -> Response {
+    Response::new(code::MESSAGE_NOT_FOUND, format!("message {} not found", id))
+}
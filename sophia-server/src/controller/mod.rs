@@ -2,6 +2,7 @@ mod conn_manager;
 mod handler;
 mod server;
 mod caller;
+mod rate_limiter;
 
 
 pub use server::Server;
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::Mutex;
+
+/// requests a single session may burst / sustain per second
+pub const SESSION_BURST: u32 = 10;
+pub const SESSION_RATE: f64 = 2.0;
+
+/// requests a single remote ip may burst / sustain per second,
+/// shared by every connection (and every login attempt) from that ip
+pub const IP_BURST: u32 = 50;
+pub const IP_RATE: f64 = 20.0;
+
+/// typing events a single session may send, clients send one every few seconds
+pub const TYPING_BURST: u32 = 3;
+pub const TYPING_RATE: f64 = 1.0;
+
+/// how often the buckets that refilled completely are dropped
+pub const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Debug)]
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(capacity: f64, now: Instant) -> Self {
+        TokenBucket { tokens: capacity, last_refill: now }
+    }
+
+    fn refill(&mut self, capacity: f64, rate: f64, now: Instant) {
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(capacity);
+        self.last_refill = now;
+    }
+}
+
+/// Token bucket rate limiter keyed by an arbitrary string (session id, remote ip ...)
+#[derive(Clone)]
+pub struct RateLimiter {
+    capacity: f64,
+    rate: f64,
+    buckets: Arc<Mutex<HashMap<String, TokenBucket>>>,
+}
+
+impl RateLimiter {
+    pub fn new(burst: u32, per_second: f64) -> Self {
+        RateLimiter {
+            capacity: burst as f64,
+            rate: per_second,
+            buckets: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// take one token for `key`, on failure returns how long until a token is available
+    pub async fn check(&self, key: &str) -> std::result::Result<(), Duration> {
+        self.check_at(key, Instant::now()).await
+    }
+
+    async fn check_at(&self, key: &str, now: Instant) -> std::result::Result<(), Duration> {
+        let mut buckets = self.buckets.lock().await;
+        let bucket = buckets.entry(key.to_string())
+            .or_insert_with(|| TokenBucket::new(self.capacity, now));
+        bucket.refill(self.capacity, self.rate, now);
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+
+        let wait = (1.0 - bucket.tokens) / self.rate;
+        Err(Duration::from_secs_f64(wait))
+    }
+
+    /// drop the buckets that refilled completely, a new bucket starts out full anyway
+    pub async fn sweep(&self) {
+        self.sweep_at(Instant::now()).await
+    }
+
+    async fn sweep_at(&self, now: Instant) {
+        let (capacity, rate) = (self.capacity, self.rate);
+        self.buckets.lock().await.retain(|_, bucket| {
+            bucket.refill(capacity, rate, now);
+            bucket.tokens < capacity
+        });
+    }
+
+    pub async fn remove(&self, key: &str) {
+        let mut buckets = self.buckets.lock().await;
+        buckets.remove(key);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::RateLimiter;
+
+    #[tokio::test]
+    async fn burst_then_wait() {
+        let limiter = RateLimiter::new(3, 2.0);
+        let now = Instant::now();
+        for _ in 0..3 {
+            assert_eq!(limiter.check_at("a", now).await, Ok(()));
+        }
+        assert_eq!(limiter.check_at("a", now).await, Err(Duration::from_millis(500)));
+        // other keys have buckets of their own
+        assert_eq!(limiter.check_at("b", now).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn tokens_refill_over_time() {
+        let limiter = RateLimiter::new(2, 2.0);
+        let now = Instant::now();
+        limiter.check_at("a", now).await.unwrap();
+        limiter.check_at("a", now).await.unwrap();
+
+        let later = now + Duration::from_millis(250);
+        assert_eq!(limiter.check_at("a", later).await, Err(Duration::from_millis(250)));
+        let later = now + Duration::from_millis(500);
+        assert_eq!(limiter.check_at("a", later).await, Ok(()));
+        assert!(limiter.check_at("a", later).await.is_err());
+
+        // never more than the burst
+        let much_later = now + Duration::from_secs(60);
+        for _ in 0..2 {
+            assert_eq!(limiter.check_at("a", much_later).await, Ok(()));
+        }
+        assert!(limiter.check_at("a", much_later).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn sweep_drops_full_buckets() {
+        let limiter = RateLimiter::new(2, 1.0);
+        let now = Instant::now();
+        limiter.check_at("drained", now).await.unwrap();
+        limiter.check_at("drained", now).await.unwrap();
+        limiter.check_at("refilled", now).await.unwrap();
+
+        limiter.sweep_at(now + Duration::from_secs(1)).await;
+        let buckets = limiter.buckets.lock().await;
+        assert!(buckets.contains_key("drained"));
+        assert!(!buckets.contains_key("refilled"));
+    }
+}
@@ -1,20 +1,23 @@
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
 use futures_util::future::BoxFuture;
-use log::{error, info};
+use tracing::{debug, error, info, info_span, Instrument, warn};
 
-use sophia_core::command::CommandType;
+use sophia_core::command::{Command, CommandResult, CommandType, StreamHeader};
 use sophia_core::consts::code;
-use sophia_core::errno_new;
+use sophia_core::{errno, errno_new};
 use sophia_core::errors::Result;
 use sophia_core::model::{Request, Response};
 use sophia_net::quic;
 
 use crate::controller::conn_manager::ConnectionManager;
-use crate::service::{ChatRepo, MessageRepo, SessionRepo, user};
-use crate::service::push;
+use crate::controller::rate_limiter::{self, RateLimiter};
+use crate::metrics::Metrics;
+use crate::service::{AccountRepo, ChatRepo, FileRepo, InboxRepo, MessageRepo, ModerationRepo, ReadStateRepo, SessionRepo, TopicRepo, user};
+use crate::service::{file, push};
 
 use super::handler::Handler;
 
@@ -23,6 +26,10 @@ pub struct Server {
     callbacks: HashMap<CommandType, Callback>,
     pub cons: ConnectionManager,
     pub repo: Repository,
+    pub metrics: Metrics,
+    session_limiter: RateLimiter,
+    ip_limiter: RateLimiter,
+    typing_limiter: RateLimiter,
 }
 
 #[derive(Clone)]
@@ -30,6 +37,12 @@ pub struct Repository {
     pub session: Arc<dyn SessionRepo>,
     pub chat: Arc<dyn ChatRepo>,
     pub message: Arc<dyn MessageRepo>,
+    pub moderation: Arc<dyn ModerationRepo>,
+    pub read_state: Arc<dyn ReadStateRepo>,
+    pub account: Arc<dyn AccountRepo>,
+    pub inbox: Arc<dyn InboxRepo>,
+    pub file: Arc<dyn FileRepo>,
+    pub topic: Arc<dyn TopicRepo>,
 }
 
 type Callback = Arc<dyn Send + Sync + Fn(Server, Request) -> BoxFuture<'static, Result<Response>>>;
@@ -44,7 +57,11 @@ impl Server {
     pub fn new(repo: Repository) -> Self {
         let callbacks = HashMap::new();
         let cons = ConnectionManager::new();
-        let mut s = Self { callbacks, cons, repo };
+        let session_limiter = RateLimiter::new(rate_limiter::SESSION_BURST, rate_limiter::SESSION_RATE);
+        let ip_limiter = RateLimiter::new(rate_limiter::IP_BURST, rate_limiter::IP_RATE);
+        let typing_limiter = RateLimiter::new(rate_limiter::TYPING_BURST, rate_limiter::TYPING_RATE);
+        let metrics = Metrics::new();
+        let mut s = Self { callbacks, cons, repo, metrics, session_limiter, ip_limiter, typing_limiter };
         s.register_command();
 
         s
@@ -53,7 +70,37 @@ impl Server {
 
     fn register_command(&mut self) {
         self.register(CommandType::Login, async_function!(Server::login_handler));
+        self.register(CommandType::Register, async_function!(Server::register_handler));
         self.register(CommandType::SendMessage, async_function!(Server::send_message_handler));
+        self.register(CommandType::Kick, async_function!(Server::kick_handler));
+        self.register(CommandType::Ban, async_function!(Server::ban_handler));
+        self.register(CommandType::Unban, async_function!(Server::unban_handler));
+        self.register(CommandType::Mute, async_function!(Server::mute_handler));
+        self.register(CommandType::SetRole, async_function!(Server::set_role_handler));
+        self.register(CommandType::SetStatus, async_function!(Server::set_status_handler));
+        self.register(CommandType::EditMessage, async_function!(Server::edit_message_handler));
+        self.register(CommandType::DeleteMessage, async_function!(Server::delete_message_handler));
+        self.register(CommandType::React, async_function!(Server::react_handler));
+        self.register(CommandType::Unreact, async_function!(Server::react_handler));
+        self.register(CommandType::MarkRead, async_function!(Server::mark_read_handler));
+        self.register(CommandType::SendDirectMessage, async_function!(Server::send_direct_message_handler));
+        self.register(CommandType::SearchMessages, async_function!(Server::search_messages_handler));
+        self.register(CommandType::OfferFile, async_function!(Server::offer_file_handler));
+        self.register(CommandType::DownloadFile, async_function!(Server::download_file_handler));
+        self.register(CommandType::JoinChat, async_function!(Server::join_chat_handler));
+        self.register(CommandType::SetTopic, async_function!(Server::set_topic_handler));
+        self.register(CommandType::ChangeNick, async_function!(Server::change_nick_handler));
+    }
+
+    /// keep the rate limiters from growing with every session and ip ever seen
+    pub async fn sweep_rate_limiters(self) {
+        let mut interval = tokio::time::interval(rate_limiter::SWEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            for limiter in [&self.session_limiter, &self.ip_limiter, &self.typing_limiter] {
+                limiter.sweep().await;
+            }
+        }
     }
 
     pub fn get_callback(&self, cmd_type: CommandType) -> Result<Callback> {
@@ -82,6 +129,8 @@ impl Server {
 
         // 4. remove user session
         self.repo.session.remove(remote_addr).await?;
+        self.session_limiter.remove(&user.session_id).await;
+        self.typing_limiter.remove(&user.session_id).await;
 
         // 5. notification user login out
         let res = push::user_offline_event(self, &user).await;
@@ -94,7 +143,7 @@ impl Server {
 
 
     async fn auth_session(&self, request: &Request) -> Result<Response> {
-        if request.cmd_type == CommandType::Login {
+        if is_sessionless(request.cmd_type) {
             return Ok(Response::success("ok".to_string()));
         }
 
@@ -109,60 +158,128 @@ impl Server {
             }
         }
     }
+
+
+    /// per remote ip limit, applied before the session is checked so that
+    /// floods of logins or forged session ids are throttled as well
+    async fn check_ip_rate(&self, request: &Request) -> Option<Response> {
+        let ip = user::remote_ip(&request.base.remote_add);
+        self.ip_limiter.check(&ip).await.err().map(rate_limited)
+    }
+
+
+    async fn check_session_rate(&self, request: &Request) -> Option<Response> {
+        if is_sessionless(request.cmd_type) {
+            return None;
+        }
+
+        self.session_limiter.check(&request.base.session_id).await.err().map(rate_limited)
+    }
+}
+
+
+/// commands sent before there is a session to check
+fn is_sessionless(cmd_type: CommandType) -> bool {
+    matches!(cmd_type, CommandType::Login | CommandType::Register)
+}
+
+
+fn rate_limited(retry_after: Duration) -> Response {
+    let millis = retry_after.as_millis().max(1) as u64;
+    let mut resp = Response::new(code::RATE_LIMITED,
+                                 format!("too many requests, retry after {} ms", millis));
+    resp.data = Some(CommandResult::RetryAfter(millis));
+
+    resp
 }
 
 
 #[async_trait]
 impl quic::RequestCallback for Server {
     async fn handle_request(&self, request: Request) -> Result<Response> {
-        let result = self.auth_session(&request).await?;
-        if result.code != 0 {
-            error!("[{}]: receive invalid session request = {:?} ",
-                    request.base.remote_add, request);
+        let user = match &request.cmd {
+            Command::Login(login) => Some(login.user_name.to_string()),
+            Command::Register(registration) => Some(registration.user_name.to_string()),
+            _ => self.repo.session.get(&request.base.session_id).await?.map(|u| u.name),
+        };
+        let span = info_span!("request", session = %request.base.redacted_session(),
+                              user = user.as_deref().unwrap_or_default(), cmd = %request.cmd_type);
+
+        self.dispatch(request).instrument(span).await
+    }
 
-            return Ok(result);
-        }
+    fn stream_handled(&self, cmd_type: CommandType, elapsed: Duration, success: bool) {
+        self.metrics.request(cmd_type, elapsed, success);
+    }
 
-        let callback = self.get_callback(request.cmd_type)?;
-        let resp = callback(self.clone(), request.clone()).await;
-        info!("[{}]: receive request = {:?} , send response = {:?}",
-                    request.base.remote_add, request, resp);
+    async fn handle_datagram(&self, request: Request) {
+        if let Err(e) = self.typing(request).await {
+            debug!(err = %e, "drop datagram");
+        }
+    }
 
-        let mut debug_server = self.clone();
-        debug_server_info(&mut debug_server).await;
-        resp
+    async fn handle_stream(&self, remote: String, header: StreamHeader, recv: quic::UniRecv) {
+        file::handle_stream(self, &remote, header, recv).await
     }
 }
 
 
-async fn debug_server_info(s: &mut Server) {
-    let chat_to_users_list = s.repo.chat.list().await;
-    let mut info_str = String::new();
+impl Server {
+    /// datagrams are best effort, invalid or throttled ones are dropped silently
+    async fn typing(&self, request: Request) -> Result<()> {
+        let chat_id = match request.cmd {
+            Command::Typing { chat_id } => chat_id,
+            _ => return errno!("cmd {} not allowed in datagram", request.cmd_type),
+        };
 
-    info_str.push_str("\nchat list :".as_ref());
+        let session_id = &request.base.session_id;
+        user::check_session(self, session_id, &request.base.remote_add).await?;
+        if self.typing_limiter.check(session_id).await.is_err() {
+            return errno!("typing rate limited");
+        }
 
-    for (chat_id, user_map) in chat_to_users_list {
-        info_str.push_str(format!("\n chat_id = {}", chat_id).as_str());
-        for (remote, user_info) in user_map.iter() {
-            info_str.push_str(format!("\n\t user: {}-{} ", remote, &user_info.name).as_str());
+        let user = self.repo.session.get(session_id).await?
+            .ok_or(errno_new!("session {} not found", request.base.redacted_session()))?;
+        if user.chat_id != chat_id {
+            return errno!("chat_id invalid");
         }
-        info_str.push_str("\n".as_ref());
+
+        push::user_typing(self, &user).await
     }
+}
+
 
-    // info_str.push_str("\nsession list :".as_ref());
-    //
-    //
-    // let list = s.repo.session.list_sessions().await;
-    // for (session, user) in list {
-    //     info_str.push_str(format!("\n\t session = {}, user = {:?} \n", session, user).as_str());
-    // }
-    //
-    // info_str.push_str("\nremote list :".as_ref());
-    //
-    // let list = s.repo.session.list_remote().await;
-    // for (session, user) in list {
-    //     info_str.push_str(format!("\n\t remote = {}, user = {:?} \n", session, user).as_str());
-    // }
-
-    info!("debug info  ={} ",info_str.as_str());
-}
\ No newline at end of file
+impl Server {
+    async fn dispatch(&self, request: Request) -> Result<Response> {
+        if let Some(resp) = self.check_ip_rate(&request).await {
+            warn!("ip rate limited");
+            return Ok(resp);
+        }
+
+        let result = self.auth_session(&request).await?;
+        if result.code != 0 {
+            error!(reason = %result.msg, "receive invalid session request");
+
+            return Ok(result);
+        }
+
+        if let Some(resp) = self.check_session_rate(&request).await {
+            warn!("session rate limited");
+            return Ok(resp);
+        }
+
+        let callback = self.get_callback(request.cmd_type)?;
+        let resp = callback(self.clone(), request.clone()).await;
+        if request.cmd_type == CommandType::Login {
+            self.metrics.login(resp.as_ref().map_or(code::INTERNAL_ERROR, |r| r.code));
+        }
+
+        match &resp {
+            Ok(r) => info!(code = r.code, msg = %r.msg, "request handled"),
+            Err(e) => error!(err = %e, "request failed"),
+        }
+        debug!(?request, "request detail");
+
+        resp
+    }
+}
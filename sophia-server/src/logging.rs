@@ -0,0 +1,56 @@
+use std::fs::File;
+use std::sync::Mutex;
+
+use tracing_subscriber::{EnvFilter, fmt, Registry, reload};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+use sophia_core::errno_new;
+use sophia_core::errors::Result;
+
+const DEFAULT_FILTER: &str = "info";
+
+/// Handle to change the log filter of the running server.
+#[derive(Clone)]
+pub struct LogLevel {
+    handle: reload::Handle<EnvFilter, Registry>,
+}
+
+impl LogLevel {
+    /// `directives` uses the `RUST_LOG` syntax, e.g. `debug` or `info,sophia_net=trace`
+    pub fn set(&self, directives: &str) -> Result<()> {
+        let filter = EnvFilter::try_new(directives)
+            .map_err(|e| errno_new!("invalid log level {} , err = {}", directives, e))?;
+        self.handle.reload(filter)
+            .map_err(|e| errno_new!("reload log level failed = {}", e))?;
+
+        Ok(())
+    }
+}
+
+
+/// Log to stderr, and additionally as json lines to `json_path` if given.
+/// The initial filter is read from `RUST_LOG`, defaulting to `info`.
+pub fn init(json_path: Option<&str>) -> Result<LogLevel> {
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    let (filter, handle) = reload::Layer::new(filter);
+
+    let json = match json_path {
+        Some(path) => {
+            let file = File::options().create(true).append(true).open(path)
+                .map_err(|e| errno_new!("open json log file {} failed = {}", path, e))?;
+            Some(fmt::layer().json().with_current_span(true).with_span_list(true).with_writer(Mutex::new(file)))
+        }
+        None => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_writer(std::io::stderr))
+        .with(json)
+        .try_init()
+        .map_err(|e| errno_new!("init tracing failed = {}", e))?;
+
+    Ok(LogLevel { handle })
+}
@@ -7,6 +7,9 @@ mod service;
 mod repository;
 mod controller;
 mod server;
+mod admin;
+mod metrics;
+mod logging;
 
 
 #[derive(Parser, Debug)] // requires `derive` feature
@@ -18,6 +21,24 @@ pub struct Args {
     cert: String,
     #[arg(short = 'k', long = "key", default_value = "./sophia-core/cert/cert.key")]
     key: String,
+    /// directory for persistent server state (roles, bans ...)
+    #[arg(long = "data-dir", default_value = "./sophia-data")]
+    data_dir: String,
+    /// MiB that shared files may take up in `<data-dir>/files`
+    #[arg(long = "file-quota", default_value = "1024")]
+    file_quota: u64,
+    /// days shared files are kept after they were offered
+    #[arg(long = "file-retention-days", default_value = "30")]
+    file_retention_days: u64,
+    /// admin control channel unix socket, defaults to `<data-dir>/admin.sock`
+    #[arg(long = "admin-socket")]
+    admin_socket: Option<String>,
+    /// prometheus metrics http address
+    #[arg(long = "metrics-addr", default_value = "127.0.0.1:9858")]
+    metrics_addr: String,
+    /// also write json logs to this file
+    #[arg(long = "log-json")]
+    log_json: Option<String>,
     #[arg(default_value = "quic-demo")]
     application_level_protocol: String,
 }
@@ -25,14 +46,10 @@ pub struct Args {
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    env_logger::builder()
-        .filter_level(log::LevelFilter::Info)
-        .parse_env("RUST_LOG")
-        .init();
-
     let args = Args::parse();
+    let log_level = logging::init(args.log_json.as_deref())?;
 
-    server::run(args).await?;
+    server::run(args, log_level).await?;
 
     Ok(())
 }
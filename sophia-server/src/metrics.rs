@@ -0,0 +1,206 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use prometheus::{Encoder, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec,
+                 IntGauge, IntGaugeVec, GaugeVec, Opts, Registry, TextEncoder};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{error, info};
+
+use sophia_core::command::CommandType;
+use sophia_core::errno_new;
+use sophia_core::errors::Result;
+
+use crate::controller::Server;
+
+const MAX_HTTP_REQUEST: usize = 8 * 1024;
+/// chats counted under a label of their own, messages of any other chat go to `other`
+const MAX_CHAT_LABELS: usize = 100;
+const OTHER_CHAT: &str = "other";
+
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    connected_clients: IntGauge,
+    logins: IntCounterVec,
+    messages: IntCounterVec,
+    /// chats that got a `chat_id` label so far
+    chat_labels: Arc<Mutex<HashSet<i64>>>,
+    push_duration: Histogram,
+    push_failures: IntCounter,
+    request_duration: HistogramVec,
+    quic_rtt: GaugeVec,
+    quic_lost_packets: IntGaugeVec,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new_custom(Some("sophia".to_string()), None)
+            .expect("metrics namespace is valid");
+
+        let connected_clients = IntGauge::new("connected_clients", "Number of connected QUIC clients").unwrap();
+        let logins = IntCounterVec::new(
+            Opts::new("logins_total", "Login attempts by response code"), &["code"]).unwrap();
+        let messages = IntCounterVec::new(
+            Opts::new("messages_total", "Chat messages sent by chat room"), &["chat_id"]).unwrap();
+        let push_duration = Histogram::with_opts(
+            HistogramOpts::new("push_duration_seconds", "Time to push a request to a client")).unwrap();
+        let push_failures = IntCounter::new("push_failures_total", "Failed pushes to clients").unwrap();
+        let request_duration = HistogramVec::new(
+            HistogramOpts::new("request_duration_seconds", "Time to read, handle and answer a request stream"),
+            &["cmd", "success"]).unwrap();
+        let quic_rtt = GaugeVec::new(
+            Opts::new("quic_rtt_seconds", "Current round trip time estimate per client"), &["remote"]).unwrap();
+        let quic_lost_packets = IntGaugeVec::new(
+            Opts::new("quic_lost_packets", "Packets lost on the path per client"), &["remote"]).unwrap();
+
+        registry.register(Box::new(connected_clients.clone())).unwrap();
+        registry.register(Box::new(logins.clone())).unwrap();
+        registry.register(Box::new(messages.clone())).unwrap();
+        registry.register(Box::new(push_duration.clone())).unwrap();
+        registry.register(Box::new(push_failures.clone())).unwrap();
+        registry.register(Box::new(request_duration.clone())).unwrap();
+        registry.register(Box::new(quic_rtt.clone())).unwrap();
+        registry.register(Box::new(quic_lost_packets.clone())).unwrap();
+
+        Metrics {
+            registry,
+            connected_clients,
+            logins,
+            messages,
+            chat_labels: Arc::new(Mutex::new(HashSet::new())),
+            push_duration,
+            push_failures,
+            request_duration,
+            quic_rtt,
+            quic_lost_packets,
+        }
+    }
+
+    pub fn login(&self, code: usize) {
+        self.logins.with_label_values(&[&code.to_string()]).inc();
+    }
+
+    /// clients pick the chat ids, so only the first `MAX_CHAT_LABELS` chats that exist get a series of their own
+    pub fn message(&self, chat_id: i64, exists: bool) {
+        let labelled = exists && {
+            let mut labels = self.chat_labels.lock().unwrap();
+            labels.contains(&chat_id) || (labels.len() < MAX_CHAT_LABELS && labels.insert(chat_id))
+        };
+        let label = match labelled {
+            true => chat_id.to_string(),
+            false => OTHER_CHAT.to_string(),
+        };
+        self.messages.with_label_values(&[&label]).inc();
+    }
+
+    pub fn push(&self, elapsed: Duration, success: bool) {
+        self.push_duration.observe(elapsed.as_secs_f64());
+        if !success {
+            self.push_failures.inc();
+        }
+    }
+
+    pub fn request(&self, cmd_type: CommandType, elapsed: Duration, success: bool) {
+        self.request_duration
+            .with_label_values(&[&cmd_type.to_string(), &success.to_string()])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// gauges that are sampled from the live connections when scraped
+    async fn collect_connections(&self, s: &Server) {
+        let cons = s.cons.list().await;
+        self.connected_clients.set(cons.len() as i64);
+
+        // drop the series of clients that went away since the last scrape
+        self.quic_rtt.reset();
+        self.quic_lost_packets.reset();
+        for conn in cons {
+            let remote = conn.remote_address();
+            let path = conn.stats().path;
+            self.quic_rtt.with_label_values(&[&remote]).set(path.rtt.as_secs_f64());
+            self.quic_lost_packets.with_label_values(&[&remote]).set(path.lost_packets as i64);
+        }
+    }
+
+    async fn encode(&self, s: &Server) -> Result<Vec<u8>> {
+        self.collect_connections(s).await;
+
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)
+            .map_err(|e| errno_new!("encode metrics failed = {}", e))?;
+
+        Ok(buffer)
+    }
+}
+
+
+/// Serve `GET /metrics` in the prometheus text format on `addr`.
+pub async fn run(server: Server, addr: String) -> Result<()> {
+    let listener = TcpListener::bind(&addr).await
+        .map_err(|e| errno_new!("bind metrics addr {} failed = {}", addr, e))?;
+    info!("metrics listen add = {}", addr);
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let server = server.clone();
+
+        tokio::spawn(async move {
+            if let Err(e) = serve(server, stream).await {
+                error!("metrics request failed = {}", e);
+            }
+        });
+    }
+}
+
+
+async fn serve(server: Server, mut stream: TcpStream) -> Result<()> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 || buf.len() > MAX_HTTP_REQUEST {
+            return Ok(());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+
+    let request_line = String::from_utf8_lossy(&buf);
+    let request_line = request_line.lines().next().unwrap_or_default();
+
+    let (status, content_type, body) = if request_line.starts_with("GET /metrics ") {
+        let body = server.metrics.encode(&server).await?;
+        ("200 OK", TextEncoder::new().format_type().to_string(), body)
+    } else {
+        ("404 Not Found", "text/plain".to_string(), b"not found\n".to_vec())
+    };
+
+    let header = format!("HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                         status, content_type, body.len());
+    stream.write_all(header.as_bytes()).await?;
+    stream.write_all(&body).await?;
+    stream.shutdown().await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Metrics, MAX_CHAT_LABELS, OTHER_CHAT};
+
+    #[test]
+    fn message_labels_are_bounded() {
+        let metrics = Metrics::new();
+        for chat_id in 0..MAX_CHAT_LABELS as i64 + 10 {
+            metrics.message(chat_id, true);
+        }
+        metrics.message(1, true);
+        metrics.message(-1, false);
+
+        let count = |label: &str| metrics.messages.with_label_values(&[label]).get();
+        assert_eq!(count("1"), 2);
+        assert_eq!(count(OTHER_CHAT), 11);
+        assert_eq!(metrics.chat_labels.lock().unwrap().len(), MAX_CHAT_LABELS);
+    }
+}
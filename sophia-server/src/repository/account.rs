@@ -0,0 +1,44 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+
+use sophia_core::errors::Result;
+
+use crate::repository::json_file::JsonFile;
+use crate::service::account::Account;
+use crate::service::AccountRepo;
+
+/// Registered accounts kept in a json file, by user name.
+#[derive(Clone)]
+pub struct AccountFileImpl {
+    state: JsonFile<HashMap<String, Account>>,
+}
+
+impl AccountFileImpl {
+    pub fn open(path: PathBuf) -> Result<Self> {
+        Ok(Self { state: JsonFile::open(path)? })
+    }
+}
+
+
+#[async_trait]
+impl AccountRepo for AccountFileImpl {
+    async fn get(&self, user_name: &str) -> Result<Option<Account>> {
+        let state = self.state.read().await;
+
+        Ok(state.get(user_name).cloned())
+    }
+
+    async fn create(&self, account: Account) -> Result<bool> {
+        let mut state = self.state.write().await;
+        if state.contains_key(&account.user_name) {
+            return Ok(false);
+        }
+
+        state.insert(account.user_name.to_string(), account);
+        self.state.flush(&state).await?;
+
+        Ok(true)
+    }
+}
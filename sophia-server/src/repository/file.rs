@@ -0,0 +1,270 @@
+use std::collections::{HashMap, HashSet};
+use std::io::SeekFrom;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::fs::File;
+use tokio::io::AsyncSeekExt;
+use tokio::sync::Mutex;
+
+use sophia_core::{errno, errno_new};
+use sophia_core::errors::Result;
+use sophia_core::hash::sha256_hex;
+
+use crate::repository::json_file::JsonFile;
+use crate::service::file::{Reservation, StoredFile, MAX_PENDING_UPLOADS, OWNER_QUOTA};
+use crate::service::FileRepo;
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+struct FileState {
+    last_id: i64,
+    files: HashMap<i64, StoredFile>,
+}
+
+/// File metadata kept in a json file, the contents in `<dir>/<id>` (`<id>.part` while uploading).
+#[derive(Clone)]
+pub struct FileStoreImpl {
+    dir: PathBuf,
+    /// bytes all stored files and unfinished uploads may take up together
+    quota: u64,
+    state: JsonFile<FileState>,
+    /// ids of the uploads a stream is receiving right now
+    uploading: Arc<Mutex<HashSet<i64>>>,
+}
+
+impl FileStoreImpl {
+    pub fn open(path: PathBuf, dir: PathBuf, quota: u64) -> Result<Self> {
+        std::fs::create_dir_all(&dir)?;
+
+        Ok(Self { dir, quota, state: JsonFile::open(path)?, uploading: Arc::new(Mutex::new(HashSet::new())) })
+    }
+
+    fn part_path(&self, id: i64) -> PathBuf {
+        self.dir.join(format!("{}.part", id))
+    }
+
+    fn path(&self, id: i64) -> PathBuf {
+        self.dir.join(id.to_string())
+    }
+}
+
+
+#[async_trait]
+impl FileRepo for FileStoreImpl {
+    async fn find_pending(&self, chat_id: i64, owner: &str, hash: &str, size: u64) -> Result<Option<StoredFile>> {
+        let state = self.state.read().await;
+        let file = state.files.values()
+            .find(|f| !f.complete && f.chat_id == chat_id && f.owner == owner
+                && f.offer.hash == hash && f.offer.size == size)
+            .cloned();
+
+        Ok(file)
+    }
+
+    async fn create(&self, mut file: StoredFile) -> Result<Reservation> {
+        let mut state = self.state.write().await;
+        let owned: Vec<&StoredFile> = state.files.values().filter(|f| f.owner == file.owner).collect();
+        if owned.iter().filter(|f| !f.complete).count() >= MAX_PENDING_UPLOADS {
+            return Ok(Reservation::TooManyPending);
+        }
+        if owned.iter().map(|f| f.offer.size).sum::<u64>() + file.offer.size > OWNER_QUOTA {
+            return Ok(Reservation::OwnerFull);
+        }
+        let usage: u64 = state.files.values().map(|f| f.offer.size).sum();
+        if usage + file.offer.size > self.quota {
+            return Ok(Reservation::ServerFull);
+        }
+
+        state.last_id += 1;
+        file.offer.id = state.last_id;
+        state.files.insert(file.offer.id, file.clone());
+        self.state.flush(&state).await?;
+
+        Ok(Reservation::Accepted(file.offer.id, 0))
+    }
+
+    async fn get(&self, id: i64) -> Result<Option<StoredFile>> {
+        let state = self.state.read().await;
+
+        Ok(state.files.get(&id).cloned())
+    }
+
+    async fn received(&self, id: i64) -> Result<u64> {
+        match tokio::fs::metadata(self.part_path(id)).await {
+            Ok(meta) => Ok(meta.len()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(0),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn append_at(&self, id: i64, offset: u64) -> Result<File> {
+        let mut file = File::options().create(true).truncate(false).write(true).open(self.part_path(id)).await?;
+        file.set_len(offset).await?;
+        file.seek(SeekFrom::Start(offset)).await?;
+
+        Ok(file)
+    }
+
+    async fn complete(&self, id: i64) -> Result<bool> {
+        let expected = match self.state.read().await.files.get(&id) {
+            Some(file) => file.offer.hash.to_string(),
+            None => return errno!("file {} not found", id),
+        };
+
+        // hashing takes a while for big files, the other uploads and downloads go on meanwhile
+        let part = self.part_path(id);
+        let path = part.clone();
+        let hash = tokio::task::spawn_blocking(move || sha256_hex(&path)).await
+            .map_err(|e| errno_new!("hash file failed = {}", e))??;
+
+        let mut state = self.state.write().await;
+        if !state.files.contains_key(&id) {
+            return errno!("file {} not found", id);
+        }
+        if hash != expected {
+            state.files.remove(&id);
+            self.state.flush(&state).await?;
+            tokio::fs::remove_file(&part).await?;
+            return Ok(false);
+        }
+
+        tokio::fs::rename(&part, self.path(id)).await?;
+        if let Some(file) = state.files.get_mut(&id) {
+            file.complete = true;
+        }
+        self.state.flush(&state).await?;
+
+        Ok(true)
+    }
+
+    async fn read_at(&self, id: i64, offset: u64) -> Result<File> {
+        let mut file = File::open(self.path(id)).await?;
+        file.seek(SeekFrom::Start(offset)).await?;
+
+        Ok(file)
+    }
+
+    async fn begin_upload(&self, id: i64) -> bool {
+        self.uploading.lock().await.insert(id)
+    }
+
+    async fn end_upload(&self, id: i64) {
+        self.uploading.lock().await.remove(&id);
+    }
+
+    async fn expire(&self, pending_before: i64, complete_before: i64) -> Result<usize> {
+        let mut state = self.state.write().await;
+        let expired: Vec<i64> = {
+            let uploading = self.uploading.lock().await;
+            state.files.values()
+                .filter(|f| match f.complete {
+                    true => f.created < complete_before,
+                    false => f.created < pending_before && !uploading.contains(&f.offer.id),
+                })
+                .map(|f| f.offer.id)
+                .collect()
+        };
+        if !expired.is_empty() {
+            for id in &expired {
+                state.files.remove(id);
+            }
+            self.state.flush(&state).await?;
+        }
+
+        // the bytes of the expired files, and of the ones whose metadata was lost in a crash
+        let mut entries = tokio::fs::read_dir(&self.dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let (id, complete) = match name.strip_suffix(".part") {
+                Some(id) => (id, false),
+                None => (name.as_str(), true),
+            };
+            let known = id.parse::<i64>().ok()
+                .map(|id| state.files.get(&id).is_some_and(|f| f.complete == complete));
+            if known == Some(false) {
+                tokio::fs::remove_file(entry.path()).await?;
+            }
+        }
+
+        Ok(expired.len())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use sophia_core::consts::MAX_FILE_SIZE;
+    use sophia_core::model::FileOffer;
+
+    use crate::service::file::{Reservation, StoredFile};
+    use crate::service::FileRepo;
+
+    use super::FileStoreImpl;
+
+    fn store(name: &str, quota: u64) -> (FileStoreImpl, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("sophia-file-test-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let store = FileStoreImpl::open(dir.join("files.json"), dir.join("files"), quota).unwrap();
+        (store, dir)
+    }
+
+    fn file(owner: &str, size: u64, created: i64) -> StoredFile {
+        StoredFile {
+            offer: FileOffer { id: 0, name: "a.txt".to_string(), size, hash: String::new() },
+            chat_id: 1,
+            owner: owner.to_string(),
+            created,
+            complete: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn create_limits_each_owner() {
+        let (store, dir) = store("limits", 1024 * MAX_FILE_SIZE);
+        assert_eq!(store.create(file("a", 1, 0)).await.unwrap(), Reservation::Accepted(1, 0));
+        assert_eq!(store.create(file("a", 1, 0)).await.unwrap(), Reservation::Accepted(2, 0));
+        assert_eq!(store.create(file("a", 1, 0)).await.unwrap(), Reservation::TooManyPending);
+        assert_eq!(store.create(file("b", 4 * MAX_FILE_SIZE + 1, 0)).await.unwrap(), Reservation::OwnerFull);
+        assert_eq!(store.create(file("b", 1, 0)).await.unwrap(), Reservation::Accepted(3, 0));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn create_keeps_to_the_server_quota() {
+        let (store, dir) = store("quota", 10);
+        assert_eq!(store.create(file("a", 6, 0)).await.unwrap(), Reservation::Accepted(1, 0));
+        assert_eq!(store.create(file("b", 5, 0)).await.unwrap(), Reservation::ServerFull);
+        assert_eq!(store.create(file("b", 4, 0)).await.unwrap(), Reservation::Accepted(2, 0));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn expire_drops_old_files_and_their_bytes() {
+        let (store, dir) = store("expire", 1024);
+        store.create(file("a", 1, 10)).await.unwrap();
+        store.create(file("a", 1, 100)).await.unwrap();
+        store.state.write().await.files.get_mut(&2).unwrap().complete = true;
+        std::fs::write(dir.join("files/1.part"), "x").unwrap();
+        std::fs::write(dir.join("files/2"), "x").unwrap();
+        std::fs::write(dir.join("files/9"), "lost").unwrap();
+
+        // only the lost bytes go while both are recent enough
+        assert_eq!(store.expire(0, 0).await.unwrap(), 0);
+        assert!(!dir.join("files/9").exists());
+        assert!(dir.join("files/1.part").exists() && dir.join("files/2").exists());
+
+        assert_eq!(store.expire(50, 50).await.unwrap(), 1);
+        assert!(!dir.join("files/1.part").exists());
+        assert_eq!(store.expire(50, 200).await.unwrap(), 1);
+        assert!(!dir.join("files/2").exists());
+        assert!(store.get(2).await.unwrap().is_none());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
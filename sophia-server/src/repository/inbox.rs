@@ -0,0 +1,50 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+
+use sophia_core::consts::MAX_INBOX_ITEMS;
+use sophia_core::errors::Result;
+use sophia_core::model::InboxItem;
+
+use crate::repository::json_file::JsonFile;
+use crate::service::InboxRepo;
+
+/// Items queued for offline users kept in a json file, user_name -> items oldest first.
+#[derive(Clone)]
+pub struct InboxFileImpl {
+    state: JsonFile<HashMap<String, Vec<InboxItem>>>,
+}
+
+impl InboxFileImpl {
+    pub fn open(path: PathBuf) -> Result<Self> {
+        Ok(Self { state: JsonFile::open(path)? })
+    }
+}
+
+
+#[async_trait]
+impl InboxRepo for InboxFileImpl {
+    async fn push(&self, user_name: &str, item: InboxItem) -> Result<()> {
+        let mut state = self.state.write().await;
+        let items = state.entry(user_name.to_string()).or_default();
+        items.push(item);
+        if items.len() > MAX_INBOX_ITEMS {
+            let overflow = items.len() - MAX_INBOX_ITEMS;
+            items.drain(..overflow);
+        }
+
+        self.state.flush(&state).await
+    }
+
+    async fn take(&self, user_name: &str) -> Result<Vec<InboxItem>> {
+        let mut state = self.state.write().await;
+        let items = match state.remove(user_name) {
+            Some(items) => items,
+            None => return Ok(Vec::new()),
+        };
+
+        self.state.flush(&state).await?;
+        Ok(items)
+    }
+}
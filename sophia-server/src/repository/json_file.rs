@@ -0,0 +1,58 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use sophia_core::errno_new;
+use sophia_core::errors::Result;
+
+/// State kept in memory and written through to a json file, so that it
+/// survives a server restart.
+pub struct JsonFile<T> {
+    path: PathBuf,
+    state: Arc<RwLock<T>>,
+}
+
+impl<T> Clone for JsonFile<T> {
+    fn clone(&self) -> Self {
+        Self { path: self.path.clone(), state: self.state.clone() }
+    }
+}
+
+impl<T: Serialize + DeserializeOwned + Default> JsonFile<T> {
+    /// Loads the state from `path`, starting empty if the file does not exist yet.
+    pub fn open(path: PathBuf) -> Result<Self> {
+        let state = match std::fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data)
+                .map_err(|e| errno_new!("parse {} failed = {}", path.display(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => T::default(),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self {
+            path,
+            state: Arc::new(RwLock::new(state)),
+        })
+    }
+
+    pub async fn read(&self) -> RwLockReadGuard<'_, T> {
+        self.state.read().await
+    }
+
+    /// changes are only saved by a following `flush`
+    pub async fn write(&self) -> RwLockWriteGuard<'_, T> {
+        self.state.write().await
+    }
+
+    /// write `state` to a temporary file first so a crash never leaves a truncated file behind
+    pub async fn flush(&self, state: &T) -> Result<()> {
+        let data = serde_json::to_vec_pretty(state)?;
+        let tmp = self.path.with_extension("json.tmp");
+        tokio::fs::write(&tmp, data).await?;
+        tokio::fs::rename(&tmp, &self.path).await?;
+
+        Ok(())
+    }
+}
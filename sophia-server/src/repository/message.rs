@@ -1,36 +1,88 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::sync::Arc;
 
 use async_trait::async_trait;
 use tokio::sync::RwLock;
 
 use sophia_core::errors::Result;
-use sophia_core::model::Message;
+use sophia_core::model::{Message, MessageEdit, Reaction};
 
 use crate::service::MessageRepo;
+use crate::service::search::{self, SearchQuery};
+
+/// token -> ids of the messages containing it
+type TokenIndex = HashMap<String, BTreeSet<i64>>;
+/// (chat_id, message id) -> previous versions, oldest first
+type EditHistory = HashMap<(i64, i64), Vec<MessageEdit>>;
 
 #[derive(Clone)]
 pub struct MessageMemoryImpl {
+    /// messages of a chat, ordered by id
     chat_id_to_messages: Arc<RwLock<HashMap<i64, Vec<Message>>>>,
+    /// last message id used in a chat, ids are not reused after a delete
+    chat_id_to_seq: Arc<RwLock<HashMap<i64, i64>>>,
+    edits: Arc<RwLock<EditHistory>>,
+    /// chat_id -> inverted index of the chat
+    index: Arc<RwLock<HashMap<i64, TokenIndex>>>,
 }
 
 impl MessageMemoryImpl {
     pub fn new() -> Self {
         Self {
             chat_id_to_messages: Arc::new(RwLock::new(HashMap::new())),
+            chat_id_to_seq: Arc::new(RwLock::new(HashMap::new())),
+            edits: Arc::new(RwLock::new(HashMap::new())),
+            index: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    async fn index(&self, chat_id: i64, id: i64, content: &str) {
+        let mut index = self.index.write().await;
+        let chat_index = index.entry(chat_id).or_default();
+        for token in search::tokens(content) {
+            chat_index.entry(token).or_default().insert(id);
+        }
+    }
+
+    async fn unindex(&self, chat_id: i64, id: i64, content: &str) {
+        let mut index = self.index.write().await;
+        let chat_index = match index.get_mut(&chat_id) {
+            Some(chat_index) => chat_index,
+            None => return,
+        };
+
+        for token in search::tokens(content) {
+            if let Some(ids) = chat_index.get_mut(&token) {
+                ids.remove(&id);
+                if ids.is_empty() {
+                    chat_index.remove(&token);
+                }
+            }
         }
     }
 }
 
 
+fn position(messages: &[Message], id: i64) -> Option<usize> {
+    messages.binary_search_by_key(&id, |m| m.id).ok()
+}
+
+
 #[async_trait]
 impl MessageRepo for MessageMemoryImpl {
-    async fn save(&self, msg: Message) -> Result<()> {
+    async fn save(&self, mut msg: Message) -> Result<Message> {
         let mut chat_id_to_messages = self.chat_id_to_messages.write().await;
+        let mut chat_id_to_seq = self.chat_id_to_seq.write().await;
+
+        let seq = chat_id_to_seq.entry(msg.user.chat_id).or_insert(0);
+        *seq += 1;
+        msg.id = *seq;
+
         let messages = chat_id_to_messages.entry(msg.user.chat_id).or_insert(Vec::new());
-        messages.push(msg);
+        messages.push(msg.clone());
+        self.index(msg.user.chat_id, msg.id, &msg.content).await;
 
-        Ok(())
+        Ok(msg)
     }
 
     async fn get(&self, chat_id: i64) -> Result<Vec<Message>> {
@@ -42,4 +94,189 @@ impl MessageRepo for MessageMemoryImpl {
             None => Ok(Vec::new()), // Return an empty vec if chat_id is not found
         }
     }
-}
\ No newline at end of file
+
+    async fn find(&self, chat_id: i64, id: i64) -> Result<Option<Message>> {
+        let chat_id_to_messages = self.chat_id_to_messages.read().await;
+        let msg = chat_id_to_messages.get(&chat_id)
+            .and_then(|messages| position(messages, id).map(|idx| messages[idx].clone()));
+
+        Ok(msg)
+    }
+
+    async fn edit(&self, chat_id: i64, id: i64, content: &str, mentions: Vec<String>, time: i64) -> Result<Option<Message>> {
+        let mut chat_id_to_messages = self.chat_id_to_messages.write().await;
+        let msg = chat_id_to_messages.get_mut(&chat_id)
+            .and_then(|messages| position(messages, id).map(move |idx| &mut messages[idx]));
+        let msg = match msg {
+            Some(msg) => msg,
+            None => return Ok(None),
+        };
+
+        let previous = MessageEdit { time, content: std::mem::replace(&mut msg.content, content.to_string()) };
+        self.unindex(chat_id, id, &previous.content).await;
+        self.index(chat_id, id, content).await;
+        msg.mentions = mentions;
+        msg.edited_at = Some(time);
+        self.edits.write().await.entry((chat_id, id)).or_default().push(previous);
+
+        Ok(Some(msg.clone()))
+    }
+
+    async fn delete(&self, chat_id: i64, id: i64, time: i64) -> Result<bool> {
+        let mut chat_id_to_messages = self.chat_id_to_messages.write().await;
+        let messages = match chat_id_to_messages.get_mut(&chat_id) {
+            Some(messages) => messages,
+            None => return Ok(false),
+        };
+
+        match position(messages, id) {
+            Some(idx) => {
+                // the edit history is kept for the admin channel
+                let msg = messages.remove(idx);
+                self.unindex(chat_id, id, &msg.content).await;
+                let deleted = MessageEdit { time, content: msg.content };
+                self.edits.write().await.entry((chat_id, id)).or_default().push(deleted);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn last_id(&self, chat_id: i64) -> Result<i64> {
+        let chat_id_to_seq = self.chat_id_to_seq.read().await;
+
+        Ok(chat_id_to_seq.get(&chat_id).copied().unwrap_or(0))
+    }
+
+    async fn react(&self, chat_id: i64, id: i64, emoji: &str, user_name: &str, add: bool) -> Result<Option<Vec<Reaction>>> {
+        let mut chat_id_to_messages = self.chat_id_to_messages.write().await;
+        let msg = chat_id_to_messages.get_mut(&chat_id)
+            .and_then(|messages| position(messages, id).map(move |idx| &mut messages[idx]));
+        let msg = match msg {
+            Some(msg) => msg,
+            None => return Ok(None),
+        };
+
+        let idx = msg.reactions.iter().position(|r| r.emoji == emoji);
+        match (idx, add) {
+            (Some(idx), true) => {
+                let users = &mut msg.reactions[idx].users;
+                if !users.iter().any(|u| u == user_name) {
+                    users.push(user_name.to_string());
+                }
+            }
+            (None, true) => msg.reactions.push(Reaction { emoji: emoji.to_string(), users: vec![user_name.to_string()] }),
+            (Some(idx), false) => {
+                msg.reactions[idx].users.retain(|u| u != user_name);
+                if msg.reactions[idx].users.is_empty() {
+                    msg.reactions.remove(idx);
+                }
+            }
+            (None, false) => {}
+        }
+
+        Ok(Some(msg.reactions.clone()))
+    }
+
+    async fn history(&self, chat_id: i64, id: i64) -> Result<Vec<MessageEdit>> {
+        let edits = self.edits.read().await;
+
+        Ok(edits.get(&(chat_id, id)).cloned().unwrap_or_default())
+    }
+
+    async fn search(&self, chat_id: i64, query: &SearchQuery, limit: usize, before: Option<i64>) -> Result<Vec<Message>> {
+        let chat_id_to_messages = self.chat_id_to_messages.read().await;
+        let messages = match chat_id_to_messages.get(&chat_id) {
+            Some(messages) => messages,
+            None => return Ok(Vec::new()),
+        };
+        let before = before.unwrap_or(i64::MAX);
+
+        // walk the rarest term's ids, newest first, and check the others against the index
+        let ids: Vec<i64> = if query.terms.is_empty() {
+            messages.iter().rev().map(|m| m.id).filter(|id| *id < before).collect()
+        } else {
+            let index = self.index.read().await;
+            let empty = BTreeSet::new();
+            let chat_index = index.get(&chat_id);
+            let mut postings: Vec<&BTreeSet<i64>> = query.terms.iter()
+                .map(|term| chat_index.and_then(|i| i.get(term)).unwrap_or(&empty))
+                .collect();
+            postings.sort_by_key(|ids| ids.len());
+
+            postings[0].range(..before).rev()
+                .filter(|id| postings[1..].iter().all(|ids| ids.contains(id)))
+                .copied()
+                .collect()
+        };
+
+        let hits = ids.into_iter()
+            .filter_map(|id| position(messages, id).map(|idx| &messages[idx]))
+            .filter(|msg| query.matches(msg))
+            .take(limit)
+            .cloned()
+            .collect();
+
+        Ok(hits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sophia_core::model::{Message, MessageKind, User, UserInfo};
+
+    use crate::service::MessageRepo;
+    use crate::service::search::SearchQuery;
+
+    use super::MessageMemoryImpl;
+
+    fn message(content: &str) -> Message {
+        let user = UserInfo::new("alice".to_string(), String::new(), String::new(), 1, 0);
+        Message {
+            id: 0,
+            user: User::from_user_info(&user),
+            time: 0,
+            content: content.to_string(),
+            reply_to: None,
+            edited_at: None,
+            reactions: Vec::new(),
+            mentions: Vec::new(),
+            file: None,
+            kind: MessageKind::Text,
+            author: None,
+        }
+    }
+
+    async fn ids(repo: &MessageMemoryImpl, query: &str, before: Option<i64>) -> Vec<i64> {
+        let query = SearchQuery::parse(query).unwrap();
+        repo.search(1, &query, 10, before).await.unwrap().iter().map(|m| m.id).collect()
+    }
+
+    #[tokio::test]
+    async fn search_intersects_terms_newest_first() {
+        let repo = MessageMemoryImpl::new();
+        for content in ["red apple", "green apple", "red pear", "Red apples"] {
+            repo.save(message(content)).await.unwrap();
+        }
+
+        assert_eq!(ids(&repo, "red", None).await, vec![4, 3, 1]);
+        assert_eq!(ids(&repo, "apple RED", None).await, vec![1]);
+        assert_eq!(ids(&repo, "red", Some(3)).await, vec![1]);
+        assert!(ids(&repo, "red banana", None).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn edits_and_deletes_update_the_index() {
+        let repo = MessageMemoryImpl::new();
+        repo.save(message("meet at noon")).await.unwrap();
+        repo.save(message("noon works")).await.unwrap();
+
+        repo.edit(1, 1, "meet at five", Vec::new(), 1).await.unwrap();
+        assert_eq!(ids(&repo, "noon", None).await, vec![2]);
+        assert_eq!(ids(&repo, "five", None).await, vec![1]);
+
+        repo.delete(1, 2, 2).await.unwrap();
+        assert!(ids(&repo, "noon", None).await.is_empty());
+        assert!(ids(&repo, "works", None).await.is_empty());
+    }
+}
@@ -1,3 +1,10 @@
 pub mod session;
 pub mod chat;
-pub mod message;
\ No newline at end of file
+pub mod message;
+pub mod moderation;
+pub mod read_state;
+pub mod account;
+pub mod inbox;
+pub mod file;
+pub mod topic;
+pub mod json_file;
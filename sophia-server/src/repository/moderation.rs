@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use sophia_core::errors::Result;
+use sophia_core::model::{Ban, Role};
+
+use crate::repository::json_file::JsonFile;
+use crate::service::ModerationRepo;
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+struct ModerationState {
+    /// chat_id -> account -> role, members are not stored
+    roles: HashMap<i64, HashMap<String, Role>>,
+    bans: HashMap<i64, Vec<Ban>>,
+    /// chat_id -> user_name -> muted until
+    mutes: HashMap<i64, HashMap<String, i64>>,
+}
+
+/// Moderation state kept in a json file, so that roles and bans survive a server restart.
+#[derive(Clone)]
+pub struct ModerationFileImpl {
+    state: JsonFile<ModerationState>,
+}
+
+impl ModerationFileImpl {
+    pub fn open(path: PathBuf) -> Result<Self> {
+        Ok(Self { state: JsonFile::open(path)? })
+    }
+}
+
+
+#[async_trait]
+impl ModerationRepo for ModerationFileImpl {
+    async fn role(&self, chat_id: i64, account: &str) -> Result<Role> {
+        let state = self.state.read().await;
+        let role = state.roles.get(&chat_id)
+            .and_then(|roles| roles.get(account))
+            .copied()
+            .unwrap_or_default();
+
+        Ok(role)
+    }
+
+    async fn set_role(&self, chat_id: i64, account: &str, role: Role) -> Result<()> {
+        let mut state = self.state.write().await;
+        let roles = state.roles.entry(chat_id).or_default();
+        if role == Role::Member {
+            roles.remove(account);
+        } else {
+            roles.insert(account.to_string(), role);
+        }
+
+        self.state.flush(&state).await
+    }
+
+    async fn claim_owner(&self, chat_id: i64, account: &str) -> Result<bool> {
+        let mut state = self.state.write().await;
+        let roles = state.roles.entry(chat_id).or_default();
+        if roles.values().any(|role| *role == Role::Owner) {
+            return Ok(false);
+        }
+
+        roles.insert(account.to_string(), Role::Owner);
+        self.state.flush(&state).await?;
+
+        Ok(true)
+    }
+
+    async fn ban(&self, chat_id: i64, ban: Ban) -> Result<()> {
+        let mut state = self.state.write().await;
+        let bans = state.bans.entry(chat_id).or_default();
+        bans.retain(|b| b.user_name != ban.user_name);
+        bans.push(ban);
+
+        self.state.flush(&state).await
+    }
+
+    async fn unban(&self, chat_id: i64, user_name: &str) -> Result<bool> {
+        let mut state = self.state.write().await;
+        let bans = state.bans.entry(chat_id).or_default();
+        let len = bans.len();
+        bans.retain(|b| b.user_name != user_name);
+        if bans.len() == len {
+            return Ok(false);
+        }
+
+        self.state.flush(&state).await?;
+        Ok(true)
+    }
+
+    async fn get_ban(&self, chat_id: i64, user_name: &str, ip: &str, now: i64) -> Result<Option<Ban>> {
+        let state = self.state.read().await;
+        let ban = state.bans.get(&chat_id)
+            .and_then(|bans| bans.iter().find(|b| {
+                b.is_active(now) && (b.user_name == user_name || (!b.ip.is_empty() && b.ip == ip))
+            }))
+            .cloned();
+
+        Ok(ban)
+    }
+
+    async fn mute(&self, chat_id: i64, user_name: &str, until: i64) -> Result<()> {
+        let mut state = self.state.write().await;
+        state.mutes.entry(chat_id).or_default().insert(user_name.to_string(), until);
+
+        self.state.flush(&state).await
+    }
+
+    async fn get_mute(&self, chat_id: i64, user_name: &str, now: i64) -> Result<Option<i64>> {
+        let state = self.state.read().await;
+        let until = state.mutes.get(&chat_id)
+            .and_then(|mutes| mutes.get(user_name))
+            .filter(|until| **until > now)
+            .copied();
+
+        Ok(until)
+    }
+}
@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+
+use sophia_core::errors::Result;
+
+use crate::repository::json_file::JsonFile;
+use crate::service::ReadStateRepo;
+
+/// chat_id -> user_name -> last read message id
+type ReadState = HashMap<i64, HashMap<String, i64>>;
+
+/// Read positions kept in a json file.
+#[derive(Clone)]
+pub struct ReadStateFileImpl {
+    state: JsonFile<ReadState>,
+}
+
+impl ReadStateFileImpl {
+    pub fn open(path: PathBuf) -> Result<Self> {
+        Ok(Self { state: JsonFile::open(path)? })
+    }
+}
+
+
+#[async_trait]
+impl ReadStateRepo for ReadStateFileImpl {
+    async fn list(&self, chat_id: i64) -> Result<HashMap<String, i64>> {
+        let state = self.state.read().await;
+
+        Ok(state.get(&chat_id).cloned().unwrap_or_default())
+    }
+
+    async fn chats_of(&self, user_name: &str) -> Result<HashMap<i64, i64>> {
+        let state = self.state.read().await;
+        let chats = state.iter()
+            .filter_map(|(chat_id, users)| users.get(user_name).map(|seq| (*chat_id, *seq)))
+            .collect();
+
+        Ok(chats)
+    }
+
+    async fn mark(&self, chat_id: i64, user_name: &str, seq: i64) -> Result<bool> {
+        let mut state = self.state.write().await;
+        let last_read = state.entry(chat_id).or_default().entry(user_name.to_string()).or_insert(0);
+        if seq <= *last_read {
+            return Ok(false);
+        }
+
+        *last_read = seq;
+        self.state.flush(&state).await?;
+
+        Ok(true)
+    }
+
+    async fn discard_past(&self, chat_id: i64, last_id: i64) -> Result<()> {
+        let mut state = self.state.write().await;
+        let users = match state.get_mut(&chat_id) {
+            Some(users) if users.values().any(|seq| *seq > last_id) => users,
+            _ => return Ok(()),
+        };
+
+        users.retain(|_, seq| *seq <= last_id);
+        self.state.flush(&state).await
+    }
+}
@@ -0,0 +1,42 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+
+use sophia_core::errors::Result;
+
+use crate::repository::json_file::JsonFile;
+use crate::service::TopicRepo;
+
+/// Chat topics kept in a json file, chat_id -> topic.
+#[derive(Clone)]
+pub struct TopicFileImpl {
+    state: JsonFile<HashMap<i64, String>>,
+}
+
+impl TopicFileImpl {
+    pub fn open(path: PathBuf) -> Result<Self> {
+        Ok(Self { state: JsonFile::open(path)? })
+    }
+}
+
+
+#[async_trait]
+impl TopicRepo for TopicFileImpl {
+    async fn get(&self, chat_id: i64) -> Result<String> {
+        let state = self.state.read().await;
+
+        Ok(state.get(&chat_id).cloned().unwrap_or_default())
+    }
+
+    async fn set(&self, chat_id: i64, topic: &str) -> Result<()> {
+        let mut state = self.state.write().await;
+        if topic.is_empty() {
+            state.remove(&chat_id);
+        } else {
+            state.insert(chat_id, topic.to_string());
+        }
+
+        self.state.flush(&state).await
+    }
+}
@@ -1,19 +1,29 @@
+use std::path::PathBuf;
 use std::sync::Arc;
 
-use log::{error, info};
+use tracing::{error, info};
 
 use sophia_core;
+use sophia_core::errno_new;
 use sophia_core::errors::Errno::ConnectionClosed;
 use sophia_core::errors::Result;
 use sophia_net::quic;
 
-use crate::Args;
+use crate::{admin, metrics, Args};
 use crate::controller::{Repository, Server};
+use crate::service::file;
+use crate::logging::LogLevel;
 use crate::repository::chat::ChatMemoryImpl;
 use crate::repository::message::MessageMemoryImpl;
+use crate::repository::moderation::ModerationFileImpl;
+use crate::repository::read_state::ReadStateFileImpl;
+use crate::repository::account::AccountFileImpl;
+use crate::repository::inbox::InboxFileImpl;
+use crate::repository::file::FileStoreImpl;
+use crate::repository::topic::TopicFileImpl;
 use crate::repository::session::SessionMemoryImpl;
 
-pub async fn run(args: Args) -> Result<()> {
+pub async fn run(args: Args, log_level: LogLevel) -> Result<()> {
     let mut quic_server = quic::Server::new();
     let quic_server = quic_server
         .with_cert_path(args.cert)
@@ -25,14 +35,39 @@ pub async fn run(args: Args) -> Result<()> {
     let listen = quic_server.listen().await?;
     info!("listen add = {}", quic_server.address());
 
-    let repo = setup_repo_impl();
+    let file_quota = args.file_quota.checked_mul(1024 * 1024)
+        .ok_or(errno_new!("file quota of {} MiB is too large", args.file_quota))?;
+    let file_retention = i64::try_from(args.file_retention_days).ok()
+        .and_then(|days| days.checked_mul(24 * 3600))
+        .ok_or(errno_new!("file retention of {} days is too long", args.file_retention_days))?;
+    let repo = setup_repo_impl(&args.data_dir, file_quota)?;
     let server = Server::new(repo);
 
+    let admin_socket = args.admin_socket
+        .unwrap_or_else(|| PathBuf::from(&args.data_dir).join("admin.sock").to_string_lossy().to_string());
+    let admin_server = server.clone();
+    tokio::spawn(async move {
+        if let Err(e) = admin::run(admin_server, log_level, admin_socket).await {
+            error!("admin channel stopped = {}", e);
+        }
+    });
+
+    tokio::spawn(file::expire(server.clone(), file_retention));
+    tokio::spawn(server.clone().sweep_rate_limiters());
+
+    let metrics_server = server.clone();
+    let metrics_addr = args.metrics_addr;
+    tokio::spawn(async move {
+        if let Err(e) = metrics::run(metrics_server, metrics_addr).await {
+            error!("metrics endpoint stopped = {}", e);
+        }
+    });
+
 
     loop {
         let conn = listen.accept().await;
         if let Err(e) = conn {
-            error!("accept error = {:?}", e);
+            error!("accept error = {}", e);
             continue;
         }
 
@@ -43,6 +78,19 @@ pub async fn run(args: Args) -> Result<()> {
         let server = server.clone();
         let remote = conn.remote_address();
 
+        let datagram_conn = conn.clone();
+        let datagram_server = server.clone();
+        tokio::spawn(async move {
+            // ends together with the connection, `accept_request` below does the clean up
+            let _ = datagram_conn.accept_datagrams(datagram_server).await;
+        });
+
+        let stream_conn = conn.clone();
+        let stream_server = server.clone();
+        tokio::spawn(async move {
+            let _ = stream_conn.accept_uni_streams(stream_server).await;
+        });
+
         tokio::spawn(async move {
             let res = conn.accept_request(server.clone()).await;
             if let Err(e) = res {
@@ -63,11 +111,22 @@ pub async fn run(args: Args) -> Result<()> {
 }
 
 
-fn setup_repo_impl() -> Repository {
-    Repository {
+fn setup_repo_impl(data_dir: &str, file_quota: u64) -> Result<Repository> {
+    let data_dir = PathBuf::from(data_dir);
+    std::fs::create_dir_all(&data_dir)?;
+
+    let repo = Repository {
         chat: Arc::new(ChatMemoryImpl::new()),
         session: Arc::new(SessionMemoryImpl::new()),
         message: Arc::new(MessageMemoryImpl::new()),
-    }
+        moderation: Arc::new(ModerationFileImpl::open(data_dir.join("moderation.json"))?),
+        read_state: Arc::new(ReadStateFileImpl::open(data_dir.join("read_state.json"))?),
+        account: Arc::new(AccountFileImpl::open(data_dir.join("accounts.json"))?),
+        inbox: Arc::new(InboxFileImpl::open(data_dir.join("inbox.json"))?),
+        file: Arc::new(FileStoreImpl::open(data_dir.join("files.json"), data_dir.join("files"), file_quota)?),
+        topic: Arc::new(TopicFileImpl::open(data_dir.join("topics.json"))?),
+    };
+
+    Ok(repo)
 }
 
@@ -0,0 +1,100 @@
+use std::num::NonZeroU32;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chrono::Utc;
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+
+use sophia_core::errno_new;
+use sophia_core::command::Registration;
+use sophia_core::errors::Result;
+
+use crate::controller::Server;
+
+const HASH_SCHEME: &str = "pbkdf2-sha256";
+const HASH_ITERATIONS: u32 = 100_000;
+const SALT_LEN: usize = 16;
+const HASH_LEN: usize = 32;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Account {
+    pub user_name: String,
+    /// `pbkdf2-sha256$<iterations>$<salt>$<hash>`, salt and hash base64 encoded
+    pub password_hash: String,
+    pub created: i64,
+}
+
+
+/// returns `false` if the name is already registered
+pub async fn register(s: &Server, registration: Registration) -> Result<bool> {
+    if s.repo.account.get(&registration.user_name).await?.is_some() {
+        return Ok(false);
+    }
+
+    let password = registration.password;
+    let password_hash = tokio::task::spawn_blocking(move || hash_password(&password)).await
+        .map_err(|e| errno_new!("hash password failed = {}", e))??;
+
+    let account = Account {
+        user_name: registration.user_name,
+        password_hash,
+        created: Utc::now().timestamp(),
+    };
+    s.repo.account.create(account).await
+}
+
+
+/// `None` if `user_name` is not registered
+pub async fn verify(s: &Server, user_name: &str, password: &str) -> Result<Option<bool>> {
+    let account = match s.repo.account.get(user_name).await? {
+        Some(account) => account,
+        None => return Ok(None),
+    };
+
+    let password = password.to_string();
+    let valid = tokio::task::spawn_blocking(move || verify_password(&account.password_hash, &password)).await
+        .map_err(|e| errno_new!("verify password failed = {}", e))?;
+
+    Ok(Some(valid))
+}
+
+
+pub async fn is_registered(s: &Server, user_name: &str) -> Result<bool> {
+    Ok(s.repo.account.get(user_name).await?.is_some())
+}
+
+
+fn hash_password(password: &str) -> Result<String> {
+    let mut salt = [0u8; SALT_LEN];
+    SystemRandom::new().fill(&mut salt)
+        .map_err(|_| errno_new!("generate salt failed"))?;
+
+    let iterations = NonZeroU32::new(HASH_ITERATIONS).expect("iterations are not zero");
+    let mut hash = [0u8; HASH_LEN];
+    pbkdf2::derive(pbkdf2::PBKDF2_HMAC_SHA256, iterations, &salt, password.as_bytes(), &mut hash);
+
+    Ok(format!("{}${}${}${}", HASH_SCHEME, HASH_ITERATIONS, STANDARD.encode(salt), STANDARD.encode(hash)))
+}
+
+
+/// a malformed stored hash never matches
+fn verify_password(password_hash: &str, password: &str) -> bool {
+    let parts: Vec<&str> = password_hash.split('$').collect();
+    let (iterations, salt, hash) = match parts.as_slice() {
+        [HASH_SCHEME, iterations, salt, hash] => (iterations, salt, hash),
+        _ => return false,
+    };
+
+    let iterations = match iterations.parse().ok().and_then(NonZeroU32::new) {
+        Some(iterations) => iterations,
+        None => return false,
+    };
+    let (salt, hash) = match (STANDARD.decode(salt), STANDARD.decode(hash)) {
+        (Ok(salt), Ok(hash)) => (salt, hash),
+        _ => return false,
+    };
+
+    pbkdf2::verify(pbkdf2::PBKDF2_HMAC_SHA256, iterations, &salt, password.as_bytes(), &hash).is_ok()
+}
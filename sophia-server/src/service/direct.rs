@@ -0,0 +1,46 @@
+use chrono::Utc;
+
+use sophia_core::command::Command;
+use sophia_core::errors::Result;
+use sophia_core::model::{InboxItem, Message, MessageKind, Request, User, UserInfo};
+
+use crate::controller::Server;
+use crate::service::{account, push, user};
+
+/// send `msg` to every session of the account `to`, queued in its inbox while it is offline,
+/// or else to the guest named `to` in the chat of `from`. Returns `false` if there is nobody to deliver the message to
+pub async fn send(s: &Server, from: &UserInfo, to: &str, msg: &str) -> Result<bool> {
+    let message = Message {
+        id: 0,
+        user: User::from_user_info(from),
+        time: Utc::now().timestamp(),
+        content: msg.to_string(),
+        reply_to: None,
+        edited_at: None,
+        reactions: Vec::new(),
+        mentions: Vec::new(),
+        file: None,
+        kind: MessageKind::Text,
+        author: Some(from.author()),
+    };
+
+    // guest names are only unique inside a chat
+    let registered = account::is_registered(s, to).await?;
+    let targets = match registered {
+        true => user::sessions_of_account(s, to).await,
+        false => user::find_by_name(s, from.chat_id, to).await?.into_iter().collect(),
+    };
+    if targets.is_empty() {
+        if !registered {
+            return Ok(false);
+        }
+
+        s.repo.inbox.push(to, InboxItem::Direct { message: message.clone() }).await?;
+    }
+
+    let req = Request::new(Command::DirectMessage { to: to.to_string(), message });
+    push::push_to_user(req.clone(), s, "", &targets).await;
+    push::push_to_user(req, s, "", &vec![from.clone()]).await;
+
+    Ok(true)
+}
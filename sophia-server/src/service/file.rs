@@ -0,0 +1,188 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tracing::{error, info};
+
+use sophia_core::{errno, errno_new};
+use sophia_core::command::StreamHeader;
+use sophia_core::consts::{FILE_CHUNK_SIZE, MAX_FILE_SIZE};
+use sophia_core::errors::Result;
+use sophia_core::hash::sha256_hex_bytes;
+use sophia_core::model::{Author, FileOffer, UserInfo};
+use sophia_net::quic::UniRecv;
+
+use crate::controller::Server;
+use crate::service::{message, push, user};
+
+/// unfinished uploads are dropped this long after they were offered
+const PENDING_TTL_SECS: i64 = 24 * 3600;
+const EXPIRE_INTERVAL: Duration = Duration::from_secs(3600);
+/// unfinished uploads one owner may have at a time
+pub const MAX_PENDING_UPLOADS: usize = 2;
+/// bytes the stored files and unfinished uploads of one owner may take up together
+pub const OWNER_QUOTA: u64 = 4 * MAX_FILE_SIZE;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StoredFile {
+    pub offer: FileOffer,
+    pub chat_id: i64,
+    /// the account of the uploader, or the hashed session of a guest, see `owner`
+    pub owner: String,
+    pub created: i64,
+    /// all bytes were received and matched the hash
+    pub complete: bool,
+}
+
+
+/// what became of an offer
+#[derive(Debug, PartialEq, Eq)]
+pub enum Reservation {
+    /// the file id and the offset to continue the upload from
+    Accepted(i64, u64),
+    /// the server quota has no room for the file
+    ServerFull,
+    /// the owner already has `MAX_PENDING_UPLOADS` unfinished uploads
+    TooManyPending,
+    /// the files of the owner would take up more than `OWNER_QUOTA`
+    OwnerFull,
+}
+
+
+/// who the uploads of `user_info` count against: the account, which outlasts a rename,
+/// or the session of a guest, hashed as the session id is a bearer token
+pub fn owner(user_info: &UserInfo) -> String {
+    match user_info.author() {
+        Author::Account(account) => format!("account:{}", account),
+        Author::Session(session_id) => format!("session:{}", sha256_hex_bytes(session_id.as_bytes())),
+    }
+}
+
+
+/// start or resume an upload
+pub async fn offer(s: &Server, user_info: &UserInfo, name: &str, size: u64, hash: &str) -> Result<Reservation> {
+    let hash = hash.to_lowercase();
+    let owner = owner(user_info);
+    if let Some(file) = s.repo.file.find_pending(user_info.chat_id, &owner, &hash, size).await? {
+        let received = s.repo.file.received(file.offer.id).await?.min(size);
+        return Ok(Reservation::Accepted(file.offer.id, received));
+    }
+
+    let file = StoredFile {
+        offer: FileOffer { id: 0, name: name.to_string(), size, hash },
+        chat_id: user_info.chat_id,
+        owner,
+        created: Utc::now().timestamp(),
+        complete: false,
+    };
+    // pending uploads reserve their size up front, until they complete or expire
+    s.repo.file.create(file).await
+}
+
+
+/// drop the uploads that were never finished and the files kept longer than `retention_secs`,
+/// at startup and then every `EXPIRE_INTERVAL`
+pub async fn expire(s: Server, retention_secs: i64) {
+    let mut interval = tokio::time::interval(EXPIRE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let now = Utc::now().timestamp();
+        match s.repo.file.expire(now - PENDING_TTL_SECS, now.saturating_sub(retention_secs)).await {
+            Ok(0) => (),
+            Ok(n) => info!("expired {} files and unfinished uploads", n),
+            Err(e) => error!("expire files failed = {}", e),
+        }
+    }
+}
+
+
+/// unidirectional stream opened by a client
+pub async fn handle_stream(s: &Server, remote: &str, header: StreamHeader, recv: UniRecv) {
+    let res = match header {
+        StreamHeader::Upload { session_id, id, offset } => receive(s, remote, &session_id, id, offset, recv).await,
+        header => errno!("unexpected stream {:?}", header),
+    };
+
+    if let Err(e) = res {
+        error!("file stream from {} failed = {}", remote, e);
+    }
+}
+
+
+/// write the uploaded bytes from `offset` on, and share the file with the chat once it is complete.
+/// An interrupted upload keeps what was received so far for `offer` to resume it
+async fn receive(s: &Server, remote: &str, session_id: &str, id: i64, offset: u64, mut recv: UniRecv) -> Result<()> {
+    user::check_session(s, session_id, remote).await?;
+    let user_info = s.repo.session.get(session_id).await?
+        .ok_or(errno_new!("session_id invalid"))?;
+
+    let file = s.repo.file.get(id).await?
+        .filter(|f| !f.complete && f.owner == owner(&user_info) && f.chat_id == user_info.chat_id)
+        .ok_or(errno_new!("upload {} not found", id))?;
+
+    // a second stream would write the same part file
+    if !s.repo.file.begin_upload(id).await {
+        return errno!("upload {} is already being received", id);
+    }
+    let res = write_part(s, id, offset, file.offer.size, &mut recv).await;
+    s.repo.file.end_upload(id).await;
+
+    match res? {
+        None => Ok(()),
+        Some(false) => {
+            let notice = format!("upload of {} failed: the content does not match its hash", file.offer.name);
+            push::notice_to_user(s, &user_info, &notice).await
+        }
+        Some(true) => message::send_file(s, user_info, file.offer).await,
+    }
+}
+
+
+/// write what `recv` brings to the part file of `id` from `offset` on, returns `None` while
+/// the upload is unfinished, else whether the complete file matched its hash
+async fn write_part(s: &Server, id: i64, offset: u64, size: u64, recv: &mut UniRecv) -> Result<Option<bool>> {
+    let received = s.repo.file.received(id).await?;
+    if offset > received {
+        return errno!("upload {} offset {} is past the {} bytes received", id, offset, received);
+    }
+
+    let mut out = s.repo.file.append_at(id, offset).await?;
+    let mut written = offset;
+    while let Some(chunk) = recv.read_chunk(FILE_CHUNK_SIZE).await? {
+        written += chunk.len() as u64;
+        if written > size {
+            return errno!("upload {} is larger than the announced {} bytes", id, size);
+        }
+        out.write_all(&chunk).await?;
+    }
+    out.flush().await?;
+
+    if written < size {
+        info!("upload {} paused at {} of {} bytes", id, written, size);
+        return Ok(None);
+    }
+
+    Ok(Some(s.repo.file.complete(id).await?))
+}
+
+
+/// stream a complete file from `offset` on to the client at `remote`
+pub async fn send(s: &Server, remote: &str, file: StoredFile, offset: u64) -> Result<()> {
+    let conn = s.cons.get(remote).await
+        .ok_or(errno_new!("conn not found {}", remote))?;
+    let id = file.offer.id;
+
+    let mut input = s.repo.file.read_at(id, offset).await?;
+    let mut stream = conn.open_uni(&StreamHeader::Download { id, offset }).await?;
+    let mut buf = vec![0u8; FILE_CHUNK_SIZE];
+    loop {
+        let n = input.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        stream.write(&buf[..n]).await?;
+    }
+
+    stream.finish().await
+}
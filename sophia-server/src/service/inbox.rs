@@ -0,0 +1,51 @@
+use tracing::error;
+
+use sophia_core::command::Command;
+use sophia_core::errors::Result;
+use sophia_core::model::{InboxItem, Message, Request, UserInfo};
+
+use crate::controller::Server;
+use crate::service::{account, push};
+
+/// queue `message` for the registered users it mentions that are not online in its chat,
+/// failures are only logged as the message itself was sent
+pub async fn queue_mentions(s: &Server, message: &Message) {
+    for name in &message.mentions {
+        if let Err(e) = queue_mention(s, name, message).await {
+            error!("queue mention of {} failed = {}", name, e);
+        }
+    }
+}
+
+async fn queue_mention(s: &Server, account: &str, message: &Message) -> Result<()> {
+    let chat_id = message.user.chat_id;
+    if !account::is_registered(s, account).await? {
+        return Ok(());
+    }
+
+    let users = s.repo.chat.get(chat_id).await?;
+    if users.values().any(|u| u.account.as_deref() == Some(account)) {
+        return Ok(());
+    }
+
+    s.repo.inbox.push(account, InboxItem::Mention { chat_id, message: message.clone() }).await
+}
+
+
+/// push everything queued for the account of `user_info` while it was away, the inbox is empty afterwards
+pub async fn deliver(s: &Server, user_info: &UserInfo) -> Result<()> {
+    let account = match &user_info.account {
+        Some(account) => account,
+        None => return Ok(()),
+    };
+
+    let items = s.repo.inbox.take(account).await?;
+    if items.is_empty() {
+        return Ok(());
+    }
+
+    let req = Request::new(Command::Inbox { items });
+    push::push_to_user(req, s, "", &vec![user_info.clone()]).await;
+
+    Ok(())
+}
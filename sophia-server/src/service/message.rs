@@ -2,24 +2,164 @@ use chrono::Utc;
 
 use sophia_core::command::Command;
 use sophia_core::errors::Result;
-use sophia_core::model::{Message, Request, User, UserInfo};
+use sophia_core::model::{FileOffer, Message, MessageKind, Request, User, UserInfo};
 
 use crate::controller::Server;
-use crate::service::push;
+use crate::service::{inbox, push, validate};
 
-pub async fn send(s: &Server, user: UserInfo, msg: &str) -> Result<()> {
+pub async fn send(s: &Server, user: UserInfo, msg: &str, reply_to: Option<i64>, kind: MessageKind) -> Result<()> {
     let u = User::from_user_info(&user);
     let now = Utc::now().timestamp();
     let message = Message {
+        id: 0,
         user: u,
         time: now,
         content: msg.to_string(),
+        reply_to,
+        edited_at: None,
+        reactions: Vec::new(),
+        mentions: mentions(msg),
+        file: None,
+        kind,
+        author: Some(user.author()),
     };
 
-    let _ = s.repo.message.save(message.clone()).await?;
-    let req = Request::new(Command::NewMessage(message));
+    publish(s, &user, message).await
+}
+
+
+/// share an uploaded file with the chat of `user`, the file name is the content
+pub async fn send_file(s: &Server, user: UserInfo, file: FileOffer) -> Result<()> {
+    let message = Message {
+        id: 0,
+        user: User::from_user_info(&user),
+        time: Utc::now().timestamp(),
+        content: file.name.to_string(),
+        reply_to: None,
+        edited_at: None,
+        reactions: Vec::new(),
+        mentions: Vec::new(),
+        file: Some(file),
+        kind: MessageKind::Text,
+        author: Some(user.author()),
+    };
+
+    publish(s, &user, message).await
+}
+
+
+async fn publish(s: &Server, user: &UserInfo, message: Message) -> Result<()> {
+    let message = s.repo.message.save(message).await?;
+    let req = Request::new(Command::NewMessage(message.clone()));
 
     push::push_to_chat_user(req, s, "", user.chat_id).await?;
+    let exists = !s.repo.chat.get(user.chat_id).await?.is_empty();
+    s.metrics.message(user.chat_id, exists);
+    inbox::queue_mentions(s, &message).await;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// returns `false` if the message does not exist (anymore)
+pub async fn edit(s: &Server, chat_id: i64, id: i64, content: &str) -> Result<bool> {
+    let now = Utc::now().timestamp();
+    let message = match s.repo.message.edit(chat_id, id, content, mentions(content), now).await? {
+        Some(message) => message,
+        None => return Ok(false),
+    };
+
+    let req = Request::new(Command::MessageEdited(message));
+    push::push_to_chat_user(req, s, "", chat_id).await?;
+
+    Ok(true)
+}
+
+
+pub async fn delete(s: &Server, chat_id: i64, id: i64) -> Result<bool> {
+    if !s.repo.message.delete(chat_id, id, Utc::now().timestamp()).await? {
+        return Ok(false);
+    }
+
+    let req = Request::new(Command::MessageDeleted { chat_id, id });
+    push::push_to_chat_user(req, s, "", chat_id).await?;
+
+    Ok(true)
+}
+
+
+/// add (or remove) the reaction of `user_info`, returns `false` if the message does not exist
+pub async fn react(s: &Server, user_info: &UserInfo, id: i64, emoji: &str, add: bool) -> Result<bool> {
+    let chat_id = user_info.chat_id;
+    let reactions = match s.repo.message.react(chat_id, id, emoji, &user_info.name, add).await? {
+        Some(reactions) => reactions,
+        None => return Ok(false),
+    };
+
+    let req = Request::new(Command::ReactionsChanged { chat_id, message_id: id, reactions });
+    push::push_to_chat_user(req, s, "", chat_id).await?;
+
+    Ok(true)
+}
+
+
+/// user names mentioned as `@name`, in order of first mention. The `@` must not follow
+/// a name char (so `bob@example.com` is no mention), a trailing `.` ends the sentence
+pub fn mentions(content: &str) -> Vec<String> {
+    let mut mentions: Vec<String> = Vec::new();
+    let mut prev: Option<char> = None;
+    let mut chars = content.char_indices().peekable();
+
+    while let Some((idx, c)) = chars.next() {
+        let starts_mention = c == '@' && !prev.is_some_and(validate::is_user_name_char);
+        prev = Some(c);
+        if !starts_mention {
+            continue;
+        }
+
+        let start = idx + c.len_utf8();
+        let mut end = start;
+        while let Some((i, c)) = chars.next_if(|(_, c)| validate::is_user_name_char(*c)) {
+            end = i + c.len_utf8();
+            prev = Some(c);
+        }
+
+        let name = content[start..end].trim_end_matches('.');
+        if validate::user_name(name).is_none() && !mentions.iter().any(|m| m == name) {
+            mentions.push(name.to_string());
+        }
+    }
+
+    mentions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::mentions;
+
+    #[test]
+    fn mentions_end_at_punctuation() {
+        assert_eq!(mentions("thanks @bob."), vec!["bob"]);
+        assert_eq!(mentions("@bob, @carol! (@dave)"), vec!["bob", "carol", "dave"]);
+        // a dot inside a name is part of it
+        assert_eq!(mentions("@j.doe..."), vec!["j.doe"]);
+    }
+
+    #[test]
+    fn mentions_skip_addresses_and_bare_at() {
+        assert!(mentions("mail bob@example.com").is_empty());
+        assert!(mentions("meet @ noon, @@").is_empty());
+        assert!(mentions("jane.doe@mail.example.org").is_empty());
+    }
+
+    #[test]
+    fn mentions_are_kept_once_in_order() {
+        assert_eq!(mentions("@carol @bob @carol @bob."), vec!["carol", "bob"]);
+    }
+
+    #[test]
+    fn mentions_keep_the_case_of_names() {
+        // names are case sensitive, `Bob` and `bob` are different users
+        assert_eq!(mentions("@Bob @bob @BOB"), vec!["Bob", "bob", "BOB"]);
+        assert_eq!(mentions("@Émile"), vec!["Émile"]);
+    }
+}
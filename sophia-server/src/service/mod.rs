@@ -3,11 +3,24 @@ use std::collections::HashMap;
 use async_trait::async_trait;
 
 use sophia_core::errors::Result;
-use sophia_core::model::{Message, UserInfo};
+use sophia_core::model::{Ban, InboxItem, Message, MessageEdit, Reaction, Role, UserInfo};
+
+use crate::service::account::Account;
+use crate::service::file::{Reservation, StoredFile};
+use crate::service::search::SearchQuery;
 
 pub mod user;
 pub mod push;
 pub mod message;
+pub mod validate;
+pub mod moderation;
+pub mod read_state;
+pub mod account;
+pub mod direct;
+pub mod inbox;
+pub mod search;
+pub mod file;
+pub mod topic;
 
 #[async_trait]
 pub trait SessionRepo: Send + Sync {
@@ -30,6 +43,100 @@ pub trait ChatRepo: Send + Sync {
 
 #[async_trait]
 pub trait MessageRepo: Send + Sync {
-    async fn save(&self, msg: Message) -> Result<()>;
+    /// assigns the next id of the chat to `msg`, returns the saved message
+    async fn save(&self, msg: Message) -> Result<Message>;
     async fn get(&self, chat_id: i64) -> Result<Vec<Message>>;
-}
\ No newline at end of file
+    async fn find(&self, chat_id: i64, id: i64) -> Result<Option<Message>>;
+    /// replace the content at `time`, the previous content is kept in the history
+    async fn edit(&self, chat_id: i64, id: i64, content: &str, mentions: Vec<String>, time: i64) -> Result<Option<Message>>;
+    /// the last content is kept in the history
+    async fn delete(&self, chat_id: i64, id: i64, time: i64) -> Result<bool>;
+    async fn history(&self, chat_id: i64, id: i64) -> Result<Vec<MessageEdit>>;
+    /// id of the newest message ever saved in the chat, 0 if there was none
+    async fn last_id(&self, chat_id: i64) -> Result<i64>;
+    /// add or remove the reaction of `user_name`, returns the reactions of the
+    /// message afterwards or `None` if the message does not exist
+    async fn react(&self, chat_id: i64, id: i64, emoji: &str, user_name: &str, add: bool) -> Result<Option<Vec<Reaction>>>;
+    /// at most `limit` messages matching `query` with an id below `before`, newest first
+    async fn search(&self, chat_id: i64, query: &SearchQuery, limit: usize, before: Option<i64>) -> Result<Vec<Message>>;
+}
+
+#[async_trait]
+pub trait ModerationRepo: Send + Sync {
+    /// roles are held by registered accounts, anyone else is a member
+    async fn role(&self, chat_id: i64, account: &str) -> Result<Role>;
+    async fn set_role(&self, chat_id: i64, account: &str, role: Role) -> Result<()>;
+    /// make `account` the owner of the chat if it does not have one yet
+    async fn claim_owner(&self, chat_id: i64, account: &str) -> Result<bool>;
+    async fn ban(&self, chat_id: i64, ban: Ban) -> Result<()>;
+    async fn unban(&self, chat_id: i64, user_name: &str) -> Result<bool>;
+    async fn get_ban(&self, chat_id: i64, user_name: &str, ip: &str, now: i64) -> Result<Option<Ban>>;
+    async fn mute(&self, chat_id: i64, user_name: &str, until: i64) -> Result<()>;
+    async fn get_mute(&self, chat_id: i64, user_name: &str, now: i64) -> Result<Option<i64>>;
+}
+
+
+#[async_trait]
+pub trait ReadStateRepo: Send + Sync {
+    /// user_name -> last read message id of every member that read `chat_id`
+    async fn list(&self, chat_id: i64) -> Result<HashMap<String, i64>>;
+    /// chat_id -> last read message id of every chat `user_name` read
+    async fn chats_of(&self, user_name: &str) -> Result<HashMap<i64, i64>>;
+    /// move the read position forward, returns `false` if `seq` is not past the current one
+    async fn mark(&self, chat_id: i64, user_name: &str, seq: i64) -> Result<bool>;
+    /// forget the positions in `chat_id` past `last_id`
+    async fn discard_past(&self, chat_id: i64, last_id: i64) -> Result<()>;
+}
+
+
+#[async_trait]
+pub trait AccountRepo: Send + Sync {
+    async fn get(&self, user_name: &str) -> Result<Option<Account>>;
+    /// returns `false` if an account with the same name exists
+    async fn create(&self, account: Account) -> Result<bool>;
+}
+
+
+#[async_trait]
+pub trait InboxRepo: Send + Sync {
+    /// queue an item for `user_name`, keeping at most `MAX_INBOX_ITEMS`
+    async fn push(&self, user_name: &str, item: InboxItem) -> Result<()>;
+    /// remove and return all queued items, oldest first
+    async fn take(&self, user_name: &str) -> Result<Vec<InboxItem>>;
+}
+
+
+#[async_trait]
+pub trait TopicRepo: Send + Sync {
+    /// empty if the chat has no topic
+    async fn get(&self, chat_id: i64) -> Result<String>;
+    /// an empty topic clears it
+    async fn set(&self, chat_id: i64, topic: &str) -> Result<()>;
+}
+
+
+#[async_trait]
+pub trait FileRepo: Send + Sync {
+    /// the unfinished upload of the same content by `owner` to `chat_id`, to resume it
+    async fn find_pending(&self, chat_id: i64, owner: &str, hash: &str, size: u64) -> Result<Option<StoredFile>>;
+    /// assigns the next file id and reserves the size of the file, stored files and unfinished
+    /// uploads count against the quota of the server and the limits of their owner
+    async fn create(&self, file: StoredFile) -> Result<Reservation>;
+    async fn get(&self, id: i64) -> Result<Option<StoredFile>>;
+    /// bytes of an unfinished upload received so far
+    async fn received(&self, id: i64) -> Result<u64>;
+    /// open an unfinished upload to write at `offset`, anything after it is dropped
+    async fn append_at(&self, id: i64, offset: u64) -> Result<tokio::fs::File>;
+    /// check the hash of a fully received upload and mark it complete,
+    /// returns `false` and discards the upload if the hash does not match
+    async fn complete(&self, id: i64) -> Result<bool>;
+    /// open a complete file to read from `offset`
+    async fn read_at(&self, id: i64, offset: u64) -> Result<tokio::fs::File>;
+    /// claim the upload of `id` for one stream, returns `false` if another stream is receiving it
+    async fn begin_upload(&self, id: i64) -> bool;
+    async fn end_upload(&self, id: i64);
+    /// drop the unfinished uploads offered before `pending_before` that no stream is receiving
+    /// and the complete files offered before `complete_before`, along with their bytes,
+    /// returns how many were dropped
+    async fn expire(&self, pending_before: i64, complete_before: i64) -> Result<usize>;
+}
@@ -0,0 +1,200 @@
+use chrono::Utc;
+use tracing::error;
+
+use sophia_core::command::Command;
+use sophia_core::consts::code;
+use sophia_core::duration::format_duration;
+use sophia_core::errno_new;
+use sophia_core::errors::Result;
+use sophia_core::model::{Ban, ModerationAction, ModerationEvent, Request, Response, Role, UserInfo};
+
+use crate::controller::Server;
+use crate::service::{push, user};
+
+/// owner outranks moderator outranks member, members can not moderate anyone
+pub fn can_moderate(operator: Role, target: Role) -> bool {
+    operator != Role::Member && operator < target
+}
+
+
+/// check `operator` may moderate `target_name` in `chat_id`, returns the error response if not
+pub async fn check_permission(s: &Server, operator: &UserInfo, chat_id: i64, target_name: &str) -> Result<Option<Response>> {
+    if chat_id != operator.chat_id {
+        return Ok(Some(Response::new(code::CHAT_ID_INVALID, "chat_id invalid".to_string())));
+    }
+
+    if target_name == operator.name {
+        return Ok(Some(Response::new(code::PERMISSION_DENIED, "you can not moderate yourself".to_string())));
+    }
+
+    // an online user has the role of the account it logged in with, whatever its name is now
+    let target_role = match user::find_by_name(s, chat_id, target_name).await? {
+        Some(target) => target.role,
+        None => s.repo.moderation.role(chat_id, target_name).await?,
+    };
+    if !can_moderate(operator.role, target_role) {
+        let msg = format!("{} can not moderate {} {}", operator.role, target_role, target_name);
+        return Ok(Some(Response::new(code::PERMISSION_DENIED, msg)));
+    }
+
+    Ok(None)
+}
+
+
+pub async fn check_ban(s: &Server, chat_id: i64, user_name: &str, remote: &str) -> Result<Option<Response>> {
+    let now = Utc::now().timestamp();
+    let ban = s.repo.moderation.get_ban(chat_id, user_name, &user::remote_ip(remote), now).await?;
+
+    let resp = ban.map(|ban| {
+        let msg = match ban.until {
+            Some(until) => format!("you are banned from chat {} by {}, {} remaining",
+                                   chat_id, ban.operator, format_duration(until - now)),
+            None => format!("you are banned from chat {} by {}", chat_id, ban.operator),
+        };
+        Response::new(code::USER_BANNED, msg)
+    });
+
+    Ok(resp)
+}
+
+
+pub async fn check_mute(s: &Server, user_info: &UserInfo) -> Result<Option<Response>> {
+    let now = Utc::now().timestamp();
+    let until = s.repo.moderation.get_mute(user_info.chat_id, &user_info.name, now).await?;
+
+    let resp = until.map(|until| {
+        let msg = format!("you are muted, {} remaining", format_duration(until - now));
+        Response::new(code::USER_MUTED, msg)
+    });
+
+    Ok(resp)
+}
+
+
+/// `operator` is the moderator's user name, or `admin` for the server's admin channel
+pub async fn kick(s: &Server, operator: &str, target: &UserInfo) -> Result<()> {
+    let event = new_event(target.chat_id, operator, &target.name, ModerationAction::Kick);
+    announce_and_disconnect(s, event, target).await
+}
+
+
+/// ban `user_name` for `duration` seconds (forever if `None`), disconnecting the user if online
+pub async fn ban(s: &Server, operator: &UserInfo, user_name: &str, duration: Option<i64>) -> Result<()> {
+    let now = Utc::now().timestamp();
+    let until = match duration {
+        Some(d) => Some(now.checked_add(d).ok_or_else(|| errno_new!("ban duration {} overflows", d))?),
+        None => None,
+    };
+    let target = user::find_by_name(s, operator.chat_id, user_name).await?;
+
+    let ban = Ban {
+        user_name: user_name.to_string(),
+        ip: target.as_ref().map(|t| user::remote_ip(&t.address)).unwrap_or_default(),
+        operator: operator.name.to_string(),
+        time: now,
+        until,
+    };
+    s.repo.moderation.ban(operator.chat_id, ban).await?;
+
+    let event = new_event(operator.chat_id, &operator.name, user_name, ModerationAction::Ban { until });
+    match target {
+        Some(target) => announce_and_disconnect(s, event, &target).await,
+        None => announce(s, event).await,
+    }
+}
+
+
+pub async fn unban(s: &Server, operator: &UserInfo, user_name: &str) -> Result<bool> {
+    if !s.repo.moderation.unban(operator.chat_id, user_name).await? {
+        return Ok(false);
+    }
+
+    announce(s, new_event(operator.chat_id, &operator.name, user_name, ModerationAction::Unban)).await?;
+    Ok(true)
+}
+
+
+pub async fn mute(s: &Server, operator: &UserInfo, user_name: &str, duration: i64) -> Result<()> {
+    let until = Utc::now().timestamp().checked_add(duration)
+        .ok_or_else(|| errno_new!("mute duration {} overflows", duration))?;
+    s.repo.moderation.mute(operator.chat_id, user_name, until).await?;
+
+    announce(s, new_event(operator.chat_id, &operator.name, user_name, ModerationAction::Mute { until })).await
+}
+
+
+/// give `account` the role, `user_name` is how it is shown to the chat
+pub async fn set_role(s: &Server, operator: &UserInfo, user_name: &str, account: &str, role: Role) -> Result<()> {
+    let chat_id = operator.chat_id;
+    s.repo.moderation.set_role(chat_id, account, role).await?;
+
+    // online users carry their role in the user list
+    let targets: Vec<UserInfo> = s.repo.chat.get(chat_id).await?.into_values()
+        .filter(|u| u.account.as_deref() == Some(account))
+        .collect();
+    if !targets.is_empty() {
+        for mut target in targets {
+            target.role = role;
+            user::update(s, &target).await?;
+        }
+
+        let to_users = s.repo.chat.get(chat_id).await?.into_values().collect();
+        push::chat_user_list(s, chat_id, "", &to_users).await?;
+    }
+
+    announce(s, new_event(operator.chat_id, &operator.name, user_name, ModerationAction::SetRole(role))).await
+}
+
+
+fn new_event(chat_id: i64, operator: &str, target: &str, action: ModerationAction) -> ModerationEvent {
+    ModerationEvent {
+        time: Utc::now().timestamp(),
+        chat_id,
+        action,
+        target: target.to_string(),
+        operator: operator.to_string(),
+    }
+}
+
+async fn announce(s: &Server, event: ModerationEvent) -> Result<()> {
+    let chat_id = event.chat_id;
+    let notice = push::notice(&event_content(&event));
+    let req = Request::new(Command::ModerationEvent(event));
+    push::push_to_chat_user(req, s, "", chat_id).await?;
+    push::push_to_chat_user(notice, s, "", chat_id).await
+}
+
+/// tell the target why it is leaving before closing its connection, the normal
+/// disconnect path (`Server::kick_out`) then cleans up the session
+async fn announce_and_disconnect(s: &Server, event: ModerationEvent, target: &UserInfo) -> Result<()> {
+    let notice = push::notice(&event_content(&event));
+    let req = Request::new(Command::ModerationEvent(event));
+
+    if let Some(conn) = s.cons.get(&target.address).await {
+        for req in [&notice, &req] {
+            if let Err(e) = conn.send(req.clone()).await {
+                error!("failed push moderation event to {} , err = {}", target.address, e);
+            }
+        }
+        conn.closed().await;
+    }
+
+    push::push_to_chat_user(req, s, &target.address, target.chat_id).await?;
+    push::push_to_chat_user(notice, s, &target.address, target.chat_id).await
+}
+
+/// e.g. `bob was muted by alice for 10m0s`
+fn event_content(event: &ModerationEvent) -> String {
+    let (target, operator) = (&event.target, &event.operator);
+    match &event.action {
+        ModerationAction::Kick => format!("{} was kicked by {}", target, operator),
+        ModerationAction::Ban { until: Some(until) } =>
+            format!("{} was banned by {} for {}", target, operator, format_duration(until - event.time)),
+        ModerationAction::Ban { until: None } => format!("{} was banned by {}", target, operator),
+        ModerationAction::Unban => format!("{} was unbanned by {}", target, operator),
+        ModerationAction::Mute { until } =>
+            format!("{} was muted by {} for {}", target, operator, format_duration(until - event.time)),
+        ModerationAction::SetRole(role) =>
+            format!("{} is now a {} (by {})", target, role.to_string().to_lowercase(), operator),
+    }
+}
@@ -1,5 +1,7 @@
+use std::time::Instant;
+
 use chrono::prelude::*;
-use log::{error, info};
+use tracing::{debug, error};
 
 use sophia_core::command::Command;
 use sophia_core::errors::Result;
@@ -41,7 +43,9 @@ pub async fn push_user_conn_change_event(s: &Server, user_info: &UserInfo, is_on
     push_to_user(req, s, &user_info.address, &user_vec).await;
     chat_user_list(s, user_info.chat_id, except_for_addr, &user_vec).await?;
 
-    Ok(())
+    let action = if is_online { "online" } else { "offline" };
+    let notice = format!("{} {} is {}", user_info.address, user_info.name, action);
+    system_notice(s, user_info.chat_id, &notice).await
 }
 
 
@@ -77,7 +81,7 @@ pub(super) async fn push_to_chat_user(req: Request, s: &Server, except_for_addr:
     Ok(())
 }
 
-async fn push_to_user(req: Request, s: &Server, except_for_addr: &str, to_users: &Vec<UserInfo>) {
+pub(super) async fn push_to_user(req: Request, s: &Server, except_for_addr: &str, to_users: &Vec<UserInfo>) {
     for u in to_users {
         if u.address == except_for_addr {
             continue;
@@ -88,24 +92,51 @@ async fn push_to_user(req: Request, s: &Server, except_for_addr: &str, to_users:
 
         let conn = s.cons.get(&user_remote).await;
         if conn.is_none() {
-            error!("conn not found {}, req = {:?}", user_remote, req);
+            error!("conn not found {}, cmd = {}", user_remote, req.cmd_type);
             continue;
         }
 
         let conn = conn.unwrap();
         let user_remote = user_remote.clone();
         let req = req.clone();
+        let metrics = s.metrics.clone();
         tokio::spawn(async move {
+            let start = Instant::now();
             let res = conn.send(req.clone()).await;
+            metrics.push(start.elapsed(), res.is_ok());
             match res {
-                Err(e) => error!("failed push {} online in chat {}  failed : {} , req {:?}", user_remote, chat_id, e,req),
-                _ => info!("success push to client {}, req = {:?}", user_remote, req )
+                Err(e) => error!("failed push {} online in chat {}  failed : {} , cmd {}", user_remote, chat_id, e, req.cmd_type),
+                _ => debug!("success push to client {}, req = {:?}", user_remote, req)
             }
         });
     }
 }
 
 
+/// tell the other members of the chat that `user_info` is typing, sent as
+/// datagrams since a lost typing event does not matter
+pub async fn user_typing(s: &Server, user_info: &UserInfo) -> Result<()> {
+    let req = Request::new(Command::UserTyping {
+        chat_id: user_info.chat_id,
+        user_name: user_info.name.to_string(),
+    });
+
+    for u in s.repo.chat.get(user_info.chat_id).await?.into_values() {
+        if u.address == user_info.address {
+            continue;
+        }
+
+        if let Some(conn) = s.cons.get(&u.address).await {
+            if let Err(e) = conn.send_datagram(&req) {
+                debug!("failed send typing to {} , err = {}", u.address, e);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+
 pub async fn chat_message_list(s: &Server, user_info: &UserInfo) -> Result<()> {
     let msg_list = s.repo.message.get(user_info.chat_id).await?;
     let req = Request::new(Command::ChatMessageList { message_list: msg_list });
@@ -113,3 +144,24 @@ pub async fn chat_message_list(s: &Server, user_info: &UserInfo) -> Result<()> {
 
     Ok(())
 }
+
+
+/// notice only shown to `user_info`
+pub async fn notice_to_user(s: &Server, user_info: &UserInfo, content: &str) -> Result<()> {
+    push_to_user(notice(content), s, "", &vec![user_info.clone()]).await;
+
+    Ok(())
+}
+
+
+pub async fn system_notice(s: &Server, chat_id: i64, content: &str) -> Result<()> {
+    push_to_chat_user(notice(content), s, "", chat_id).await
+}
+
+
+pub fn notice(content: &str) -> Request {
+    Request::new(Command::SystemNotice {
+        time: Utc::now().timestamp(),
+        content: content.to_string(),
+    })
+}
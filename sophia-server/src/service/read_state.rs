@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+
+use sophia_core::command::Command;
+use sophia_core::errors::Result;
+use sophia_core::model::{Request, UserInfo};
+
+use crate::controller::Server;
+use crate::service::push;
+
+/// move the read position of `user_info` to `seq` (capped at the newest message)
+/// and tell the other members, returns `false` if the position did not move
+pub async fn mark(s: &Server, user_info: &UserInfo, seq: i64) -> Result<bool> {
+    let chat_id = user_info.chat_id;
+    let last_id = s.repo.message.last_id(chat_id).await?;
+    s.repo.read_state.discard_past(chat_id, last_id).await?;
+
+    let seq = seq.min(last_id);
+    if !s.repo.read_state.mark(chat_id, &user_info.name, seq).await? {
+        return Ok(false);
+    }
+
+    let req = Request::new(Command::ReadState { chat_id, user_name: user_info.name.to_string(), seq });
+    push::push_to_chat_user(req, s, &user_info.address, chat_id).await?;
+
+    Ok(true)
+}
+
+
+/// push the read positions of the chat, and the unread counts of the other chats, to a user that just logged in
+pub async fn push_read_states(s: &Server, user_info: &UserInfo) -> Result<()> {
+    let chat_id = user_info.chat_id;
+    s.repo.read_state.discard_past(chat_id, s.repo.message.last_id(chat_id).await?).await?;
+    let read = s.repo.read_state.list(chat_id).await?;
+
+    // ids are never reused, so deleted messages still count as unread
+    let mut unread = HashMap::new();
+    for (other, seq) in s.repo.read_state.chats_of(&user_info.name).await? {
+        if other == chat_id {
+            continue;
+        }
+
+        let last_id = s.repo.message.last_id(other).await?;
+        let count = last_id - position(seq, last_id);
+        if count > 0 {
+            unread.insert(other, count);
+        }
+    }
+
+    let req = Request::new(Command::ReadStates { chat_id, read, unread });
+    push::push_to_user(req, s, "", &vec![user_info.clone()]).await;
+
+    Ok(())
+}
+
+
+/// read positions are kept across restarts but messages are not, so the ids start over after one,
+/// a position past the newest message is from before that and counts as nothing read
+fn position(seq: i64, last_id: i64) -> i64 {
+    match seq > last_id {
+        true => 0,
+        false => seq,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::position;
+
+    #[test]
+    fn position_up_to_the_newest_message() {
+        assert_eq!(position(0, 0), 0);
+        assert_eq!(position(3, 10), 3);
+        assert_eq!(position(10, 10), 10);
+    }
+
+    #[test]
+    fn position_from_before_a_restart() {
+        assert_eq!(position(500, 3), 0);
+        assert_eq!(position(1, 0), 0);
+    }
+}
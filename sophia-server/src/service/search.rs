@@ -0,0 +1,155 @@
+use chrono::{NaiveDate, NaiveTime};
+
+use sophia_core::model::Message;
+
+/// parsed `SearchMessages` query, every part must match
+#[derive(Clone, Debug, Default)]
+pub struct SearchQuery {
+    /// lower cased words, looked up in the index
+    pub terms: Vec<String>,
+    /// lower cased phrases, matched against the content
+    pub phrases: Vec<String>,
+    pub from: Option<String>,
+    /// unix seconds, inclusive
+    pub since: Option<i64>,
+    /// unix seconds, exclusive
+    pub until: Option<i64>,
+}
+
+impl SearchQuery {
+    /// `words "a phrase" from:alice since:2024-01-01 until:2024-01-31`, dates are UTC days
+    /// and both ends are included. Returns a description of the problem if the query is invalid
+    pub fn parse(query: &str) -> Result<Self, String> {
+        let mut q = SearchQuery::default();
+        let mut rest = query.trim();
+
+        while !rest.is_empty() {
+            if let Some(quoted) = rest.strip_prefix('"') {
+                let end = quoted.find('"').ok_or("unterminated \"")?;
+                let phrase = quoted[..end].trim().to_lowercase();
+                if !phrase.is_empty() {
+                    q.terms.extend(tokens(&phrase));
+                    q.phrases.push(phrase);
+                }
+                rest = quoted[end + 1..].trim_start();
+                continue;
+            }
+
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            let word = &rest[..end];
+            rest = rest[end..].trim_start();
+
+            match word.split_once(':') {
+                Some(("from", name)) if !name.is_empty() => q.from = Some(name.to_string()),
+                Some(("since", date)) => q.since = Some(day_start(date)?),
+                Some(("until", date)) => q.until = Some(day_start(date)? + 24 * 3600),
+                _ => q.terms.extend(tokens(word)),
+            }
+        }
+
+        q.terms.sort();
+        q.terms.dedup();
+        if q.terms.is_empty() && q.from.is_none() && q.since.is_none() && q.until.is_none() {
+            return Err("query is empty".to_string());
+        }
+
+        Ok(q)
+    }
+
+    /// the parts the index can not answer
+    pub fn matches(&self, msg: &Message) -> bool {
+        if self.from.as_ref().is_some_and(|from| *from != msg.user.user_name) {
+            return false;
+        }
+
+        if self.since.is_some_and(|since| msg.time < since) || self.until.is_some_and(|until| msg.time >= until) {
+            return false;
+        }
+
+        let content = msg.content.to_lowercase();
+        self.phrases.iter().all(|phrase| content.contains(phrase.as_str()))
+    }
+}
+
+
+/// lower cased words of `text`, split at anything that is not alphanumeric
+pub fn tokens(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_lowercase())
+        .collect()
+}
+
+
+fn day_start(date: &str) -> Result<i64, String> {
+    let day = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| format!("invalid date {}, expected yyyy-mm-dd", date))?;
+
+    Ok(day.and_time(NaiveTime::MIN).and_utc().timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use sophia_core::model::{Message, MessageKind, User, UserInfo};
+
+    use super::SearchQuery;
+
+    fn message(name: &str, time: i64, content: &str) -> Message {
+        let user = UserInfo::new(name.to_string(), String::new(), String::new(), 1, 0);
+        Message {
+            id: 1,
+            user: User::from_user_info(&user),
+            time,
+            content: content.to_string(),
+            reply_to: None,
+            edited_at: None,
+            reactions: Vec::new(),
+            mentions: Vec::new(),
+            file: None,
+            kind: MessageKind::Text,
+            author: None,
+        }
+    }
+
+    #[test]
+    fn parse_words_and_phrases() {
+        let q = SearchQuery::parse(r#"Deploy "Green Build" deploy  "" "#).unwrap();
+        assert_eq!(q.terms, vec!["build", "deploy", "green"]);
+        assert_eq!(q.phrases, vec!["green build"]);
+
+        assert!(q.matches(&message("a", 0, "the green build is out")));
+        assert!(!q.matches(&message("a", 0, "green, then build")));
+    }
+
+    #[test]
+    fn parse_rejects_unterminated_quote() {
+        assert!(SearchQuery::parse(r#"a "green build"#).is_err());
+    }
+
+    #[test]
+    fn parse_filters() {
+        let q = SearchQuery::parse("from:alice since:2024-01-01 until:2024-01-31").unwrap();
+        assert!(q.terms.is_empty());
+        assert_eq!(q.from.as_deref(), Some("alice"));
+        assert_eq!(q.since, Some(1704067200));
+        // until includes the whole day
+        assert_eq!(q.until, Some(1706745600));
+
+        assert!(q.matches(&message("alice", 1704067200, "")));
+        assert!(q.matches(&message("alice", 1706745599, "")));
+        assert!(!q.matches(&message("alice", 1706745600, "")));
+        assert!(!q.matches(&message("alice", 1704067199, "")));
+        assert!(!q.matches(&message("bob", 1704067200, "")));
+
+        assert!(SearchQuery::parse("since:2024-13-01").is_err());
+        // an empty `from:` is a plain word
+        assert_eq!(SearchQuery::parse("from:").unwrap().terms, vec!["from"]);
+    }
+
+    #[test]
+    fn parse_rejects_empty_query() {
+        assert!(SearchQuery::parse("").is_err());
+        assert!(SearchQuery::parse("   ").is_err());
+        assert!(SearchQuery::parse(r#""" ..."#).is_err());
+    }
+}
@@ -0,0 +1,34 @@
+use sophia_core::command::Command;
+use sophia_core::errors::Result;
+use sophia_core::model::{Request, UserInfo};
+
+use crate::controller::Server;
+use crate::service::push;
+
+/// set the topic of the chat of `operator`, an empty topic clears it
+pub async fn set(s: &Server, operator: &UserInfo, topic: &str) -> Result<()> {
+    let chat_id = operator.chat_id;
+    let topic = topic.trim();
+    s.repo.topic.set(chat_id, topic).await?;
+
+    let req = Request::new(Command::ChatTopic { chat_id, topic: topic.to_string() });
+    push::push_to_chat_user(req, s, "", chat_id).await?;
+
+    let notice = match topic {
+        "" => format!("{} cleared the topic", operator.name),
+        topic => format!("{} set the topic to: {}", operator.name, topic),
+    };
+    push::system_notice(s, chat_id, &notice).await
+}
+
+
+/// push the topic of the chat to a user that just joined it
+pub async fn push_topic(s: &Server, user_info: &UserInfo) -> Result<()> {
+    let chat_id = user_info.chat_id;
+    let topic = s.repo.topic.get(chat_id).await?;
+
+    let req = Request::new(Command::ChatTopic { chat_id, topic });
+    push::push_to_user(req, s, "", &vec![user_info.clone()]).await;
+
+    Ok(())
+}
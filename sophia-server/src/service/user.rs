@@ -1,20 +1,29 @@
+use std::net::SocketAddr;
+
 use chrono::prelude::*;
 use rand::distributions::{Alphanumeric, DistString};
 
 use sophia_core::{errno, errno_new};
-use sophia_core::command::Login;
+use sophia_core::command::{Command, Login};
 use sophia_core::errors::Result;
-use sophia_core::model::UserInfo;
+use sophia_core::model::{Request, Role, Status, UserInfo};
 
 use crate::controller::Server;
+use crate::service::{account, push};
 
 pub async fn login_handler(s: &Server, login: Login, remote: String) -> Result<UserInfo> {
     // 1. make session
     let session_id = Alphanumeric.sample_string(&mut rand::thread_rng(), 32);
 
     let now = Utc::now().timestamp();
-    let user_info = UserInfo::new(login.user_name, remote,
-                                  session_id.clone(), login.chat_id, now);
+    let registered = account::is_registered(s, &login.user_name).await?;
+    let mut user_info = UserInfo::new(login.user_name, remote,
+                                      session_id.clone(), login.chat_id, now);
+    // the password was checked by `auth`, guests share theirs and get no account
+    if registered {
+        user_info.account = Some(user_info.name.clone());
+    }
+    user_info.role = chat_role(s, &user_info, login.chat_id).await?;
 
     // 2. save session_id -> user_info
     s.repo.session.save(session_id.clone(), user_info.clone()).await?;
@@ -26,8 +35,74 @@ pub async fn login_handler(s: &Server, login: Login, remote: String) -> Result<U
 }
 
 
-pub async fn auth(request: &Login) -> Result<bool> {
-    Ok(request.password == "666666")
+/// move the session of `user_info` to `chat_id`, the role is the one its account has in the new chat
+pub async fn join(s: &Server, mut user_info: UserInfo, chat_id: i64) -> Result<UserInfo> {
+    s.repo.chat.remove(user_info.chat_id, &user_info.address).await?;
+    push::user_offline_event(s, &user_info).await?;
+
+    user_info.role = chat_role(s, &user_info, chat_id).await?;
+    user_info.chat_id = chat_id;
+    user_info.login_time = Utc::now().timestamp();
+    update(s, &user_info).await?;
+
+    Ok(user_info)
+}
+
+
+/// rename the session of `user_info` and tell the chat, the session keeps its account and role
+pub async fn rename(s: &Server, mut user_info: UserInfo, user_name: &str) -> Result<UserInfo> {
+    let old = std::mem::replace(&mut user_info.name, user_name.to_string());
+    update(s, &user_info).await?;
+
+    let notice = format!("{} is now known as {}", old, user_name);
+    let req = Request::new(Command::NickChanged {
+        time: Utc::now().timestamp(),
+        old,
+        new: user_name.to_string(),
+    });
+    push::push_to_chat_user(req, s, "", user_info.chat_id).await?;
+
+    let to_users = s.repo.chat.get(user_info.chat_id).await?.into_values().collect();
+    push::chat_user_list(s, user_info.chat_id, "", &to_users).await?;
+    push::system_notice(s, user_info.chat_id, &notice).await?;
+
+    Ok(user_info)
+}
+
+
+/// role of the account of `user_info` in `chat_id`, the first account to join a chat owns it,
+/// guests are always members
+async fn chat_role(s: &Server, user_info: &UserInfo, chat_id: i64) -> Result<Role> {
+    match &user_info.account {
+        Some(account) => {
+            s.repo.moderation.claim_owner(chat_id, account).await?;
+            s.repo.moderation.role(chat_id, account).await
+        }
+        None => Ok(Role::Member),
+    }
+}
+
+
+/// the registered account behind `user_name` in `chat_id`: the one an online user logged in with,
+/// else the account of that name, `None` for guests
+pub async fn account_of(s: &Server, chat_id: i64, user_name: &str) -> Result<Option<String>> {
+    if let Some(user) = find_by_name(s, chat_id, user_name).await? {
+        return Ok(user.account);
+    }
+
+    match account::is_registered(s, user_name).await? {
+        true => Ok(Some(user_name.to_string())),
+        false => Ok(None),
+    }
+}
+
+
+/// registered names need their own password, any other name the shared guest password
+pub async fn auth(s: &Server, request: &Login) -> Result<bool> {
+    match account::verify(s, &request.user_name, &request.password).await? {
+        Some(valid) => Ok(valid),
+        None => Ok(request.password == "666666"),
+    }
 }
 
 pub async fn check_user_name(s: &Server, user_name: &str, chat_id: i64) -> Result<bool> {
@@ -53,3 +128,59 @@ pub async fn check_session(s: &Server, session_id: &str, remote: &str) -> Result
 
     Ok(())
 }
+
+
+/// online user of `chat_id` named `user_name`
+pub async fn find_by_name(s: &Server, chat_id: i64, user_name: &str) -> Result<Option<UserInfo>> {
+    let users = s.repo.chat.get(chat_id).await?;
+
+    Ok(users.into_values().find(|u| u.name == user_name))
+}
+
+
+/// sessions logged in with `account`, in any chat and under any name
+pub async fn sessions_of_account(s: &Server, account: &str) -> Vec<UserInfo> {
+    s.repo.session.list_sessions().await
+        .into_values()
+        .filter(|u| u.account.as_deref() == Some(account))
+        .collect()
+}
+
+
+/// save changed user info to both the session and the chat user list
+pub async fn update(s: &Server, user_info: &UserInfo) -> Result<()> {
+    s.repo.session.save(user_info.session_id.clone(), user_info.clone()).await?;
+    s.repo.chat.save(user_info.chat_id, user_info.clone()).await?;
+
+    Ok(())
+}
+
+
+/// change the presence of `user_info` and push the new user list to the chat
+pub async fn set_status(s: &Server, mut user_info: UserInfo, status: Status, text: &str) -> Result<()> {
+    // going away is not activity, the user list keeps showing how long the user has been idle
+    if status != Status::Away {
+        user_info.last_active = Utc::now().timestamp();
+    }
+    user_info.status = status;
+    user_info.status_text = text.trim().to_string();
+    update(s, &user_info).await?;
+
+    let to_users = s.repo.chat.get(user_info.chat_id).await?.into_values().collect();
+    push::chat_user_list(s, user_info.chat_id, "", &to_users).await
+}
+
+
+/// record that the user just did something, e.g. sent a message
+pub async fn touch(s: &Server, user_info: &mut UserInfo) -> Result<()> {
+    user_info.last_active = Utc::now().timestamp();
+    update(s, user_info).await
+}
+
+
+/// ip part of a remote `ip:port` address
+pub fn remote_ip(remote: &str) -> String {
+    remote.parse::<SocketAddr>()
+        .map(|addr| addr.ip().to_string())
+        .unwrap_or_else(|_| remote.to_string())
+}
@@ -0,0 +1,175 @@
+use sophia_core::consts::{code, MAX_EMOJI_LEN, MAX_FILE_NAME_LEN, MAX_FILE_SIZE, MAX_MODERATION_SECS, MAX_MSG_LEN,
+                          MAX_STATUS_TEXT_LEN, MAX_TOPIC_LEN, MAX_USER_NAME_LEN, MIN_PASSWORD_LEN};
+use sophia_core::model::Response;
+
+/// check message content before it is stored and fanned out to other users,
+/// returns the error response if the content is rejected
+pub fn message(content: &str) -> Option<Response> {
+    if content.trim().is_empty() {
+        return Some(Response::new(code::MESSAGE_EMPTY, "message is empty".to_string()));
+    }
+
+    let len = content.chars().count();
+    if len > MAX_MSG_LEN {
+        let msg = format!("message has {} chars, must be at most {}", len, MAX_MSG_LEN);
+        return Some(Response::new(code::MESSAGE_TOO_LONG, msg));
+    }
+
+    // escape sequences would be interpreted by the other users' terminals, line breaks are fine
+    if let Some(c) = content.chars().find(|c| *c != '\n' && is_forbidden_char(*c)) {
+        let msg = format!("message contains invalid char {:?}", c);
+        return Some(Response::new(code::MESSAGE_INVALID_CHAR, msg));
+    }
+
+    None
+}
+
+
+/// check user name at login, returns the error response if the name is rejected
+pub fn user_name(name: &str) -> Option<Response> {
+    if name.is_empty() {
+        return Some(Response::new(code::USER_NAME_EMPTY, "user name is empty".to_string()));
+    }
+
+    let len = name.chars().count();
+    if len > MAX_USER_NAME_LEN {
+        let msg = format!("user name has {} chars, must be at most {}", len, MAX_USER_NAME_LEN);
+        return Some(Response::new(code::USER_NAME_TOO_LONG, msg));
+    }
+
+    if let Some(c) = name.chars().find(|c| !is_user_name_char(*c)) {
+        let msg = format!("user name contains invalid char {:?}, only letters, digits, '_', '-' and '.' are allowed", c);
+        return Some(Response::new(code::USER_NAME_INVALID_CHAR, msg));
+    }
+
+    None
+}
+
+
+/// check a custom status text, empty clears the text
+pub fn status_text(text: &str) -> Option<Response> {
+    let len = text.chars().count();
+    if len > MAX_STATUS_TEXT_LEN {
+        let msg = format!("status text has {} chars, must be at most {}", len, MAX_STATUS_TEXT_LEN);
+        return Some(Response::new(code::STATUS_TEXT_TOO_LONG, msg));
+    }
+
+    if let Some(c) = text.chars().find(|c| is_forbidden_char(*c)) {
+        let msg = format!("status text contains invalid char {:?}", c);
+        return Some(Response::new(code::STATUS_TEXT_INVALID_CHAR, msg));
+    }
+
+    None
+}
+
+
+/// check a chat topic, empty clears the topic
+pub fn topic(topic: &str) -> Option<Response> {
+    let len = topic.chars().count();
+    if len > MAX_TOPIC_LEN {
+        let msg = format!("topic has {} chars, must be at most {}", len, MAX_TOPIC_LEN);
+        return Some(Response::new(code::TOPIC_TOO_LONG, msg));
+    }
+
+    if let Some(c) = topic.chars().find(|c| is_forbidden_char(*c)) {
+        let msg = format!("topic contains invalid char {:?}", c);
+        return Some(Response::new(code::TOPIC_INVALID_CHAR, msg));
+    }
+
+    None
+}
+
+
+/// a reaction is a short run of non whitespace chars, usually a single emoji
+pub fn emoji(emoji: &str) -> Option<Response> {
+    let len = emoji.chars().count();
+    if len == 0 || len > MAX_EMOJI_LEN {
+        let msg = format!("reaction must have 1 to {} chars", MAX_EMOJI_LEN);
+        return Some(Response::new(code::REACTION_INVALID, msg));
+    }
+
+    if let Some(c) = emoji.chars().find(|c| c.is_whitespace() || is_forbidden_char(*c)) {
+        let msg = format!("reaction contains invalid char {:?}", c);
+        return Some(Response::new(code::REACTION_INVALID, msg));
+    }
+
+    None
+}
+
+
+/// check the seconds of a ban or mute, returns the error response if they are rejected
+pub fn duration(secs: i64) -> Option<Response> {
+    if secs <= 0 || secs > MAX_MODERATION_SECS {
+        let msg = format!("duration must be 1s to {}d", MAX_MODERATION_SECS / 86400);
+        return Some(Response::new(code::DURATION_INVALID, msg));
+    }
+
+    None
+}
+
+
+fn is_forbidden_char(c: char) -> bool {
+    // control chars (ESC, BEL, C1 ...) plus the bidi overrides that can reorder displayed text
+    c.is_control() || matches!(c, '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}')
+}
+
+pub fn is_user_name_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.')
+}
+
+
+/// check the password of a new account, returns the error response if it is rejected
+pub fn password(password: &str) -> Option<Response> {
+    let len = password.chars().count();
+    if len < MIN_PASSWORD_LEN {
+        let msg = format!("password has {} chars, must be at least {}", len, MIN_PASSWORD_LEN);
+        return Some(Response::new(code::PASSWORD_TOO_SHORT, msg));
+    }
+
+    None
+}
+
+
+/// check an offered file before the upload starts, returns the error response if it is rejected
+pub fn file(name: &str, size: u64, hash: &str) -> Option<Response> {
+    if size == 0 || size > MAX_FILE_SIZE {
+        let msg = format!("file has {} bytes, must be 1 to {}", size, MAX_FILE_SIZE);
+        return Some(Response::new(code::FILE_TOO_LARGE, msg));
+    }
+
+    // the name ends up in a path on the downloading side
+    let len = name.chars().count();
+    if name.trim().is_empty() || len > MAX_FILE_NAME_LEN || name == "." || name == ".."
+        || name.chars().any(|c| is_forbidden_char(c) || c == '/' || c == '\\') {
+        let msg = format!("file name must be 1 to {} chars without path separators", MAX_FILE_NAME_LEN);
+        return Some(Response::new(code::FILE_INVALID, msg));
+    }
+
+    if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Some(Response::new(code::FILE_INVALID, "file hash must be a hex sha256".to_string()));
+    }
+
+    None
+}
+
+
+#[cfg(test)]
+mod tests {
+    use sophia_core::consts::{code, MAX_MODERATION_SECS};
+
+    use super::duration;
+
+    #[test]
+    fn duration_in_range() {
+        assert!(duration(1).is_none());
+        assert!(duration(3600).is_none());
+        assert!(duration(MAX_MODERATION_SECS).is_none());
+    }
+
+    #[test]
+    fn duration_out_of_range() {
+        for secs in [0, -1, i64::MIN, MAX_MODERATION_SECS + 1, i64::MAX] {
+            assert_eq!(duration(secs).map(|r| r.code), Some(code::DURATION_INVALID), "{}", secs);
+        }
+    }
+}
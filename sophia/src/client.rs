@@ -2,13 +2,15 @@ use std::io::Stdout;
 use std::sync::Arc;
 
 use crossterm::event;
-use crossterm::event::KeyCode;
+use crossterm::event::{KeyCode, KeyModifiers, MouseButton, MouseEvent};
 use log::Level;
 use tokio::sync::{mpsc, RwLock};
 use tokio::sync::mpsc::{Receiver, Sender};
 
 use sophia_core::command;
+use sophia_core::consts::MAX_MSG_LEN;
 use sophia_core::errors::Result;
+use sophia_core::model::MessageKind;
 use sophia_net::quic;
 
 use crate::config;
@@ -47,19 +49,33 @@ pub async fn io_run_loop(conf: config::Config, sender: Sender<Arc<RwLock<AppView
         .with_server_addr(conf.server_addr)
         .with_server_name(conf.server_name);
 
-    let login = command::Login {
+    let mut login = command::Login {
         user_name: conf.user_name,
         chat_id: conf.chat_id,
         password: conf.password,
     };
 
 
+    let mut registration = conf.register.then(|| command::Registration {
+        user_name: login.user_name.to_string(),
+        password: login.password.to_string(),
+    });
+
+
+    controller.load_history().await;
+
     let controller1 = controller.clone();
     tokio::spawn(async move {
         keyboard_event(controller1).await;
     });
 
 
+    let controller2 = controller.clone();
+    tokio::spawn(async move {
+        controller2.watch_idle().await;
+    });
+
+
     controller.log(Level::Info, format!("TIPS: Press the 'ESC' key to EXIT")).await;
     loop {
         if controller.stop_accept_stream().await {
@@ -78,10 +94,28 @@ pub async fn io_run_loop(conf: config::Config, sender: Sender<Arc<RwLock<AppView
         let conn = res.unwrap();
         controller.set_conn(conn.clone()).await;
 
+        // a reconnect goes back to the chat we switched to, the name we changed to is taken again after the login,
+        // which is made with the name the password belongs to
+        let conf = controller.get_view_model().await.conf;
+        login.chat_id = conf.chat_id;
+        let nick = conf.user_name;
+
         controller.log(Level::Info, format!("attempting to log in with username ({}) to the chat room ({}), please wait"
                                             , login.user_name, login.chat_id)).await;
 
 
+        // only once, a reconnect logs in to the account created before
+        if let Some(cmd) = registration.take() {
+            match controller.register(cmd).await {
+                Ok(true) => controller.log(Level::Info, format!("account {} registered.", login.user_name)).await,
+                Ok(false) => controller.log(Level::Warn, format!("account {} already exists, logging in", login.user_name)).await,
+                Err(e) => {
+                    controller.log(Level::Error, format!("register failed , err = {}", e)).await;
+                    break;
+                }
+            }
+        }
+
         let res = controller.login(login.clone()).await;
         if let Err(e) = res {
             controller.log(Level::Error, format!("login failed , err = {}", e)).await;
@@ -97,6 +131,25 @@ pub async fn io_run_loop(conf: config::Config, sender: Sender<Arc<RwLock<AppView
             *s = session_id;
         }
 
+        if nick != login.user_name {
+            if let Err(e) = controller.change_nick(&nick).await {
+                controller.log(Level::Warn, format!("could not change the name to {} again, err = {}", nick, e)).await;
+                controller.rename_user(&nick, &login.user_name).await;
+            }
+        }
+
+
+        let datagram_conn = conn.clone();
+        let datagram_controller = controller.clone();
+        tokio::spawn(async move {
+            let _ = datagram_conn.accept_datagrams(datagram_controller).await;
+        });
+
+        let stream_conn = conn.clone();
+        let stream_controller = controller.clone();
+        tokio::spawn(async move {
+            let _ = stream_conn.accept_uni_streams(stream_controller).await;
+        });
 
         // let res = accept_request(conn, controller).await;
         let res = conn.accept_request(controller.clone()).await;
@@ -120,41 +173,131 @@ async fn keyboard_event(controller: Controller) {
 
         let ev = result.unwrap();
         match ev {
-            event::Event::Key(event::KeyEvent { code, .. }) => {
+            event::Event::Key(event::KeyEvent { code, modifiers }) => {
                 if code == KeyCode::Esc {
-                    exit_app(&controller).await;
+                    // Esc first backs out of selecting / replying
+                    if controller.cancel_selection().await {
+                        controller.refresh().await;
+                        continue;
+                    }
+
+                    controller.quit().await;
                     return;
                 }
 
-                handle_key(code, &controller).await;
+                controller.user_active().await;
+                handle_key(code, modifiers, &controller).await;
+
+                // `/quit` or `/leave` in the first room
+                if controller.quitting().await {
+                    return;
+                }
+            }
+            event::Event::Mouse(ev) => {
+                controller.user_active().await;
+                handle_mouse(ev, &controller).await;
             }
             event::Event::Resize(_, _) => {
                 // controller.log(Level::Info, format!("resize to {}x{}", w, h)).await;
                 controller.refresh().await;
             }
-            _ => ()
         }
     }
 }
 
-async fn exit_app(controller: &Controller) {
-    let mut exit_app = controller.exit_app.write().await;
-    *exit_app = true;
-    controller.log(Level::Info, "bye~~".to_string()).await;
-    let conn = controller.opt_conn().await;
-    if conn.is_none() {
-        return;
+async fn handle_mouse(ev: MouseEvent, controller: &Controller) {
+    match ev {
+        MouseEvent::ScrollUp(column, row, _) => controller.mouse_scroll(column, row, KeyCode::Up).await,
+        MouseEvent::ScrollDown(column, row, _) => controller.mouse_scroll(column, row, KeyCode::Down).await,
+        MouseEvent::Down(button, column, row, _) => controller.mouse_down(button, column, row).await,
+        MouseEvent::Drag(MouseButton::Left, column, row, _) => controller.mouse_drag(column, row).await,
+        MouseEvent::Up(MouseButton::Left, _, _, _) => controller.mouse_up().await,
+        _ => return,
     }
 
-    conn.as_ref().unwrap().closed().await;
+    controller.refresh().await;
 }
 
-async fn handle_key(code: KeyCode, controller: &Controller) {
+async fn handle_key(code: KeyCode, modifiers: KeyModifiers, controller: &Controller) {
     // controller.log(Level::Info, format!("key code {:?} ", code)).await;
 
+    match code {
+        KeyCode::Tab | KeyCode::BackTab => {}
+        _ => controller.end_completion().await,
+    }
+    controller.clear_mouse_selection().await;
+
+    // the reaction picker takes the keys while it is open
+    if controller.picker_open().await {
+        match code {
+            KeyCode::Left | KeyCode::Right => controller.picker_move(code).await,
+            KeyCode::Enter => controller.picker_choose(None).await,
+            KeyCode::Char(c @ '1'..='9') => controller.picker_choose(Some(c as usize - '1' as usize)).await,
+            _ => {}
+        }
+
+        controller.refresh().await;
+        return;
+    }
+
+    // so does the search result list
+    if controller.search_open().await {
+        match code {
+            KeyCode::Up | KeyCode::Down => controller.search_move(code).await,
+            KeyCode::Enter => controller.search_jump().await,
+            _ => {}
+        }
+
+        controller.refresh().await;
+        return;
+    }
+
+    // readline style editing, Up / Down recall sent input so the message pane scrolls with Ctrl
+    if modifiers.contains(KeyModifiers::CONTROL) {
+        match code {
+            KeyCode::Char('w') => controller.input_kill_word(false).await,
+            KeyCode::Char('k') => controller.input_kill_line(true).await,
+            KeyCode::Char('u') => controller.input_kill_line(false).await,
+            KeyCode::Char('y') => controller.input_yank().await,
+            KeyCode::Char('a') => controller.input_move_cursor(KeyCode::Home).await,
+            KeyCode::Char('e') => controller.input_move_cursor(KeyCode::End).await,
+            KeyCode::Up | KeyCode::Down => controller.scroll(code).await,
+            // oldest / newest loaded message
+            KeyCode::Home | KeyCode::End => controller.scroll(code).await,
+            _ => {}
+        }
+
+        controller.refresh().await;
+        return;
+    }
+
+    if modifiers.contains(KeyModifiers::ALT) {
+        match code {
+            // select a message in the scrollback
+            KeyCode::Up | KeyCode::Down => controller.select_message(code).await,
+            KeyCode::Char('b') => controller.input_move_word(false).await,
+            KeyCode::Char('f') => controller.input_move_word(true).await,
+            KeyCode::Char('d') => controller.input_kill_word(true).await,
+            KeyCode::Backspace => controller.input_kill_word(false).await,
+            // reply to the selected message
+            KeyCode::Char('r') => controller.reply_selected().await,
+            // react to the selected message
+            KeyCode::Char('e') => controller.open_picker().await,
+            // jump to the previous message mentioning us
+            KeyCode::Char('m') => controller.select_previous_mention().await,
+            // line break, for terminals that don't report Shift+Enter
+            KeyCode::Enter => controller.input_write('\n').await,
+            _ => {}
+        }
+
+        controller.refresh().await;
+        return;
+    }
+
     match code {
         KeyCode::Char(character) => {
             controller.input_write(character).await;
+            controller.typing().await;
         }
         KeyCode::Delete => {
             controller.input_remove().await;
@@ -165,8 +308,20 @@ async fn handle_key(code: KeyCode, controller: &Controller) {
         KeyCode::Left | KeyCode::Right | KeyCode::Home | KeyCode::End => {
             controller.input_move_cursor(code).await;
         }
-        KeyCode::Up | KeyCode::Down | KeyCode::PageUp => {
-            controller.messages_scroll(code).await;
+        KeyCode::Up | KeyCode::Down => {
+            controller.input_history(code).await;
+        }
+        KeyCode::PageUp | KeyCode::PageDown => {
+            controller.scroll(code).await;
+        }
+        KeyCode::Tab => {
+            controller.complete(false).await;
+        }
+        KeyCode::BackTab => {
+            controller.complete(true).await;
+        }
+        KeyCode::Enter if modifiers.contains(KeyModifiers::SHIFT) => {
+            controller.input_write('\n').await;
         }
         KeyCode::Enter => {
             send_message(&controller).await;
@@ -177,11 +332,14 @@ async fn handle_key(code: KeyCode, controller: &Controller) {
     controller.refresh().await;
 }
 
-const MAX_MSG_LEN: usize = 1024;
-
 async fn send_message(ctrl: &Controller) {
     let vm = ctrl.get_view_model().await;
-    if vm.input_vm.text.len() == 0 {
+    if vm.input_vm.text.iter().all(|c| c.is_whitespace()) {
+        return;
+    }
+
+    if let Some(remaining) = ctrl.throttle_remaining().await {
+        ctrl.log(Level::Warn, format!("sending too fast, retry in {}s", remaining.as_secs() + 1)).await;
         return;
     }
 
@@ -192,7 +350,14 @@ async fn send_message(ctrl: &Controller) {
 
     let msg: String = vm.input_vm.text.iter().collect();
     let chat_id = vm.conf.chat_id;
-    let res = ctrl.send_msg(&msg, chat_id).await;
+    ctrl.remember_input(&msg).await;
+    if msg.starts_with('/') {
+        ctrl.commands.run(ctrl, &msg).await;
+        return;
+    }
+
+    let reply_to = vm.input_vm.reply_to.as_ref().map(|r| r.id);
+    let res = ctrl.send_msg(&msg, chat_id, reply_to, MessageKind::Text).await;
     if let Err(e) = res {
         ctrl.log(Level::Error, format!("send msg error : {}", e)).await;
         return;
@@ -201,5 +366,3 @@ async fn send_message(ctrl: &Controller) {
     ctrl.clean_input().await;
 }
 
-
-
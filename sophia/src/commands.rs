@@ -0,0 +1,430 @@
+use futures_util::future::BoxFuture;
+use log::Level;
+
+use sophia_core::command::Command;
+use sophia_core::errors::Errno;
+use sophia_core::model::{MessageKind, Role, Status};
+
+use crate::controller::Caller;
+use crate::controller::Controller;
+use crate::view_model::Message;
+
+macro_rules! command {
+    ($name:expr, $usage:expr, $help:expr, $function:expr) => {
+        SlashCommand { name: $name, usage: $usage, help: $help, run: |ctrl, args| Box::pin($function(ctrl, args)) }
+    };
+}
+
+/// why a command did not run, reported to the log panel
+pub enum CommandError {
+    /// the arguments do not fit, the usage is shown
+    Usage,
+    /// e.g. there is no message to edit
+    Invalid(String),
+    Failed(Errno),
+}
+
+impl From<Errno> for CommandError {
+    fn from(e: Errno) -> Self {
+        CommandError::Failed(e)
+    }
+}
+
+type Outcome = Result<(), CommandError>;
+
+/// gets the input after the command name, trimmed
+type Run = fn(Controller, String) -> BoxFuture<'static, Outcome>;
+
+pub struct SlashCommand {
+    /// without the leading `/`
+    pub name: &'static str,
+    pub usage: &'static str,
+    pub help: &'static str,
+    run: Run,
+}
+
+/// The commands that can be typed into the input, `/help` lists them in registration order.
+pub struct Registry {
+    commands: Vec<SlashCommand>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        let mut registry = Self { commands: Vec::new() };
+        registry.register_builtin();
+
+        registry
+    }
+
+    fn register_builtin(&mut self) {
+        self.register(command!("join", "/join <chat_id>", "switch to another chat room", join));
+        self.register(command!("leave", "/leave", "go back to the previous room, or quit in the first one", leave));
+        self.register(command!("nick", "/nick <name>", "change your user name", nick));
+        self.register(command!("me", "/me <action>", "describe what you are doing", me));
+        self.register(command!("msg", "/msg <user> <text>", "direct message to a registered user in any room, or a guest in this one", direct_message));
+        self.register(command!("topic", "/topic [text]", "show the topic, moderators can set it", topic));
+        self.register(command!("status", "/status <online|away|busy> [text]", "set your presence", set_status));
+        self.register(command!("react", "/react <emoji>", "toggle a reaction on the selected or newest message", react));
+        self.register(command!("edit", "/edit <text>", "replace your last message", edit));
+        self.register(command!("delete", "/delete", "delete your last message", delete));
+        self.register(command!("search", "/search <words> [\"phrase\"] [from:user] [since:yyyy-mm-dd] [until:yyyy-mm-dd]",
+            "search the messages of the room", search));
+        self.register(command!("upload", "/upload <path>", "share a file with the room", upload));
+        self.register(command!("download", "/download <id> <dest>", "save a shared file", download));
+        self.register(command!("kick", "/kick <user>", "disconnect a user from the room", kick));
+        self.register(command!("ban", "/ban <user> [duration]", "ban a user, e.g. for 15m, 2h or 7d", ban));
+        self.register(command!("unban", "/unban <user>", "lift a ban", unban));
+        self.register(command!("mute", "/mute <user> <duration>", "mute a user, e.g. for 15m", mute));
+        self.register(command!("op", "/op <user>", "make a user moderator (owner only)", op));
+        self.register(command!("deop", "/deop <user>", "make a moderator a member again (owner only)", deop));
+        self.register(command!("clear", "/clear", "clear the message pane", clear));
+        self.register(command!("help", "/help [command]", "list the commands", help));
+        self.register(command!("quit", "/quit", "exit, like Esc", quit));
+    }
+
+    /// a command registered under an existing name replaces it
+    pub fn register(&mut self, cmd: SlashCommand) {
+        match self.commands.iter_mut().find(|c| c.name == cmd.name) {
+            Some(existing) => *existing = cmd,
+            None => self.commands.push(cmd),
+        }
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.commands.iter().map(|c| c.name).collect()
+    }
+
+    pub fn find(&self, name: &str) -> Option<&SlashCommand> {
+        self.commands.iter().find(|c| c.name.eq_ignore_ascii_case(name))
+    }
+
+    /// run the command in `input`, which starts with `/`. The input is cleared if it ran,
+    /// otherwise the problem goes to the log panel and the input is kept to be fixed
+    pub async fn run(&self, ctrl: &Controller, input: &str) {
+        let input = input.trim().trim_start_matches('/');
+        let (name, args) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+
+        let cmd = match self.find(name) {
+            Some(cmd) => cmd,
+            None => {
+                ctrl.log(Level::Warn, format!("unknown command /{}, /help lists the commands", name)).await;
+                return;
+            }
+        };
+
+        match (cmd.run)(ctrl.clone(), args.trim().to_string()).await {
+            Ok(()) => ctrl.clean_input().await,
+            Err(CommandError::Usage) => ctrl.log(Level::Warn, format!("usage: {}", cmd.usage)).await,
+            Err(CommandError::Invalid(msg)) => ctrl.log(Level::Warn, format!("/{}: {}", cmd.name, msg)).await,
+            Err(CommandError::Failed(e)) => ctrl.log(Level::Error, format!("/{} failed : {}", cmd.name, e)).await,
+        }
+    }
+
+    /// usage and help of every command, or of the one named, as lines for the message pane
+    fn help_lines(&self, name: &str) -> Option<Vec<String>> {
+        let commands: Vec<&SlashCommand> = match name {
+            "" => self.commands.iter().collect(),
+            name => vec![self.find(name.trim_start_matches('/'))?],
+        };
+
+        // the long `/search` usage would push every description off screen
+        let width = commands.iter().map(|c| c.usage.len()).filter(|len| *len <= 40).max().unwrap_or(0);
+        let lines = commands.iter()
+            .flat_map(|c| match c.usage.len() > width {
+                true => vec![c.usage.to_string(), format!("{:<width$}  {}", "", c.help, width = width)],
+                false => vec![format!("{:<width$}  {}", c.usage, c.help, width = width)],
+            })
+            .collect();
+
+        Some(lines)
+    }
+}
+
+
+async fn join(ctrl: Controller, args: String) -> Outcome {
+    let chat_id = args.parse().map_err(|_| CommandError::Usage)?;
+    ctrl.join_chat(chat_id).await?;
+
+    Ok(())
+}
+
+async fn leave(ctrl: Controller, _args: String) -> Outcome {
+    if !ctrl.leave_chat().await? {
+        ctrl.quit().await;
+    }
+
+    Ok(())
+}
+
+async fn nick(ctrl: Controller, args: String) -> Outcome {
+    if args.is_empty() || args.contains(char::is_whitespace) {
+        return Err(CommandError::Usage);
+    }
+
+    ctrl.change_nick(&args).await?;
+
+    Ok(())
+}
+
+/// shown as `* alice waves`
+async fn me(ctrl: Controller, args: String) -> Outcome {
+    if args.is_empty() {
+        return Err(CommandError::Usage);
+    }
+
+    let chat_id = ctrl.chat_id().await;
+    ctrl.send_msg(&args, chat_id, None, MessageKind::Emote).await?;
+
+    Ok(())
+}
+
+async fn direct_message(ctrl: Controller, args: String) -> Outcome {
+    let (to, msg) = match args.split_once(char::is_whitespace) {
+        Some((to, msg)) if !msg.trim().is_empty() => (to.to_string(), msg.trim().to_string()),
+        _ => return Err(CommandError::Usage),
+    };
+    ctrl.request(Command::SendDirectMessage { to, msg }).await?;
+
+    Ok(())
+}
+
+/// without text the current topic is shown in the log
+async fn topic(ctrl: Controller, args: String) -> Outcome {
+    let vm = ctrl.get_view_model().await;
+    if args.is_empty() {
+        let msg = match vm.msg_vm.topic.as_str() {
+            "" => format!("chat {} has no topic", vm.conf.chat_id),
+            topic => format!("topic of chat {}: {}", vm.conf.chat_id, topic),
+        };
+        ctrl.log(Level::Info, msg).await;
+        return Ok(());
+    }
+
+    ctrl.request(Command::SetTopic { chat_id: vm.conf.chat_id, topic: args }).await?;
+
+    Ok(())
+}
+
+async fn set_status(ctrl: Controller, args: String) -> Outcome {
+    let (status, text) = args.split_once(char::is_whitespace).unwrap_or((&args, ""));
+    let status = match status {
+        "online" => Status::Online,
+        "away" => Status::Away,
+        "busy" => Status::Busy,
+        _ => return Err(CommandError::Usage),
+    };
+
+    let chat_id = ctrl.chat_id().await;
+    ctrl.set_status(status, text.trim().to_string(), chat_id).await?;
+
+    Ok(())
+}
+
+/// toggles a reaction on the selected message, or else the newest one
+async fn react(ctrl: Controller, args: String) -> Outcome {
+    if args.is_empty() || args.contains(char::is_whitespace) {
+        return Err(CommandError::Usage);
+    }
+
+    let id = ctrl.reaction_target().await
+        .ok_or_else(|| CommandError::Invalid("there is no message to react to".to_string()))?;
+    ctrl.toggle_reaction(id, &args).await?;
+
+    Ok(())
+}
+
+async fn edit(ctrl: Controller, args: String) -> Outcome {
+    if args.is_empty() {
+        return Err(CommandError::Usage);
+    }
+
+    let id = last_own_message(&ctrl).await?;
+    ctrl.request(Command::EditMessage { id, content: args }).await?;
+
+    Ok(())
+}
+
+async fn delete(ctrl: Controller, _args: String) -> Outcome {
+    let id = last_own_message(&ctrl).await?;
+    ctrl.request(Command::DeleteMessage { id }).await?;
+
+    Ok(())
+}
+
+async fn last_own_message(ctrl: &Controller) -> Result<i64, CommandError> {
+    ctrl.last_own_message().await
+        .ok_or_else(|| CommandError::Invalid("you have not sent a message yet".to_string()))
+}
+
+async fn search(ctrl: Controller, args: String) -> Outcome {
+    if args.is_empty() {
+        return Err(CommandError::Usage);
+    }
+
+    ctrl.search(&args).await?;
+
+    Ok(())
+}
+
+/// the transfer runs in the background, the path may contain spaces
+async fn upload(ctrl: Controller, args: String) -> Outcome {
+    if args.is_empty() {
+        return Err(CommandError::Usage);
+    }
+
+    tokio::spawn(async move {
+        if let Err(e) = ctrl.upload(&args).await {
+            ctrl.log(Level::Error, format!("/upload failed : {}", e)).await;
+        }
+    });
+
+    Ok(())
+}
+
+async fn download(ctrl: Controller, args: String) -> Outcome {
+    let (id, dest) = match args.split_once(char::is_whitespace) {
+        Some((id, dest)) => (id.parse::<i64>().map_err(|_| CommandError::Usage)?, dest.trim().to_string()),
+        None => return Err(CommandError::Usage),
+    };
+
+    tokio::spawn(async move {
+        if let Err(e) = ctrl.download(id, &dest).await {
+            ctrl.log(Level::Error, format!("/download failed : {}", e)).await;
+        }
+    });
+
+    Ok(())
+}
+
+async fn kick(ctrl: Controller, args: String) -> Outcome {
+    let user_name = single_user(&args)?;
+    let chat_id = ctrl.chat_id().await;
+    ctrl.request(Command::Kick { chat_id, user_name }).await?;
+
+    Ok(())
+}
+
+async fn ban(ctrl: Controller, args: String) -> Outcome {
+    let (user_name, duration) = match args.split_whitespace().collect::<Vec<_>>()[..] {
+        [user_name] => (user_name.to_string(), None),
+        [user_name, duration] => (user_name.to_string(), Some(parse_duration(duration).ok_or(CommandError::Usage)?)),
+        _ => return Err(CommandError::Usage),
+    };
+
+    let chat_id = ctrl.chat_id().await;
+    ctrl.request(Command::Ban { chat_id, user_name, duration }).await?;
+
+    Ok(())
+}
+
+async fn unban(ctrl: Controller, args: String) -> Outcome {
+    let user_name = single_user(&args)?;
+    let chat_id = ctrl.chat_id().await;
+    ctrl.request(Command::Unban { chat_id, user_name }).await?;
+
+    Ok(())
+}
+
+async fn mute(ctrl: Controller, args: String) -> Outcome {
+    let (user_name, duration) = match args.split_whitespace().collect::<Vec<_>>()[..] {
+        [user_name, duration] => (user_name.to_string(), parse_duration(duration).ok_or(CommandError::Usage)?),
+        _ => return Err(CommandError::Usage),
+    };
+
+    let chat_id = ctrl.chat_id().await;
+    ctrl.request(Command::Mute { chat_id, user_name, duration }).await?;
+
+    Ok(())
+}
+
+async fn op(ctrl: Controller, args: String) -> Outcome {
+    set_role(ctrl, args, Role::Moderator).await
+}
+
+async fn deop(ctrl: Controller, args: String) -> Outcome {
+    set_role(ctrl, args, Role::Member).await
+}
+
+async fn set_role(ctrl: Controller, args: String, role: Role) -> Outcome {
+    let user_name = single_user(&args)?;
+    let chat_id = ctrl.chat_id().await;
+    ctrl.request(Command::SetRole { chat_id, user_name, role }).await?;
+
+    Ok(())
+}
+
+fn single_user(args: &str) -> Result<String, CommandError> {
+    match args.split_whitespace().collect::<Vec<_>>()[..] {
+        [user_name] => Ok(user_name.to_string()),
+        _ => Err(CommandError::Usage),
+    }
+}
+
+async fn clear(ctrl: Controller, _args: String) -> Outcome {
+    ctrl.clear_messages().await;
+
+    Ok(())
+}
+
+/// the list goes to the message pane, `/clear` removes it again
+async fn help(ctrl: Controller, args: String) -> Outcome {
+    let lines = ctrl.commands.help_lines(&args)
+        .ok_or_else(|| CommandError::Invalid(format!("unknown command {}", args)))?;
+
+    let now = chrono::Utc::now().timestamp();
+    for line in lines {
+        ctrl.push_message(Message::notice(now, line)).await;
+    }
+
+    Ok(())
+}
+
+async fn quit(ctrl: Controller, _args: String) -> Outcome {
+    ctrl.quit().await;
+
+    Ok(())
+}
+
+/// `90`, `90s`, `15m`, `2h`, `7d` to seconds
+fn parse_duration(s: &str) -> Option<i64> {
+    let (num, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(idx) => s.split_at(idx),
+        None => (s, "s"),
+    };
+
+    let num: i64 = num.parse().ok()?;
+    let unit = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        _ => return None,
+    };
+
+    num.checked_mul(unit)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::parse_duration;
+
+    #[test]
+    fn parse_duration_units() {
+        assert_eq!(parse_duration("90"), Some(90));
+        assert_eq!(parse_duration("90s"), Some(90));
+        assert_eq!(parse_duration("15m"), Some(900));
+        assert_eq!(parse_duration("2h"), Some(7200));
+        assert_eq!(parse_duration("7d"), Some(604800));
+    }
+
+    #[test]
+    fn parse_duration_rejects_garbage() {
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("m"), None);
+        assert_eq!(parse_duration("5w"), None);
+        assert_eq!(parse_duration("-5m"), None);
+        assert_eq!(parse_duration("1.5h"), None);
+        assert_eq!(parse_duration("9223372036854775807d"), None);
+    }
+}
@@ -13,6 +13,14 @@ pub struct Config {
     pub theme: String,
     pub chat_id: i64,
     pub password: String,
+    /// register the user name with the password before the first login
+    pub register: bool,
+    /// seconds of keyboard inactivity before going away, 0 disables it
+    pub away_after: u64,
+    /// bell, osc or off
+    pub notify: String,
+    /// file keeping the sent input across sessions, empty keeps it in memory only
+    pub history: String,
 }
 
 
@@ -26,7 +34,11 @@ impl Config {
             user_name: args.user_name,
             chat_id: args.chat_id,
             password: args.password,
+            register: args.register,
             theme: args.theme,
+            away_after: args.away_after,
+            notify: args.notify,
+            history: expand_home(args.history),
         };
 
         if config.user_name.len() == 0 {
@@ -51,3 +63,12 @@ impl Config {
     //     }
     // }
 }
+
+
+/// `~/` at the start of a path is the home directory
+fn expand_home(path: String) -> String {
+    match (path.strip_prefix("~/"), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => format!("{}/{}", home, rest),
+        _ => path,
+    }
+}
@@ -1,16 +1,24 @@
+use std::time::Duration;
+
 use async_trait::async_trait;
 
 use sophia_core::{command, errno};
-use sophia_core::command::Command;
+use sophia_core::command::{Command, CommandResult};
+use sophia_core::consts::code;
 use sophia_core::errors::Result;
-use sophia_core::model::{Request, Response};
+use sophia_core::model::{MessageKind, Request, Response, Status};
 
 use super::controller::Controller;
 
 #[async_trait]
 pub trait Caller {
     async fn login(&self, cmd: command::Login) -> Result<String>;
-    async fn send_msg(&self, msg: &str, chat_id: i64) -> Result<String>;
+    /// returns `false` if the account already exists
+    async fn register(&self, cmd: command::Registration) -> Result<bool>;
+    async fn send_msg(&self, msg: &str, chat_id: i64, reply_to: Option<i64>, kind: MessageKind) -> Result<String>;
+    async fn set_status(&self, status: Status, text: String, chat_id: i64) -> Result<()>;
+    /// send a command with the current session, returns the response on success
+    async fn request(&self, cmd: Command) -> Result<Response>;
 }
 
 
@@ -30,20 +38,62 @@ impl Caller for Controller {
         return Ok(session_id);
     }
 
-    async fn send_msg(&self, msg: &str, chat_id: i64) -> Result<String> {
+    async fn register(&self, cmd: command::Registration) -> Result<bool> {
         if self.not_connect().await {
             return errno!("connect failed")
         }
 
-        let mut req = Request::new(Command::SendTextMessage { msg: msg.to_string(), chat_id });
-        req.base.session_id = self.session_id.read().await.to_string();
+        let req = Request::new(Command::Register(cmd));
 
         let resp = self.conn().await.send(req).await?;
+        if resp.code == code::ACCOUNT_EXISTS {
+            return Ok(false);
+        }
         let _ = if_response_code_not_zero_return_err(&resp)?;
+
+        Ok(true)
+    }
+
+    async fn send_msg(&self, msg: &str, chat_id: i64, reply_to: Option<i64>, kind: MessageKind) -> Result<String> {
+        let resp = self.request(Command::SendTextMessage { msg: msg.to_string(), chat_id, reply_to, kind }).await?;
         let session_id = resp.msg;
 
         return Ok(session_id);
     }
+
+    async fn set_status(&self, status: Status, text: String, chat_id: i64) -> Result<()> {
+        self.request(Command::SetStatus { chat_id, status, text }).await?;
+
+        Ok(())
+    }
+
+    async fn request(&self, cmd: Command) -> Result<Response> {
+        if self.not_connect().await {
+            return errno!("connect failed")
+        }
+
+        let mut req = Request::new(cmd);
+        req.base.session_id = self.session_id.read().await.to_string();
+
+        let resp = self.conn().await.send(req).await?;
+        if let Some(retry_after) = retry_after(&resp) {
+            self.throttle(retry_after).await;
+        }
+        let _ = if_response_code_not_zero_return_err(&resp)?;
+
+        Ok(resp)
+    }
+}
+
+fn retry_after(resp: &Response) -> Option<Duration> {
+    if resp.code != code::RATE_LIMITED {
+        return None;
+    }
+
+    match resp.data {
+        Some(CommandResult::RetryAfter(millis)) => Some(Duration::from_millis(millis)),
+        _ => Some(Duration::from_secs(1)),
+    }
 }
 
 fn if_response_code_not_zero_return_err(resp: &Response) -> Result<()> {
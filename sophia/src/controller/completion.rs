@@ -0,0 +1,101 @@
+/// what the word before the cursor can be completed to
+pub(super) struct Sources {
+    /// user names of the room, without our own
+    pub users: Vec<String>,
+    /// command names without the leading `/`
+    pub commands: Vec<&'static str>,
+    /// rooms we know of, for `/join`
+    pub rooms: Vec<i64>,
+}
+
+/// where the word before the cursor starts, and its completions followed by a separator:
+/// `/join ` for commands, `@alice ` for mentions, `alice: ` to address someone at the start of the input,
+/// the rooms after `/join` and bare user names anywhere else
+pub(super) fn candidates(before: &[char], sources: &Sources) -> Option<(usize, Vec<String>)> {
+    let start = before.iter().rposition(|c| c.is_whitespace()).map_or(0, |i| i + 1);
+    let word: String = before[start..].iter().collect();
+    let head: String = before[..start].iter().collect();
+
+    let mut candidates: Vec<String> = if start == 0 && word.starts_with('/') {
+        matching(sources.commands.iter().copied(), &word[1..]).map(|name| format!("/{} ", name)).collect()
+    } else if head.trim() == "/join" {
+        let rooms = sources.rooms.iter().map(|room| room.to_string()).collect::<Vec<_>>();
+        matching(rooms.iter().map(String::as_str), &word).map(|room| format!("{} ", room)).collect()
+    } else if let Some(prefix) = word.strip_prefix('@') {
+        matching(sources.users.iter().map(String::as_str), prefix).map(|name| format!("@{} ", name)).collect()
+    } else if word.is_empty() {
+        Vec::new()
+    } else if start == 0 {
+        matching(sources.users.iter().map(String::as_str), &word).map(|name| format!("{}: ", name)).collect()
+    } else {
+        matching(sources.users.iter().map(String::as_str), &word).map(|name| format!("{} ", name)).collect()
+    };
+
+    candidates.sort_by_key(|c| c.to_lowercase());
+    candidates.dedup();
+    match candidates.is_empty() {
+        true => None,
+        false => Some((start, candidates)),
+    }
+}
+
+/// case insensitive prefix match
+fn matching<'a>(names: impl Iterator<Item=&'a str>, prefix: &str) -> impl Iterator<Item=&'a str> {
+    let prefix = prefix.to_lowercase();
+    names.filter(move |name| name.to_lowercase().starts_with(&prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{candidates, Sources};
+
+    fn sources() -> Sources {
+        Sources {
+            users: vec![String::from("alice"), String::from("Alan"), String::from("bob")],
+            commands: vec!["join", "quit", "msg", "mute"],
+            rooms: vec![1, 12, 2],
+        }
+    }
+
+    fn complete(before: &str) -> Option<(usize, Vec<String>)> {
+        candidates(&before.chars().collect::<Vec<_>>(), &sources())
+    }
+
+    fn list(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn commands() {
+        assert_eq!(complete("/m"), Some((0, list(&["/msg ", "/mute "]))));
+        assert_eq!(complete("/"), Some((0, list(&["/join ", "/msg ", "/mute ", "/quit "]))));
+        // a command only at the start of the input
+        assert_eq!(complete("hi /q"), None);
+    }
+
+    #[test]
+    fn mentions() {
+        assert_eq!(complete("hi @al"), Some((3, list(&["@Alan ", "@alice "]))));
+        assert_eq!(complete("@B"), Some((0, list(&["@bob "]))));
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(complete("AL"), Some((0, list(&["Alan: ", "alice: "]))));
+        assert_eq!(complete("ask bo"), Some((4, list(&["bob "]))));
+    }
+
+    #[test]
+    fn join_rooms() {
+        assert_eq!(complete("/join 1"), Some((6, list(&["1 ", "12 "]))));
+        assert_eq!(complete("/join "), Some((6, list(&["1 ", "12 ", "2 "]))));
+    }
+
+    #[test]
+    fn nothing_to_complete() {
+        assert_eq!(complete(""), None);
+        assert_eq!(complete("hi "), None);
+        assert_eq!(complete("zed"), None);
+        assert_eq!(complete("/join 3"), None);
+    }
+}
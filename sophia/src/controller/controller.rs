@@ -1,25 +1,32 @@
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
-use crossterm::event::KeyCode;
+use crossterm::event::{KeyCode, MouseButton};
 use futures_util::future::BoxFuture;
 use log::Level;
 use tokio::sync::mpsc::Sender;
 use tokio::sync::RwLock;
 
-use sophia_core::command::CommandType;
+use sophia_core::command::{Command, CommandResult, CommandType};
+use sophia_core::consts::MAX_SEARCH_HITS;
 use sophia_core::errno_new;
 use sophia_core::errors::Result;
-use sophia_core::model::{Request, Response, User};
+use sophia_core::command::StreamHeader;
+use sophia_core::model::{FileOffer, Message as ModelMessage, Reaction, Request, Response, Status, User};
 use sophia_net::quic;
 
+use crate::commands::Registry;
 use crate::config;
+use crate::controller::Caller;
 use crate::controller::handler::HandlerImpl;
-use crate::view_model::AppViewModel;
-use crate::view_model::Message;
+use crate::view_model::{AppViewModel, Focus};
+use crate::view_model::{Message, PICKER_EMOJIS, ReplyTarget, SearchResults, SomeUser, TYPING_EXPIRE};
 
 use super::handler::Handler;
+use super::{completion, history, transfer};
 
 macro_rules! async_function {
     ($function:expr) => {
@@ -38,8 +45,45 @@ pub struct Controller {
     view_model: Arc<RwLock<AppViewModel>>,
     sender: Sender<Arc<RwLock<AppViewModel>>>,
     pub exit_app: Arc<RwLock<bool>>,
+    /// set by Esc or `/quit`, ends the keyboard loop
+    quit: Arc<RwLock<bool>>,
+    typing_sent: Arc<RwLock<Option<Instant>>>,
+    activity: Arc<RwLock<Activity>>,
+    /// newest message id we told the server we read
+    read_sent: Arc<RwLock<i64>>,
+    /// file id -> requested download, until its stream ended
+    pub(super) downloads: Arc<RwLock<HashMap<i64, Download>>>,
+    /// chats left by `/join`, `/leave` goes back to the last one
+    left_chats: Arc<RwLock<Vec<i64>>>,
+    pub commands: Arc<Registry>,
 }
 
+/// a `/download` waiting for its stream
+#[derive(Clone)]
+pub(super) struct Download {
+    pub offer: FileOffer,
+    pub dest: PathBuf,
+}
+
+/// keyboard activity, used to turn the status to away while idle
+struct Activity {
+    last_input: Instant,
+    /// status text to restore when we were set away automatically
+    auto_away: Option<String>,
+}
+
+/// new messages are marked read after this delay, so a burst ends up as one `MarkRead`
+const MARK_READ_DELAY: Duration = Duration::from_secs(1);
+
+/// how often the idle watcher looks at the keyboard activity
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// lines scrolled per notch of the mouse wheel
+const WHEEL_LINES: usize = 3;
+
+/// while typing, tell the others at most this often
+const TYPING_INTERVAL: Duration = Duration::from_secs(2);
+
 impl Controller {
     pub fn new(sender: Sender<Arc<RwLock<AppViewModel>>>, conf: config::Config) -> Self {
         let callbacks = HashMap::new();
@@ -50,6 +94,13 @@ impl Controller {
             view_model: Arc::new(RwLock::new(AppViewModel::new(conf))),
             sender,
             exit_app: Arc::new(RwLock::new(false)),
+            quit: Arc::new(RwLock::new(false)),
+            typing_sent: Arc::new(RwLock::new(None)),
+            activity: Arc::new(RwLock::new(Activity { last_input: Instant::now(), auto_away: None })),
+            read_sent: Arc::new(RwLock::new(0)),
+            downloads: Arc::new(RwLock::new(HashMap::new())),
+            left_chats: Arc::new(RwLock::new(Vec::new())),
+            commands: Arc::new(Registry::new()),
         };
         control.register_command();
 
@@ -87,6 +138,17 @@ impl Controller {
         self.register(CommandType::UserOnline, async_function!(HandlerImpl::user_online));
         self.register(CommandType::UserOffline, async_function!(HandlerImpl::user_offline));
         self.register(CommandType::ChatUserList, async_function!(HandlerImpl::chat_user_list_to_user));
+        self.register(CommandType::ModerationEvent, async_function!(HandlerImpl::moderation_event));
+        self.register(CommandType::SystemNotice, async_function!(HandlerImpl::system_notice));
+        self.register(CommandType::MessageEdited, async_function!(HandlerImpl::message_edited));
+        self.register(CommandType::MessageDeleted, async_function!(HandlerImpl::message_deleted));
+        self.register(CommandType::ReactionsChanged, async_function!(HandlerImpl::reactions_changed));
+        self.register(CommandType::ReadState, async_function!(HandlerImpl::read_state));
+        self.register(CommandType::ReadStates, async_function!(HandlerImpl::read_states));
+        self.register(CommandType::DirectMessage, async_function!(HandlerImpl::direct_message));
+        self.register(CommandType::Inbox, async_function!(HandlerImpl::inbox));
+        self.register(CommandType::ChatTopic, async_function!(HandlerImpl::chat_topic));
+        self.register(CommandType::NickChanged, async_function!(HandlerImpl::nick_changed));
     }
 
     fn get(&self, cmd_type: CommandType) -> Result<Callback> {
@@ -104,6 +166,105 @@ impl Controller {
         self.exit_app.read().await.clone()
     }
 
+    /// stop reconnecting and close the connection
+    pub async fn quit(&self) {
+        *self.quit.write().await = true;
+        *self.exit_app.write().await = true;
+        self.log(Level::Info, "bye~~".to_string()).await;
+
+        if let Some(conn) = self.opt_conn().await {
+            conn.closed().await;
+        }
+    }
+
+    pub async fn quitting(&self) -> bool {
+        *self.quit.read().await
+    }
+
+    pub async fn chat_id(&self) -> i64 {
+        self.view_model.read().await.conf.chat_id
+    }
+
+    /// switch to `chat_id`, `leave_chat` comes back to the current one
+    pub async fn join_chat(&self, chat_id: i64) -> Result<()> {
+        let current = self.chat_id().await;
+        self.switch_chat(chat_id).await?;
+        self.left_chats.write().await.push(current);
+
+        Ok(())
+    }
+
+    /// go back to the chat before the last `join_chat`, returns `false` if there is none
+    pub async fn leave_chat(&self) -> Result<bool> {
+        let previous = match self.left_chats.read().await.last() {
+            Some(previous) => *previous,
+            None => return Ok(false),
+        };
+
+        self.switch_chat(previous).await?;
+        self.left_chats.write().await.pop();
+
+        Ok(true)
+    }
+
+    /// the server pushes the user list, messages, read states and topic of the new chat
+    async fn switch_chat(&self, chat_id: i64) -> Result<()> {
+        self.request(Command::JoinChat { chat_id }).await?;
+
+        {
+            // the pushes may have overtaken the response, so only what points into the old chat is dropped
+            let mut vm = self.view_model.write().await;
+            vm.conf.chat_id = chat_id;
+            vm.msg_vm.selected = None;
+            vm.msg_vm.picker = None;
+            vm.msg_vm.search = None;
+            vm.msg_vm.typists.clear();
+            vm.input_vm.reply_to = None;
+        }
+        *self.read_sent.write().await = 0;
+        self.log(Level::Info, format!("joined the chat room ({})", chat_id)).await;
+
+        Ok(())
+    }
+
+    /// our own messages and mentions of us are told apart by the new name from now on
+    pub async fn change_nick(&self, user_name: &str) -> Result<()> {
+        self.request(Command::ChangeNick { user_name: user_name.to_string() }).await?;
+        self.view_model.write().await.conf.user_name = user_name.to_string();
+        self.refresh().await;
+
+        Ok(())
+    }
+
+    /// a member of the chat was renamed, which may be us if `ChangeNick` has not returned yet
+    pub async fn rename_user(&self, old: &str, new: &str) {
+        {
+            let mut vm = self.view_model.write().await;
+            if vm.conf.user_name == old {
+                vm.conf.user_name = new.to_string();
+            }
+            vm.msg_vm.stop_typing(old);
+        }
+        self.refresh().await;
+    }
+
+    pub async fn set_topic(&self, topic: String) {
+        self.view_model.write().await.msg_vm.topic = topic;
+        self.refresh().await;
+    }
+
+    /// only the pane is cleared, the history stays on the server
+    pub async fn clear_messages(&self) {
+        {
+            let mut vm = self.view_model.write().await;
+            vm.msg_vm.messages.clear();
+            vm.msg_vm.selected = None;
+            vm.msg_vm.picker = None;
+            vm.msg_vm.scroll_to_end();
+        }
+        self.refresh().await;
+    }
+
     pub async fn log(&self, level: Level, content: String) {
         {
             self.view_model.write().await.log_vm.log(level, content);
@@ -132,10 +293,247 @@ impl Controller {
     pub async fn push_message(&self, msg: Message) {
         {
             let mut state = self.view_model.write().await;
-            state.msg_vm.messages.push(msg);
-            state.msg_vm.scroll_to_end();
+            if let SomeUser::User(user) = &msg.user {
+                state.msg_vm.stop_typing(&user.user_name);
+
+                if msg.mentions(&state.conf.user_name) {
+                    let note = format!("{}: {}", user.user_name, msg.content);
+                    state.msg_vm.notifications.push(note);
+                }
+            }
+            let me = state.conf.user_name.to_string();
+            state.msg_vm.push(msg, &me);
         }
         self.refresh().await;
+        self.mark_read();
+    }
+
+    /// direct messages are shown inline, and notify like a mention when they are not our own
+    pub async fn push_direct_message(&self, msg: Message) {
+        {
+            let mut state = self.view_model.write().await;
+            if let SomeUser::User(user) = &msg.user {
+                if user.user_name != state.conf.user_name {
+                    let note = format!("{} (DM): {}", user.user_name, msg.content);
+                    state.msg_vm.notifications.push(note);
+                }
+            }
+            let me = state.conf.user_name.to_string();
+            state.msg_vm.push(msg, &me);
+        }
+        self.refresh().await;
+    }
+
+    /// tell the server we read up to the newest message, unless we are away
+    pub fn mark_read(&self) {
+        let ctrl = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(MARK_READ_DELAY).await;
+            if ctrl.activity.read().await.auto_away.is_some() {
+                return;
+            }
+
+            let (newest, chat_id) = {
+                let vm = ctrl.view_model.read().await;
+                (vm.msg_vm.newest_id(), vm.conf.chat_id)
+            };
+            let seq = match newest {
+                Some(seq) => seq,
+                None => return,
+            };
+
+            if seq <= *ctrl.read_sent.read().await {
+                return;
+            }
+
+            // on failure the next `mark_read` tries again
+            match ctrl.request(Command::MarkRead { chat_id, seq }).await {
+                Ok(_) => {
+                    let mut read_sent = ctrl.read_sent.write().await;
+                    *read_sent = seq.max(*read_sent);
+                }
+                Err(e) => log::debug!("mark read failed = {}", e),
+            }
+        });
+    }
+
+    pub async fn set_read_states(&self, read: HashMap<String, i64>, unread: HashMap<i64, i64>) {
+        {
+            let mut vm = self.view_model.write().await;
+            let me = vm.conf.user_name.to_string();
+            vm.msg_vm.last_read = Some(read.get(&me).copied().unwrap_or(0));
+            vm.msg_vm.read = read;
+            vm.msg_vm.unread_chats = unread;
+        }
+        self.refresh().await;
+    }
+
+    pub async fn set_read_state(&self, user_name: String, seq: i64) {
+        self.view_model.write().await.msg_vm.read.insert(user_name, seq);
+        self.refresh().await;
+    }
+
+    pub async fn edit_message(&self, msg: ModelMessage) {
+        self.view_model.write().await.msg_vm.edit(msg);
+        self.refresh().await;
+    }
+
+    pub async fn delete_message(&self, id: i64) {
+        self.view_model.write().await.msg_vm.delete(id);
+        self.refresh().await;
+    }
+
+    /// id of the last message we sent that is still in the chat
+    pub async fn last_own_message(&self) -> Option<i64> {
+        let vm = self.view_model.read().await;
+        vm.msg_vm.last_message_of(&vm.conf.user_name)
+    }
+
+    pub async fn select_message(&self, movement: KeyCode) {
+        self.view_model.write().await.msg_vm.select(movement);
+    }
+
+    /// reply to the selected message with the next message sent
+    pub async fn reply_selected(&self) {
+        let mut vm = self.view_model.write().await;
+        let target = vm.msg_vm.selected.and_then(|id| vm.msg_vm.find(id)).and_then(|m| match &m.user {
+            SomeUser::User(u) => Some(ReplyTarget { id: m.id?, user_name: u.user_name.to_string() }),
+            SomeUser::System => None,
+        });
+
+        if target.is_some() {
+            vm.input_vm.reply_to = target;
+            vm.msg_vm.selected = None;
+        }
+    }
+
+    /// close the search or the reaction picker, or else clear the selection, or else the pending reply,
+    /// returns `false` if there was none of them
+    pub async fn cancel_selection(&self) -> bool {
+        let mut vm = self.view_model.write().await;
+        if vm.mouse_vm.selection.take().is_some() {
+            return true;
+        }
+
+        if vm.input_vm.completion.take().is_some() {
+            return true;
+        }
+
+        if vm.msg_vm.search.take().is_some() {
+            return true;
+        }
+
+        if vm.msg_vm.picker.take().is_some() {
+            return true;
+        }
+
+        if vm.msg_vm.selected.take().is_some() {
+            return true;
+        }
+
+        vm.input_vm.reply_to.take().is_some()
+    }
+
+    pub async fn search(&self, query: &str) -> Result<()> {
+        let chat_id = self.get_view_model().await.conf.chat_id;
+        let cmd = Command::SearchMessages { chat_id, query: query.to_string(), limit: MAX_SEARCH_HITS, before: None };
+        let resp = self.request(cmd).await?;
+
+        let hits = match resp.data {
+            Some(CommandResult::Messages(hits)) => hits.into_iter().map(Message::from_message).collect(),
+            _ => Vec::new(),
+        };
+        self.view_model.write().await.msg_vm.search = Some(SearchResults { query: query.to_string(), hits, cursor: 0 });
+
+        Ok(())
+    }
+
+    pub async fn search_open(&self) -> bool {
+        self.view_model.read().await.msg_vm.search.is_some()
+    }
+
+    pub async fn search_move(&self, movement: KeyCode) {
+        self.view_model.write().await.msg_vm.search_move(movement);
+    }
+
+    /// scroll to the hit under the cursor and select it
+    pub async fn search_jump(&self) {
+        let found = self.view_model.write().await.msg_vm.search_jump();
+        if !found {
+            self.log(Level::Warn, "the message is not loaded anymore".to_string()).await;
+        }
+    }
+
+    pub async fn set_reactions(&self, id: i64, reactions: Vec<Reaction>) {
+        self.view_model.write().await.msg_vm.set_reactions(id, reactions);
+        self.refresh().await;
+    }
+
+    /// jump through the messages mentioning us, newest first
+    pub async fn select_previous_mention(&self) {
+        let mut vm = self.view_model.write().await;
+        let me = vm.conf.user_name.to_string();
+        vm.msg_vm.select_previous_mention(&me);
+    }
+
+    pub async fn open_picker(&self) {
+        self.view_model.write().await.msg_vm.open_picker();
+    }
+
+    pub async fn picker_open(&self) -> bool {
+        self.view_model.read().await.msg_vm.picker.is_some()
+    }
+
+    pub async fn picker_move(&self, movement: KeyCode) {
+        self.view_model.write().await.msg_vm.picker_move(movement);
+    }
+
+    /// toggle our reaction with the emoji at `idx` (the picker cursor if `None`) on the selected message
+    pub async fn picker_choose(&self, idx: Option<usize>) {
+        let (id, emoji) = {
+            let mut vm = self.view_model.write().await;
+            let idx = match idx.or(vm.msg_vm.picker) {
+                Some(idx) if idx < PICKER_EMOJIS.len() => idx,
+                _ => return,
+            };
+            let id = match vm.msg_vm.selected {
+                Some(id) => id,
+                None => return,
+            };
+            vm.msg_vm.picker = None;
+
+            (id, PICKER_EMOJIS[idx])
+        };
+
+        if let Err(e) = self.toggle_reaction(id, emoji).await {
+            self.log(Level::Error, format!("react failed : {}", e)).await;
+        }
+    }
+
+    /// the selected message, or else the newest one
+    pub async fn reaction_target(&self) -> Option<i64> {
+        let vm = self.view_model.read().await;
+        vm.msg_vm.selected.or_else(|| vm.msg_vm.messages.iter().rev().find_map(|m| m.id))
+    }
+
+    /// react with `emoji`, or take the reaction back if we already reacted with it
+    pub async fn toggle_reaction(&self, id: i64, emoji: &str) -> Result<()> {
+        let reacted = {
+            let vm = self.view_model.read().await;
+            vm.msg_vm.find(id)
+                .and_then(|m| m.reactions.iter().find(|r| r.emoji == emoji))
+                .is_some_and(|r| r.users.contains(&vm.conf.user_name))
+        };
+
+        let emoji = emoji.to_string();
+        let cmd = if reacted {
+            Command::Unreact { message_id: id, emoji }
+        } else {
+            Command::React { message_id: id, emoji }
+        };
+        self.request(cmd).await?;
+
+        Ok(())
     }
 
     pub async fn set_message_list(&self, msg_list: Vec<Message>) {
@@ -145,6 +543,7 @@ impl Controller {
             state.msg_vm.scroll_to_end();
         }
         self.refresh().await;
+        self.mark_read();
     }
 
 
@@ -170,8 +569,290 @@ impl Controller {
         self.view_model.write().await.input_vm.input_move_cursor(movement);
     }
 
-    pub async fn messages_scroll(&self, movement: KeyCode) {
-        self.view_model.write().await.msg_vm.messages_scroll(movement);
+    pub async fn input_move_word(&self, forward: bool) {
+        self.view_model.write().await.input_vm.input_move_word(forward);
+    }
+
+    pub async fn input_kill_word(&self, forward: bool) {
+        self.view_model.write().await.input_vm.input_kill_word(forward);
+    }
+
+    pub async fn input_kill_line(&self, to_end: bool) {
+        self.view_model.write().await.input_vm.input_kill_line(to_end);
+    }
+
+    pub async fn input_yank(&self) {
+        self.view_model.write().await.input_vm.input_yank();
+    }
+
+    pub async fn input_history(&self, movement: KeyCode) {
+        self.view_model.write().await.input_vm.input_history(movement);
+    }
+
+    /// Tab completes the word before the cursor, pressed again it goes on to the next candidate
+    pub async fn complete(&self, backwards: bool) {
+        let mut rooms = self.left_chats.read().await.clone();
+        let mut vm = self.view_model.write().await;
+        if vm.input_vm.complete_next(backwards) {
+            return;
+        }
+
+        rooms.extend(vm.msg_vm.unread_chats.keys());
+        rooms.sort();
+        rooms.dedup();
+        rooms.retain(|room| *room != vm.conf.chat_id);
+
+        let sources = completion::Sources {
+            users: vm.user_vm.users.iter()
+                .map(|u| u.user_name.to_string())
+                .filter(|name| *name != vm.conf.user_name)
+                .collect(),
+            commands: self.commands.names(),
+            rooms,
+        };
+
+        let cursor = vm.input_vm.cursor;
+        if let Some((start, candidates)) = completion::candidates(&vm.input_vm.text[..cursor], &sources) {
+            vm.input_vm.complete(start, candidates);
+        }
+    }
+
+    /// any key but Tab accepts the current candidate
+    pub async fn end_completion(&self) {
+        self.view_model.write().await.input_vm.completion = None;
+    }
+
+    /// fill the input history from `--history`, a missing file is an empty history
+    pub async fn load_history(&self) {
+        let path = self.view_model.read().await.conf.history.to_string();
+        if path.is_empty() {
+            return;
+        }
+
+        match history::load(&path).await {
+            Ok(entries) => self.view_model.write().await.input_vm.history = entries,
+            Err(e) => self.log(Level::Warn, format!("load history {} failed : {}", path, e)).await,
+        }
+    }
+
+    /// keep sent input for Up / Down, and in the history file for the next session
+    pub async fn remember_input(&self, entry: &str) {
+        let (added, path) = {
+            let mut state = self.view_model.write().await;
+            (state.input_vm.remember(entry), state.conf.history.to_string())
+        };
+        if !added || path.is_empty() || history::is_private(entry) {
+            return;
+        }
+
+        if let Err(e) = history::append(&path, entry).await {
+            self.log(Level::Warn, format!("save history {} failed : {}", path, e)).await;
+        }
+    }
+
+    /// block sending for `retry_after` and tick the input panel countdown until it expires
+    pub async fn throttle(&self, retry_after: Duration) {
+        let started = self.view_model.write().await.input_vm.throttle(retry_after);
+        self.refresh().await;
+        // the countdown of an earlier response is still ticking, it picks up the new end
+        if !started {
+            return;
+        }
+
+        let ctrl = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                let remaining = ctrl.view_model.write().await.input_vm.throttle_remaining();
+                ctrl.refresh().await;
+                if remaining.is_none() {
+                    break;
+                }
+            }
+        });
+    }
+
+    pub async fn throttle_remaining(&self) -> Option<Duration> {
+        self.view_model.write().await.input_vm.throttle_remaining()
+    }
+
+    /// send a typing event, unless one was sent less than `TYPING_INTERVAL` ago
+    pub async fn typing(&self) {
+        let conn = match self.opt_conn().await {
+            Some(conn) => conn,
+            None => return,
+        };
+
+        // commands are not chat messages, nobody needs to see them being typed
+        let chat_id = {
+            let vm = self.view_model.read().await;
+            if vm.input_vm.text.first() == Some(&'/') {
+                return;
+            }
+            vm.conf.chat_id
+        };
+
+        {
+            let mut typing_sent = self.typing_sent.write().await;
+            if typing_sent.is_some_and(|sent| sent.elapsed() < TYPING_INTERVAL) {
+                return;
+            }
+            *typing_sent = Some(Instant::now());
+        }
+
+        let mut req = Request::new(Command::Typing { chat_id });
+        req.base.session_id = self.session_id.read().await.to_string();
+        if let Err(e) = conn.send_datagram(&req) {
+            log::debug!("send typing failed = {}", e);
+        }
+    }
+
+    /// show `user_name` as typing until no event arrived for `TYPING_EXPIRE`
+    pub async fn user_typing(&self, user_name: &str) {
+        self.view_model.write().await.msg_vm.typing(user_name);
+        self.refresh().await;
+
+        let ctrl = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(TYPING_EXPIRE).await;
+            ctrl.refresh().await;
+        });
+    }
+
+    /// called on every key press, comes back online if we were set away automatically
+    pub async fn user_active(&self) {
+        let restore = {
+            let mut activity = self.activity.write().await;
+            activity.last_input = Instant::now();
+            activity.auto_away.take()
+        };
+
+        if let Some(text) = restore {
+            // messages that came in while we were away are read now
+            self.mark_read();
+
+            let ctrl = self.clone();
+            tokio::spawn(async move {
+                let chat_id = ctrl.view_model.read().await.conf.chat_id;
+                if let Err(e) = ctrl.set_status(Status::Online, text, chat_id).await {
+                    ctrl.log(Level::Error, format!("set status failed : {}", e)).await;
+                }
+            });
+        }
+    }
+
+    /// set the status to away once there was no keyboard input for `away_after` seconds,
+    /// a manually chosen away or busy status is left alone
+    pub async fn watch_idle(&self) {
+        let away_after = self.view_model.read().await.conf.away_after;
+        if away_after == 0 {
+            return;
+        }
+
+        loop {
+            tokio::time::sleep(IDLE_CHECK_INTERVAL).await;
+            if self.stop_accept_stream().await {
+                return;
+            }
+
+            if self.activity.read().await.last_input.elapsed() < Duration::from_secs(away_after) {
+                continue;
+            }
+
+            let (me, chat_id) = {
+                let vm = self.view_model.read().await;
+                let me = vm.user_vm.users.iter().find(|u| u.user_name == vm.conf.user_name).cloned();
+                (me, vm.conf.chat_id)
+            };
+            let me = match me {
+                Some(me) if me.status == Status::Online => me,
+                _ => continue,
+            };
+
+            self.activity.write().await.auto_away = Some(me.status_text.to_string());
+            if let Err(e) = self.set_status(Status::Away, me.status_text, chat_id).await {
+                self.activity.write().await.auto_away = None;
+                log::debug!("set away failed = {}", e);
+            }
+        }
+    }
+
+    /// scroll the log panel when it has the focus, the message panel otherwise
+    pub async fn scroll(&self, movement: KeyCode) {
+        let mut vm = self.view_model.write().await;
+        match vm.mouse_vm.focus {
+            Focus::Log => vm.log_vm.log_scroll(movement),
+            _ => vm.msg_vm.messages_scroll(movement),
+        }
+    }
+
+    /// the wheel scrolls the panel under the pointer
+    pub async fn mouse_scroll(&self, column: u16, row: u16, movement: KeyCode) {
+        let mut vm = self.view_model.write().await;
+        for _ in 0..WHEEL_LINES {
+            match vm.mouse_vm.panel_at(column, row) {
+                Some(Focus::Messages) => vm.msg_vm.messages_scroll(movement),
+                Some(Focus::Log) => vm.log_vm.log_scroll(movement),
+                _ => return,
+            }
+        }
+    }
+
+    /// focus the clicked panel, a click on a user mentions them (left) or starts a direct message (right),
+    /// the left button starts a text selection in the message and log panels
+    pub async fn mouse_down(&self, button: MouseButton, column: u16, row: u16) {
+        let mut vm = self.view_model.write().await;
+        vm.mouse_vm.selection = None;
+        let panel = match vm.mouse_vm.panel_at(column, row) {
+            Some(panel) => panel,
+            None => return,
+        };
+        vm.mouse_vm.focus = panel;
+
+        match (panel, button) {
+            (Focus::Users, _) => {
+                let name = match vm.mouse_vm.user_at(row) {
+                    Some(name) if name != vm.conf.user_name => name.to_string(),
+                    _ => return,
+                };
+                match button {
+                    MouseButton::Right => vm.input_vm.start_direct_message(&name),
+                    _ => vm.input_vm.insert_mention(&name),
+                }
+                vm.mouse_vm.focus = Focus::Input;
+            }
+            (Focus::Messages | Focus::Log, MouseButton::Left) => vm.mouse_vm.start_selection(panel, column, row),
+            _ => {}
+        }
+    }
+
+    pub async fn mouse_drag(&self, column: u16, row: u16) {
+        self.view_model.write().await.mouse_vm.drag_selection(column, row);
+    }
+
+    pub async fn mouse_up(&self) {
+        self.view_model.write().await.mouse_vm.end_selection();
+    }
+
+    /// a key press ends the highlight of copied text
+    pub async fn clear_mouse_selection(&self) {
+        self.view_model.write().await.mouse_vm.selection = None;
+    }
+
+    /// file shared in one of the loaded messages
+    pub async fn find_file(&self, id: i64) -> Option<FileOffer> {
+        let vm = self.view_model.read().await;
+        vm.msg_vm.messages.iter().rev().find_map(|m| m.file.clone().filter(|f| f.id == id))
+    }
+
+    /// hash and stream a file to the server, resuming a previous attempt of the same file
+    pub async fn upload(&self, path: &str) -> Result<()> {
+        transfer::upload(self, path).await
+    }
+
+    /// ask for a shared file, it is written to `dest` when its stream arrives
+    pub async fn download(&self, id: i64, dest: &str) -> Result<()> {
+        transfer::download(self, id, dest).await
     }
 }
 
@@ -183,4 +864,16 @@ impl quic::RequestCallback for Controller {
         let resp = callback(self.clone(), request).await;
         resp
     }
+
+    async fn handle_stream(&self, _remote: String, header: StreamHeader, recv: quic::UniRecv) {
+        transfer::receive(self, header, recv).await
+    }
+
+    async fn handle_datagram(&self, request: Request) {
+        if let Command::UserTyping { chat_id, user_name } = request.cmd {
+            if chat_id == self.view_model.read().await.conf.chat_id {
+                self.user_typing(&user_name).await;
+            }
+        }
+    }
 }
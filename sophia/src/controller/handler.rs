@@ -1,12 +1,12 @@
 use async_trait::async_trait;
+use log::Level;
 
 use sophia_core::command::Command;
 use sophia_core::errno;
 use sophia_core::errors::Result;
-use sophia_core::model::{Request, Response, User};
+use sophia_core::model::{InboxItem, MessageKind, ModerationAction, Request, Response};
 
 use crate::view_model::Message;
-use crate::view_model::SomeUser;
 
 use super::controller::Controller;
 
@@ -18,6 +18,17 @@ pub(super) trait Handler {
     async fn user_online(ctrl: Controller, request: Request) -> Result<Response>;
     async fn user_offline(ctrl: Controller, request: Request) -> Result<Response>;
     async fn chat_user_list_to_user(ctrl: Controller, request: Request) -> Result<Response>;
+    async fn moderation_event(ctrl: Controller, request: Request) -> Result<Response>;
+    async fn system_notice(ctrl: Controller, request: Request) -> Result<Response>;
+    async fn message_edited(ctrl: Controller, request: Request) -> Result<Response>;
+    async fn message_deleted(ctrl: Controller, request: Request) -> Result<Response>;
+    async fn reactions_changed(ctrl: Controller, request: Request) -> Result<Response>;
+    async fn read_state(ctrl: Controller, request: Request) -> Result<Response>;
+    async fn read_states(ctrl: Controller, request: Request) -> Result<Response>;
+    async fn direct_message(ctrl: Controller, request: Request) -> Result<Response>;
+    async fn inbox(ctrl: Controller, request: Request) -> Result<Response>;
+    async fn chat_topic(ctrl: Controller, request: Request) -> Result<Response>;
+    async fn nick_changed(ctrl: Controller, request: Request) -> Result<Response>;
 }
 
 
@@ -58,18 +69,22 @@ impl Handler for HandlerImpl {
     }
 
 
-    async fn user_online(ctrl: Controller, request: Request) -> Result<Response> {
-        if let Command::UserOnline { user, time } = request.cmd {
-            return user_connection_change(ctrl, user, time, true).await;
+    /// the server sends the notice and the new user list
+    async fn user_online(_ctrl: Controller, request: Request) -> Result<Response> {
+        if let Command::UserOnline { .. } = request.cmd {
+            let response = Response::success("ok".to_string());
+            return Ok(response);
         }
 
         errno!("cmd {} invalid!", request.cmd_type)
     }
 
 
-    async fn user_offline(ctrl: Controller, request: Request) -> Result<Response> {
-        if let Command::UserOffline { user, time } = request.cmd {
-            return user_connection_change(ctrl, user, time, false).await;
+    /// the server sends the notice and the new user list
+    async fn user_offline(_ctrl: Controller, request: Request) -> Result<Response> {
+        if let Command::UserOffline { .. } = request.cmd {
+            let response = Response::success("ok".to_string());
+            return Ok(response);
         }
 
         errno!("cmd {} invalid!", request.cmd_type)
@@ -86,6 +101,164 @@ impl Handler for HandlerImpl {
         errno!("cmd {} invalid!", request.cmd_type)
     }
 
+    /// the server sends the notice, a removed user just stops reconnecting
+    async fn moderation_event(ctrl: Controller, request: Request) -> Result<Response> {
+        if let Command::ModerationEvent(event) = request.cmd {
+            let me = ctrl.get_view_model().await.conf.user_name;
+            let removed = matches!(event.action, ModerationAction::Kick | ModerationAction::Ban { .. });
+            if removed && event.target == me {
+                // don't reconnect straight back into the chat we were removed from
+                *ctrl.exit_app.write().await = true;
+                ctrl.log(Level::Error, format!("you were removed from the chat by {}, press 'ESC' to exit", event.operator)).await;
+            }
+
+            let response = Response::success("ok".to_string());
+            return Ok(response);
+        }
+
+        errno!("cmd {} invalid!", request.cmd_type)
+    }
+
+    async fn system_notice(ctrl: Controller, request: Request) -> Result<Response> {
+        if let Command::SystemNotice { time, content } = request.cmd {
+            let msg = Message::notice(time, content);
+
+            tokio::spawn(async move {
+                ctrl.push_message(msg).await;
+            });
+
+            let response = Response::success("ok".to_string());
+            return Ok(response);
+        }
+
+        errno!("cmd {} invalid!", request.cmd_type)
+    }
+
+    async fn message_edited(ctrl: Controller, request: Request) -> Result<Response> {
+        if let Command::MessageEdited(message) = request.cmd {
+            tokio::spawn(async move {
+                ctrl.edit_message(message).await;
+            });
+
+            let response = Response::success("ok".to_string());
+            return Ok(response);
+        }
+
+        errno!("cmd {} invalid!", request.cmd_type)
+    }
+
+    async fn message_deleted(ctrl: Controller, request: Request) -> Result<Response> {
+        if let Command::MessageDeleted { id, .. } = request.cmd {
+            tokio::spawn(async move {
+                ctrl.delete_message(id).await;
+            });
+
+            let response = Response::success("ok".to_string());
+            return Ok(response);
+        }
+
+        errno!("cmd {} invalid!", request.cmd_type)
+    }
+
+    async fn reactions_changed(ctrl: Controller, request: Request) -> Result<Response> {
+        if let Command::ReactionsChanged { message_id, reactions, .. } = request.cmd {
+            tokio::spawn(async move {
+                ctrl.set_reactions(message_id, reactions).await;
+            });
+
+            let response = Response::success("ok".to_string());
+            return Ok(response);
+        }
+
+        errno!("cmd {} invalid!", request.cmd_type)
+    }
+
+    async fn read_state(ctrl: Controller, request: Request) -> Result<Response> {
+        if let Command::ReadState { user_name, seq, .. } = request.cmd {
+            tokio::spawn(async move {
+                ctrl.set_read_state(user_name, seq).await;
+            });
+
+            let response = Response::success("ok".to_string());
+            return Ok(response);
+        }
+
+        errno!("cmd {} invalid!", request.cmd_type)
+    }
+
+    async fn read_states(ctrl: Controller, request: Request) -> Result<Response> {
+        if let Command::ReadStates { read, unread, .. } = request.cmd {
+            tokio::spawn(async move {
+                ctrl.set_read_states(read, unread).await;
+            });
+
+            let response = Response::success("ok".to_string());
+            return Ok(response);
+        }
+
+        errno!("cmd {} invalid!", request.cmd_type)
+    }
+
+    async fn direct_message(ctrl: Controller, request: Request) -> Result<Response> {
+        if let Command::DirectMessage { to, message } = request.cmd {
+            let msg = Message::from_direct(to, message);
+
+            tokio::spawn(async move {
+                ctrl.push_direct_message(msg).await;
+            });
+
+            let response = Response::success("ok".to_string());
+            return Ok(response);
+        }
+
+        errno!("cmd {} invalid!", request.cmd_type)
+    }
+
+    async fn inbox(ctrl: Controller, request: Request) -> Result<Response> {
+        if let Command::Inbox { items } = request.cmd {
+            let now = chrono::Utc::now().timestamp();
+            let mut messages = vec![Message::notice(now, inbox_summary(&items))];
+            messages.extend(items.iter().map(inbox_content).map(|(time, content)| Message::notice(time, content)));
+
+            tokio::spawn(async move {
+                for msg in messages {
+                    ctrl.push_message(msg).await;
+                }
+            });
+
+            let response = Response::success("ok".to_string());
+            return Ok(response);
+        }
+
+        errno!("cmd {} invalid!", request.cmd_type)
+    }
+
+    async fn chat_topic(ctrl: Controller, request: Request) -> Result<Response> {
+        if let Command::ChatTopic { topic, .. } = request.cmd {
+            tokio::spawn(async move {
+                ctrl.set_topic(topic).await;
+            });
+
+            let response = Response::success("ok".to_string());
+            return Ok(response);
+        }
+
+        errno!("cmd {} invalid!", request.cmd_type)
+    }
+
+    async fn nick_changed(ctrl: Controller, request: Request) -> Result<Response> {
+        if let Command::NickChanged { old, new, .. } = request.cmd {
+            tokio::spawn(async move {
+                ctrl.rename_user(&old, &new).await;
+            });
+
+            let response = Response::success("ok".to_string());
+            return Ok(response);
+        }
+
+        errno!("cmd {} invalid!", request.cmd_type)
+    }
+
     // fn get_now_string() -> String {
     //     let system_time = SystemTime::now();
     //     let date_time: DateTime<Local> = system_time.into(); // 将 SystemTime 转换为 DateTime<Local>
@@ -94,20 +267,28 @@ impl Handler for HandlerImpl {
     // }
 }
 
-async fn user_connection_change(ctrl: Controller, user: User, time: i64, online: bool) -> Result<Response> {
-    let mut action = "online";
-    if !online {
-        action = "offline";
-    }
+/// e.g. `3 mentions, 2 DMs while you were away`
+fn inbox_summary(items: &[InboxItem]) -> String {
+    let mentions = items.iter().filter(|i| matches!(i, InboxItem::Mention { .. })).count();
+    let directs = items.len() - mentions;
+    let plural = |n: usize, word: &str| format!("{} {}{}", n, word, if n == 1 { "" } else { "s" });
 
-    let content = format!("{} {} is {} ", user.address, user.user_name, action);
-    let msg = Message::new(time, content, SomeUser::System);
+    match (mentions, directs) {
+        (0, n) => format!("{} while you were away", plural(n, "DM")),
+        (n, 0) => format!("{} while you were away", plural(n, "mention")),
+        (m, n) => format!("{}, {} while you were away", plural(m, "mention"), plural(n, "DM")),
+    }
+}
 
-    tokio::spawn(async move {
-        ctrl.push_message(msg).await;
-    });
+fn inbox_content(item: &InboxItem) -> (i64, String) {
+    let (prefix, message) = match item {
+        InboxItem::Mention { chat_id, message } => (format!("[chat {}]", chat_id), message),
+        InboxItem::Direct { message } => ("[DM]".to_string(), message),
+    };
 
-    let response = Response::success("ok".to_string());
-    return Ok(response);
+    let content = match message.kind {
+        MessageKind::Emote => format!("{} * {} {}", prefix, message.user.user_name, message.content),
+        _ => format!("{} {}: {}", prefix, message.user.user_name, message.content),
+    };
+    (message.time, content)
 }
-
@@ -0,0 +1,96 @@
+use std::io::ErrorKind;
+
+use tokio::io::AsyncWriteExt;
+
+use sophia_core::errors::Result;
+
+use crate::view_model::MAX_HISTORY;
+
+/// the sent input from the history file, oldest first,
+/// the file is cut down to the newest `MAX_HISTORY` entries when it grew past them
+pub(super) async fn load(path: &str) -> Result<Vec<String>> {
+    let content = match tokio::fs::read_to_string(path).await {
+        Ok(content) => content,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut entries: Vec<String> = content.lines().map(unescape).collect();
+    if entries.len() > MAX_HISTORY {
+        entries.drain(..entries.len() - MAX_HISTORY);
+        let lines: String = entries.iter().map(|entry| format!("{}\n", escape(entry))).collect();
+        tokio::fs::write(path, lines).await?;
+    }
+
+    Ok(entries)
+}
+
+/// the file is readable by us only, what we typed is nobody else's business
+pub(super) async fn append(path: &str, entry: &str) -> Result<()> {
+    let mut file = tokio::fs::OpenOptions::new().create(true).append(true).mode(0o600).open(path).await?;
+    file.write_all(format!("{}\n", escape(entry)).as_bytes()).await?;
+
+    Ok(())
+}
+
+/// direct messages are kept for Up / Down but never written to the file
+pub(super) fn is_private(entry: &str) -> bool {
+    let command = entry.trim_start().split(char::is_whitespace).next().unwrap_or("");
+    command.eq_ignore_ascii_case("/msg")
+}
+
+/// one entry per line, so line breaks of multi-line messages are written as `\n`
+fn escape(entry: &str) -> String {
+    entry.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn unescape(line: &str) -> String {
+    let mut entry = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                entry.push('\n');
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                entry.push('\\');
+                chars.next();
+            }
+            _ => entry.push(c),
+        }
+    }
+
+    entry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape, is_private, unescape};
+
+    #[test]
+    fn escape_round_trip() {
+        for entry in ["hello", "two\nlines", "C:\\path\\n", "\\\\n", "ends with \\", "\n\n"] {
+            let line = escape(entry);
+            assert!(!line.contains('\n'));
+            assert_eq!(unescape(&line), entry);
+        }
+    }
+
+    #[test]
+    fn escape_format() {
+        assert_eq!(escape("a\nb"), "a\\nb");
+        assert_eq!(escape("a\\nb"), "a\\\\nb");
+        // a lone backslash from an older file is kept
+        assert_eq!(unescape("a\\b\\"), "a\\b\\");
+    }
+
+    #[test]
+    fn direct_messages_are_private() {
+        assert!(is_private("/msg bob hi"));
+        assert!(is_private("  /MSG bob hi"));
+        assert!(!is_private("/msgs"));
+        assert!(!is_private("hi /msg"));
+        assert!(!is_private(""));
+    }
+}
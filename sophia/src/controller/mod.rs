@@ -2,6 +2,9 @@ pub use caller::Caller;
 pub use controller::Controller;
 
 mod caller;
+mod completion;
 mod controller;
 mod handler;
+mod history;
+mod transfer;
 
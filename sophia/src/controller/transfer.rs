@@ -0,0 +1,160 @@
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+
+use log::Level;
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+use sophia_core::{errno, errno_new};
+use sophia_core::command::{Command, CommandResult, StreamHeader};
+use sophia_core::consts::{FILE_CHUNK_SIZE, MAX_FILE_SIZE};
+use sophia_core::errors::Result;
+use sophia_core::hash::sha256_hex;
+use sophia_net::quic::UniRecv;
+
+use crate::controller::Caller;
+use crate::view_model::format_size;
+
+use super::controller::{Controller, Download};
+
+pub(super) async fn upload(ctrl: &Controller, path: &str) -> Result<()> {
+    let path = PathBuf::from(path);
+    let name = path.file_name()
+        .ok_or(errno_new!("{} is not a file", path.display()))?
+        .to_string_lossy().to_string();
+    let size = tokio::fs::metadata(&path).await?.len();
+    if size > MAX_FILE_SIZE {
+        return errno!("{} is {}, files can have at most {}", name, format_size(size), format_size(MAX_FILE_SIZE));
+    }
+
+    let hash_path = path.clone();
+    let hash = tokio::task::spawn_blocking(move || sha256_hex(&hash_path)).await
+        .map_err(|e| errno_new!("hash file failed = {}", e))??;
+
+    let chat_id = ctrl.get_view_model().await.conf.chat_id;
+    let resp = ctrl.request(Command::OfferFile { chat_id, name: name.to_string(), size, hash }).await?;
+    let (id, offset) = match resp.data {
+        Some(CommandResult::Upload { id, offset }) => (id, offset),
+        _ => return errno!("offer of {} was not answered with an upload", name),
+    };
+    if offset > 0 {
+        ctrl.log(Level::Info, format!("resuming upload of {} at {}", name, format_size(offset))).await;
+    }
+
+    let mut input = File::open(&path).await?;
+    input.seek(SeekFrom::Start(offset)).await?;
+
+    let session_id = ctrl.session_id.read().await.to_string();
+    let mut stream = ctrl.conn().await.open_uni(&StreamHeader::Upload { session_id, id, offset }).await?;
+    let mut buf = vec![0u8; FILE_CHUNK_SIZE];
+    loop {
+        let n = input.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        stream.write(&buf[..n]).await?;
+    }
+    stream.finish().await?;
+
+    ctrl.log(Level::Info, format!("uploaded {} ({})", name, format_size(size))).await;
+    Ok(())
+}
+
+
+/// `dest` may be a directory, the file keeps its name then. A `<dest>.part` left by an
+/// interrupted download is resumed
+pub(super) async fn download(ctrl: &Controller, id: i64, dest: &str) -> Result<()> {
+    let offer = ctrl.find_file(id).await
+        .ok_or(errno_new!("file {} is not in the loaded messages", id))?;
+
+    let mut dest = PathBuf::from(dest);
+    if dest.is_dir() {
+        dest = dest.join(&offer.name);
+    }
+    let offset = match tokio::fs::metadata(part_path(&dest)).await {
+        Ok(meta) => meta.len().min(offer.size),
+        Err(_) => 0,
+    };
+
+    // the stream can arrive before the response
+    ctrl.downloads.write().await.insert(id, Download { offer, dest });
+    if let Err(e) = ctrl.request(Command::DownloadFile { id, offset }).await {
+        ctrl.downloads.write().await.remove(&id);
+        return Err(e);
+    }
+
+    Ok(())
+}
+
+
+/// unidirectional stream opened by the server
+pub(super) async fn receive(ctrl: &Controller, header: StreamHeader, recv: UniRecv) {
+    let (id, offset) = match header {
+        StreamHeader::Download { id, offset } => (id, offset),
+        header => {
+            ctrl.log(Level::Warn, format!("unexpected stream {:?}", header)).await;
+            return;
+        }
+    };
+
+    let pending = ctrl.downloads.write().await.remove(&id);
+    let pending = match pending {
+        Some(pending) => pending,
+        None => {
+            ctrl.log(Level::Warn, format!("file {} arrived without a /download", id)).await;
+            return;
+        }
+    };
+
+    let name = pending.offer.name.to_string();
+    if let Err(e) = save(ctrl, pending, offset, recv).await {
+        ctrl.log(Level::Error, format!("download of {} failed : {}", name, e)).await;
+    }
+}
+
+
+async fn save(ctrl: &Controller, pending: Download, offset: u64, mut recv: UniRecv) -> Result<()> {
+    let Download { offer, dest } = pending;
+    let part = part_path(&dest);
+
+    let mut out = File::options().create(true).truncate(false).write(true).open(&part).await?;
+    out.set_len(offset).await?;
+    out.seek(SeekFrom::Start(offset)).await?;
+
+    let mut written = offset;
+    while let Some(chunk) = recv.read_chunk(FILE_CHUNK_SIZE).await? {
+        written += chunk.len() as u64;
+        if written > offer.size {
+            return errno!("received more than {}", format_size(offer.size));
+        }
+        out.write_all(&chunk).await?;
+    }
+    out.flush().await?;
+
+    if written < offer.size {
+        ctrl.log(Level::Warn, format!("download of {} stopped at {} of {}, run /download {} again to resume",
+                                      offer.name, format_size(written), format_size(offer.size), offer.id)).await;
+        return Ok(());
+    }
+
+    let hash_path = part.clone();
+    let hash = tokio::task::spawn_blocking(move || sha256_hex(&hash_path)).await
+        .map_err(|e| errno_new!("hash file failed = {}", e))??;
+    if hash != offer.hash {
+        tokio::fs::remove_file(&part).await?;
+        return errno!("the content does not match its hash");
+    }
+
+    tokio::fs::rename(&part, &dest).await?;
+    ctrl.log(Level::Info, format!("downloaded {} to {}", offer.name, dest.display())).await;
+
+    Ok(())
+}
+
+
+fn part_path(dest: &Path) -> PathBuf {
+    let mut part = dest.as_os_str().to_owned();
+    part.push(".part");
+
+    PathBuf::from(part)
+}
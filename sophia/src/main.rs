@@ -1,4 +1,5 @@
 use std::net::*;
+use std::time::Duration;
 
 use clap::Parser;
 use trust_dns_resolver::config::*;
@@ -10,6 +11,7 @@ use sophia_core::errno_new;
 use sophia_core::errors::Result;
 
 mod client;
+mod commands;
 mod config;
 mod controller;
 mod view_model;
@@ -24,6 +26,9 @@ pub struct Args {
     /// login password
     #[arg(short = 'p', long = "password", default_value = "666666")]
     password: String,
+    /// create an account for the user name with the password before logging in
+    #[arg(long = "register")]
+    register: bool,
     /// room id
     #[arg(short = 'c', long = "chat_id", default_value = "10086")]
     chat_id: i64,
@@ -36,6 +41,18 @@ pub struct Args {
     /// theme
     #[arg(short = 't', long = "theme", default_value = "dark")]
     theme: String,
+    /// seconds without keyboard input before the status turns to away, 0 disables it
+    #[arg(long = "away-after", default_value = "300")]
+    away_after: u64,
+    /// how to notify about mentions: bell, osc (desktop notification) or off
+    #[arg(long = "notify", default_value = "bell")]
+    notify: String,
+    /// file keeping the sent messages for Up / Down across sessions (without /msg), "" keeps none
+    #[arg(long = "history", default_value = "~/.sophia_history")]
+    history: String,
+    /// serve tokio-console on 127.0.0.1:6669, build with RUSTFLAGS="--cfg tokio_unstable"
+    #[arg(long = "console")]
+    console: bool,
     /// e.g. www.example.com
     #[arg(default_value = "")]
     server_name: String,
@@ -50,6 +67,9 @@ async fn main() -> Result<()> {
 
 
     let args = get_env_args().await?;
+    if args.console {
+        set_up_tokio_console();
+    }
     let config = Config::from_args(args);
 
     client::run(config).await?;
@@ -57,7 +77,6 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
-// use std::time::Duration;
 fn set_up_debug_log() {
     // env_logger::builder()
     //     .filter_level(log::LevelFilter::Off)
@@ -66,15 +85,6 @@ fn set_up_debug_log() {
     //
     // return;
 
-    // console_subscriber::ConsoleLayer::builder()
-    //     // set how long the console will retain data from completed tasks
-    //     .retention(Duration::from_secs(60))
-    //     // set the address the server is bound to
-    //     .server_addr(([127, 0, 0, 1], 8881))
-    //     // ... other configurations ...
-    //     .init();
-
-
     // use std::fs::File;
     // use std::io::Write;
     // use log::LevelFilter;
@@ -106,6 +116,15 @@ fn set_up_debug_log() {
 }
 
 
+fn set_up_tokio_console() {
+    console_subscriber::ConsoleLayer::builder()
+        // set how long the console will retain data from completed tasks
+        .retention(Duration::from_secs(60))
+        .server_addr(([127, 0, 0, 1], 6669))
+        .init();
+}
+
+
 async fn get_env_args() -> Result<Args> {
     let mut args = Args::parse();
 
@@ -1,20 +1,23 @@
 use std::io::Write;
 use std::sync::Arc;
 
+use crossterm::event::{DisableMouseCapture, EnableMouseCapture};
 use crossterm::ExecutableCommand;
 use crossterm::terminal::{self};
 use tokio::sync::RwLock;
 use tui::backend::CrosstermBackend;
 use tui::Frame;
 use tui::layout::{Constraint, Direction, Layout, Rect};
+use tui::style::{Modifier, Style};
 use tui::Terminal;
 
 use sophia_core::errno_new;
 use sophia_core::errors::Result;
 
-use crate::ui::{input_view, log_view, message_view, user_list_view};
+use crate::ui::{completion_view, input_view, log_view, message_view, reaction_picker_view, search_view, user_list_view};
+use crate::ui::mouse_view::{self, FocusFrame, Selection};
 use crate::ui::theme::Theme;
-use crate::view_model::AppViewModel;
+use crate::view_model::{AppViewModel, Focus, InputViewModel, Panels};
 
 pub struct AppView<W: Write> {
     terminal: Terminal<CrosstermBackend<W>>,
@@ -29,6 +32,8 @@ impl<W: Write> AppView<W> {
 
         out.execute(terminal::EnterAlternateScreen)
             .map_err(|e| errno_new!("out.execute failed =  {}",e))?;
+        out.execute(EnableMouseCapture)
+            .map_err(|e| errno_new!("out.execute failed =  {}",e))?;
 
         Ok(AppView {
             terminal: Terminal::new(CrosstermBackend::new(out))?,
@@ -37,21 +42,72 @@ impl<W: Write> AppView<W> {
     }
 
     pub async fn render(&mut self, state: Arc<RwLock<AppViewModel>>) -> Result<()> {
-        let (_, message_chunks) = layout(self.rect);
+        let (chunks, message_chunks) = layout(self.rect, &state.read().await.input_vm);
         message_view::adjust_scroll_pos(state.clone(), message_chunks[0]).await;
-
-        let state = state.read().await.clone();
+        log_view::adjust_scroll_pos(state.clone(), message_chunks[2]).await;
+
+        // the selection is copied once, it stays highlighted until the next click or key
+        let (notifications, copy) = {
+            let mut state = state.write().await;
+            state.mouse_vm.panels = Panels {
+                messages: message_chunks[0],
+                input: message_chunks[1],
+                log: message_chunks[2],
+                users: chunks[1],
+                user_rows: user_list_view::user_rows(&state.conf, &state.user_vm, chunks[1]),
+            };
+
+            let copy = state.mouse_vm.selection.as_mut().is_some_and(|s| std::mem::take(&mut s.copy));
+            (std::mem::take(&mut state.msg_vm.notifications), copy)
+        };
+
+        let mut state = state.read().await.clone();
+        if let Some(selection) = &mut state.mouse_vm.selection {
+            selection.copy = copy;
+        }
+        let notify = state.conf.notify.to_string();
+        let mut copied = String::new();
         self.terminal.draw(|frame| {
             self.rect = frame.size();
-            draw(state, frame)
+            draw(state, frame, &mut copied)
         })?;
 
+        for note in notifications {
+            self.notify(&notify, &note)?;
+        }
+        if !copied.is_empty() {
+            let out = self.terminal.backend_mut();
+            out.write_all(mouse_view::clipboard_sequence(&copied).as_bytes())?;
+            out.flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<W: Write> AppView<W> {
+    /// ring the bell, or ask the terminal for a desktop notification (OSC 777)
+    fn notify(&mut self, notify: &str, note: &str) -> Result<()> {
+        let seq = match notify {
+            "bell" => "\x07".to_string(),
+            // `;` separates the OSC fields, the message is sanitized by the server already
+            "osc" => format!("\x1b]777;notify;sophia;{}\x1b\\", note.replace(';', ",").replace('\n', " ")),
+            _ => return Ok(()),
+        };
+
+        let out = self.terminal.backend_mut();
+        out.write_all(seq.as_bytes())?;
+        out.flush()?;
+
         Ok(())
     }
 }
 
 impl<W: Write> Drop for AppView<W> {
     fn drop(&mut self) {
+        self.terminal.backend_mut()
+            .execute(DisableMouseCapture)
+            .expect("Could not execute to stdout");
         self.terminal.backend_mut()
             .execute(terminal::LeaveAlternateScreen)
             .expect("Could not execute to stdout");
@@ -65,8 +121,8 @@ impl<W: Write> Drop for AppView<W> {
 }
 
 
-fn draw(state: AppViewModel, frame: &mut Frame<CrosstermBackend<impl Write>>) {
-    let (chunks, message_chunks) = layout(frame.size());
+fn draw(state: AppViewModel, frame: &mut Frame<CrosstermBackend<impl Write>>, copied: &mut String) {
+    let (chunks, message_chunks) = layout(frame.size(), &state.input_vm);
 
     let mut theme = Theme::default();
     if state.conf.theme != "dark" {
@@ -74,20 +130,41 @@ fn draw(state: AppViewModel, frame: &mut Frame<CrosstermBackend<impl Write>>) {
     }
 
     message_view::draw(frame, &state.conf, &state.msg_vm, message_chunks[0], &theme);
+    reaction_picker_view::draw(frame, &state.msg_vm, message_chunks[0], &theme);
+    search_view::draw(frame, &state.msg_vm, message_chunks[0], &theme);
     input_view::draw(frame, &state.input_vm, message_chunks[1], &theme);
+    completion_view::draw(frame, &state.input_vm, message_chunks[1], &theme);
     log_view::draw(frame, &state.log_vm, message_chunks[2], &theme);
     user_list_view::draw(frame, &state.conf, &state.user_vm, chunks[1], &theme);
+
+    let focused = match state.mouse_vm.focus {
+        Focus::Messages => message_chunks[0],
+        Focus::Input => message_chunks[1],
+        Focus::Log => message_chunks[2],
+        Focus::Users => chunks[1],
+    };
+    frame.render_widget(FocusFrame { style: Style::default().fg(theme.focus_color).add_modifier(Modifier::BOLD) }, focused);
+    if let Some(selection) = state.mouse_vm.selection {
+        frame.render_widget(Selection { selection, copied }, frame.size());
+    }
 }
 
-pub fn layout(chunk: Rect) -> (Vec<Rect>, Vec<Rect>) {
+/// rows of the input panel, it grows with the input up to the max
+const INPUT_MIN_ROWS: usize = 3;
+const INPUT_MAX_ROWS: usize = 10;
+
+pub fn layout(chunk: Rect, input: &InputViewModel) -> (Vec<Rect>, Vec<Rect>) {
     let chunks = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([Constraint::Min(20), Constraint::Length(33)].as_ref())
         .split(chunk);
 
+    let input_width = chunks[0].width.saturating_sub(2).max(1) as usize;
+    let input_rows = input_view::height(input, input_width).clamp(INPUT_MIN_ROWS, INPUT_MAX_ROWS);
+
     let message_chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Min(10), Constraint::Length(5), Constraint::Length(10)].as_ref())
+        .constraints([Constraint::Min(10), Constraint::Length(input_rows as u16 + 2), Constraint::Length(10)].as_ref())
         .split(chunks[0]);
 
     (chunks, message_chunks)
@@ -0,0 +1,60 @@
+use std::io::Write;
+
+use tui::backend::CrosstermBackend;
+use tui::Frame;
+use tui::layout::Rect;
+use tui::style::{Modifier, Style};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, Borders, Clear, Paragraph};
+use unicode_width::UnicodeWidthStr;
+
+use crate::ui::theme::Theme;
+use crate::view_model;
+
+/// candidates shown at once, the list scrolls with the selection
+const MAX_VISIBLE: usize = 8;
+
+/// candidate list drawn over the bottom of the message panel, right above the input panel
+pub fn draw(
+    frame: &mut Frame<CrosstermBackend<impl Write>>,
+    state: &view_model::InputViewModel,
+    input_chunk: Rect,
+    theme: &Theme,
+) {
+    let completion = match &state.completion {
+        Some(completion) => completion,
+        None => return,
+    };
+
+    let visible = completion.candidates.len().min(MAX_VISIBLE);
+    let height = visible as u16 + 2;
+    let title = format!("{}/{}", completion.selected + 1, completion.candidates.len());
+    let widest = completion.candidates.iter().map(|c| c.trim_end().width()).max().unwrap_or(0);
+    let width = ((widest.max(title.width()) + 4) as u16).min(input_chunk.width.saturating_sub(2));
+    if input_chunk.y < height || width < 5 {
+        return;
+    }
+    let area = Rect::new(input_chunk.x + 1, input_chunk.y - height, width, height);
+
+    let first = (completion.selected + 1).saturating_sub(visible);
+    let lines: Vec<Spans> = completion.candidates.iter().enumerate()
+        .skip(first)
+        .take(visible)
+        .map(|(idx, candidate)| {
+            let style = match idx == completion.selected {
+                true => Style::default().add_modifier(Modifier::REVERSED),
+                false => Style::default(),
+            };
+            Spans::from(Span::styled(format!(" {} ", candidate.trim_end()), style))
+        })
+        .collect();
+
+    let list = Paragraph::new(lines)
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .title(Span::styled(title, Style::default().add_modifier(Modifier::BOLD))))
+        .style(Style::default().fg(theme.panel_border_color));
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(list, area);
+}
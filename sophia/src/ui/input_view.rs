@@ -1,4 +1,5 @@
 use std::io::Write;
+use std::time::Instant;
 
 use tui::backend::CrosstermBackend;
 use tui::Frame;
@@ -19,25 +20,58 @@ pub fn draw(
 ) {
     let inner_width = (chunk.width - 2) as usize;
 
-    let input = state.input().iter().collect::<String>();
-    let input = split_each(input, inner_width)
+    let input = rows(state, inner_width)
         .into_iter()
         .map(|line| Spans::from(vec![Span::raw(line)]))
         .collect::<Vec<_>>();
 
+    // keep the cursor row visible once the panel stopped growing
+    let input_cursor = ui_input_cursor(state, inner_width);
+    let visible = chunk.height.saturating_sub(2);
+    let scroll = (input_cursor.1 + 1).saturating_sub(visible);
+
+    let title = match state.throttled_until {
+        Some(until) => {
+            let remaining = until.saturating_duration_since(Instant::now());
+            let secs = remaining.as_millis().div_ceil(1000);
+            Span::styled(format!("Input Your message (rate limited, retry in {}s)", secs),
+                         Style::default().fg(theme.system_info_color.0).add_modifier(Modifier::BOLD))
+        }
+        None => match &state.reply_to {
+            Some(reply) => Span::styled(format!("Reply to {} (Esc to cancel)", reply.user_name),
+                                        Style::default().fg(theme.system_info_color.1).add_modifier(Modifier::BOLD)),
+            None => Span::styled("Input Your message", Style::default().add_modifier(Modifier::BOLD)),
+        },
+    };
+
     let input_panel = Paragraph::new(input)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title(Span::styled("Input Your message", Style::default().add_modifier(Modifier::BOLD))),
+                .title(title),
         )
         .style(Style::default().fg(theme.panel_border_color))
-        .alignment(Alignment::Left);
+        .alignment(Alignment::Left)
+        .scroll((scroll, 0));
 
     frame.render_widget(input_panel, chunk);
+    frame.set_cursor(chunk.x + 1 + input_cursor.0, chunk.y + 1 + input_cursor.1 - scroll)
+}
 
-    let input_cursor = ui_input_cursor(state, inner_width);
-    frame.set_cursor(chunk.x + 1 + input_cursor.0, chunk.y + 1 + input_cursor.1)
+/// the input split at its line breaks, then wrapped to `width`
+pub fn rows(state: &view_model::InputViewModel, width: usize) -> Vec<String> {
+    let input = state.input().iter().collect::<String>();
+    input.split('\n')
+        .flat_map(|line| match split_each(line.to_string(), width) {
+            rows if rows.is_empty() => vec![String::new()],
+            rows => rows,
+        })
+        .collect()
+}
+
+/// rows the input needs, the cursor behind a full row takes the next one
+pub fn height(state: &view_model::InputViewModel, width: usize) -> usize {
+    rows(state, width).len().max(ui_input_cursor(state, width).1 as usize + 1)
 }
 
 pub fn split_each(input: String, width: usize) -> Vec<String> {
@@ -63,9 +97,22 @@ pub fn split_each(input: String, width: usize) -> Vec<String> {
 }
 
 pub fn ui_input_cursor(state: &view_model::InputViewModel, width: usize) -> (u16, u16) {
+    // the lines before the one holding the cursor are complete
+    let mut lines = state.text[..state.cursor].split(|c| *c == '\n');
+    let current = lines.next_back().unwrap_or_default();
+    let row: usize = lines
+        .map(|line| split_each(line.iter().collect(), width).len().max(1))
+        .sum();
+
+    let position = line_cursor(current, width);
+    (position.0 as u16, (row + position.1) as u16)
+}
+
+/// cursor behind `line`, as column and row of the wrapped line
+fn line_cursor(line: &[char], width: usize) -> (usize, usize) {
     let mut position = (0, 0);
 
-    for current_char in state.text.iter().take(state.cursor) {
+    for current_char in line {
         let char_width = unicode_width::UnicodeWidthChar::width(*current_char).unwrap_or(0);
 
         position.0 += char_width;
@@ -85,6 +132,6 @@ pub fn ui_input_cursor(state: &view_model::InputViewModel, width: usize) -> (u16
         }
     }
 
-    (position.0 as u16, position.1 as u16)
+    position
 }
 
@@ -1,15 +1,26 @@
 use std::io::Write;
+use std::sync::Arc;
 
 use log::Level;
+use tokio::sync::RwLock;
 use tui::backend::CrosstermBackend;
 use tui::Frame;
 use tui::layout::{Alignment, Rect};
 use tui::style::{Color, Modifier, Style};
 use tui::text::{Span, Spans};
-use tui::widgets::{Block, Borders, Paragraph, Wrap};
+use tui::widgets::{Block, Borders, Paragraph};
 
+use crate::ui::markup::{self, Line};
 use crate::ui::theme::Theme;
-use crate::view_model;
+use crate::view_model::{self, AppViewModel};
+
+/// what a piece of a log row is, to pick its color
+#[derive(Clone, Copy, PartialEq)]
+enum Piece {
+    Date,
+    Level(Level),
+    Content,
+}
 
 pub fn draw(
     frame: &mut Frame<CrosstermBackend<impl Write>>,
@@ -17,26 +28,14 @@ pub fn draw(
     chunk: Rect,
     theme: &Theme,
 ) {
-    let logs = state.contents
-        .iter()
-        .rev()
-        .map(|log| {
-            // Color::Blue, Color::Yellow, Color::Cyan, Color::Magenta
-            let date = log.time.format("%m-%d %H:%M:%S ").to_string();
-            let color = match log.level {
-                Level::Error => Color::Red,
-                Level::Warn => Color::Yellow,
-                Level::Info => Color::Green,
-                Level::Debug => Color::Blue,
-                Level::Trace => Color::Cyan,
-            };
-
-
-            Spans::from(vec![
-                Span::styled(date, Style::default().fg(theme.date_color)),
-                Span::styled(log.level.to_string(), Style::default().fg(color)),
-                Span::raw(format!(" {}", log.content.to_string())),
-            ])
+    let logs = log_rows(state, chunk.width.saturating_sub(2) as usize)
+        .into_iter()
+        .map(|row| {
+            Spans::from(row.into_iter().map(|(text, piece)| match piece {
+                Piece::Date => Span::styled(text, Style::default().fg(theme.date_color)),
+                Piece::Level(level) => Span::styled(text, Style::default().fg(level_color(level))),
+                Piece::Content => Span::raw(text),
+            }).collect::<Vec<_>>())
         })
         .collect::<Vec<_>>();
 
@@ -48,7 +47,46 @@ pub fn draw(
         )
         .style(Style::default().fg(theme.panel_border_color))
         .alignment(Alignment::Left)
-        .wrap(Wrap { trim: false });
+        .scroll((state.scroll_pos as u16, 0));
 
     frame.render_widget(log_panel, chunk);
 }
+
+/// keep the scroll position within the wrapped logs after they or the panel changed
+pub async fn adjust_scroll_pos(state: Arc<RwLock<AppViewModel>>, rect: Rect) {
+    let mut state = state.write().await;
+    let vm = &mut state.log_vm;
+    vm.page_height = rect.height.saturating_sub(2) as usize;
+    vm.lines = log_rows(vm, rect.width.saturating_sub(2) as usize).len();
+    vm.scroll_pos = vm.scroll_pos.min(vm.bottom());
+}
+
+/// the logs newest first, wrapped by us so the panel can count its rows
+fn log_rows(state: &view_model::LogViewModel, width: usize) -> Vec<Line<Piece>> {
+    state.contents
+        .iter()
+        .rev()
+        .flat_map(|log| {
+            let mut lines = log.content.split('\n');
+            let first = vec![
+                (log.time.format("%m-%d %H:%M:%S ").to_string(), Piece::Date),
+                (log.level.to_string(), Piece::Level(log.level)),
+                (format!(" {}", lines.next().unwrap_or_default()), Piece::Content),
+            ];
+            std::iter::once(first)
+                .chain(lines.map(|line| vec![(line.to_string(), Piece::Content)]))
+                .flat_map(|line| markup::wrap(&line, width))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+fn level_color(level: Level) -> Color {
+    match level {
+        Level::Error => Color::Red,
+        Level::Warn => Color::Yellow,
+        Level::Info => Color::Green,
+        Level::Debug => Color::Blue,
+        Level::Trace => Color::Cyan,
+    }
+}
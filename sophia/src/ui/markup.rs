@@ -0,0 +1,301 @@
+use tui::style::{Modifier, Style};
+use unicode_width::UnicodeWidthChar;
+
+use crate::ui::theme::Theme;
+
+/// how a piece of message text is shown, pieces of a quote keep `quote` besides their own marks
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Mark {
+    pub bold: bool,
+    pub italic: bool,
+    pub code: bool,
+    pub quote: bool,
+    pub url: bool,
+    /// trailers like the edited marker
+    pub muted: bool,
+}
+
+/// pieces of text making up one line
+pub type Line<T> = Vec<(String, T)>;
+
+const FENCE: &str = "```";
+const QUOTE_BAR: &str = "│ ";
+
+/// split `content` into lines of marked pieces, understands
+/// **bold**, *italic*, `code`, ``` code blocks ```, > quotes and http(s) urls,
+/// anything else (including unbalanced markers) stays as it was typed
+pub fn parse(content: &str) -> Vec<Line<Mark>> {
+    let mut lines = Vec::new();
+    let mut in_block = false;
+    for line in content.split('\n') {
+        if line.trim_start().starts_with(FENCE) {
+            in_block = !in_block;
+            continue;
+        }
+
+        if in_block {
+            lines.push(vec![(line.to_string(), Mark { code: true, ..Mark::default() })]);
+            continue;
+        }
+
+        match line.strip_prefix('>') {
+            Some(quoted) => {
+                let base = Mark { quote: true, ..Mark::default() };
+                let mut pieces = vec![(QUOTE_BAR.to_string(), base)];
+                pieces.extend(inline(quoted.strip_prefix(' ').unwrap_or(quoted), base));
+                lines.push(pieces);
+            }
+            None => lines.push(inline(line, Mark::default())),
+        }
+    }
+
+    // a message of nothing but fences
+    if lines.is_empty() {
+        lines.push(Vec::new());
+    }
+
+    lines
+}
+
+fn inline(text: &str, base: Mark) -> Line<Mark> {
+    let mut pieces = Vec::new();
+    let mut plain = String::new();
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        let marked = match c {
+            '`' => delimited(rest, "`").map(|(inner, len)| (inner, Mark { code: true, ..base }, len)),
+            '*' if rest.starts_with("**") => delimited(rest, "**").map(|(inner, len)| (inner, Mark { bold: true, ..base }, len)),
+            '*' => delimited(rest, "*").map(|(inner, len)| (inner, Mark { italic: true, ..base }, len)),
+            'h' if plain.is_empty() || plain.ends_with(|c: char| c.is_whitespace() || c == '(') => {
+                url(rest).map(|url| (url, Mark { url: true, ..base }, url.len()))
+            }
+            _ => None,
+        };
+
+        match marked {
+            Some((inner, mark, len)) => {
+                if !plain.is_empty() {
+                    pieces.push((std::mem::take(&mut plain), base));
+                }
+                pieces.push((inner.to_string(), mark));
+                rest = &rest[len..];
+            }
+            None => {
+                plain.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+
+    if !plain.is_empty() {
+        pieces.push((plain, base));
+    }
+
+    pieces
+}
+
+/// the text between `delim` at the start of `text` and its next occurrence, with the length of both
+/// delimiters and the text, `* spaced *` or empty pairs are no markup
+fn delimited<'a>(text: &'a str, delim: &str) -> Option<(&'a str, usize)> {
+    let body = &text[delim.len()..];
+    let end = body.find(delim)?;
+    let inner = &body[..end];
+    if inner.is_empty() || inner.starts_with(char::is_whitespace) || inner.ends_with(char::is_whitespace) {
+        return None;
+    }
+
+    Some((inner, end + delim.len() * 2))
+}
+
+/// an http(s) url at the start of `text`, without trailing punctuation
+fn url(text: &str) -> Option<&str> {
+    if !text.starts_with("http://") && !text.starts_with("https://") {
+        return None;
+    }
+
+    let end = text.find(char::is_whitespace).unwrap_or(text.len());
+    let url = text[..end].trim_end_matches(|c: char| ".,;:!?)'\"".contains(c));
+    match url.ends_with("//") {
+        true => None,
+        false => Some(url),
+    }
+}
+
+/// `base` with the look of `mark` on top
+pub fn style(mark: Mark, base: Style, theme: &Theme) -> Style {
+    if mark.muted {
+        return Style::default().fg(theme.date_color);
+    }
+
+    let mut style = base;
+    if mark.quote {
+        style = style.fg(theme.quote_color);
+    }
+    if mark.bold {
+        style = style.add_modifier(Modifier::BOLD);
+    }
+    if mark.italic {
+        style = style.add_modifier(Modifier::ITALIC);
+    }
+    if mark.code {
+        style = style.fg(theme.code_color);
+    }
+    if mark.url {
+        style = style.fg(theme.url_color).add_modifier(Modifier::UNDERLINED);
+    }
+
+    style
+}
+
+/// break `line` into rows of at most `width` columns, after a space where possible,
+/// the rows are drawn as they are so counting them gives the exact height of the line
+pub fn wrap<T: Copy + PartialEq>(line: &[(String, T)], width: usize) -> Vec<Line<T>> {
+    let chars: Vec<(char, T)> = line.iter()
+        .flat_map(|(text, tag)| text.chars().map(move |c| (c, *tag)))
+        .collect();
+    let width = width.max(1);
+
+    let mut rows = Vec::new();
+    let mut start = 0;
+    while start < chars.len() {
+        let mut used = 0;
+        let mut end = start;
+        let mut after_space = None;
+        while end < chars.len() {
+            let char_width = chars[end].0.width().unwrap_or(0);
+            if used + char_width > width {
+                break;
+            }
+            used += char_width;
+            end += 1;
+            if chars[end - 1].0 == ' ' {
+                after_space = Some(end);
+            }
+        }
+
+        if end < chars.len() {
+            if let Some(after_space) = after_space {
+                end = after_space;
+            }
+        }
+        // a single char wider than the row
+        if end == start {
+            end += 1;
+        }
+
+        rows.push(group(&chars[start..end]));
+        start = end;
+    }
+
+    if rows.is_empty() {
+        rows.push(Vec::new());
+    }
+
+    rows
+}
+
+/// join neighbouring chars with the same tag back into pieces
+fn group<T: Copy + PartialEq>(chars: &[(char, T)]) -> Line<T> {
+    let mut pieces: Line<T> = Vec::new();
+    for (c, tag) in chars {
+        match pieces.last_mut() {
+            Some((text, last)) if last == tag => text.push(*c),
+            _ => pieces.push((c.to_string(), *tag)),
+        }
+    }
+
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, wrap, Mark};
+
+    fn plain(text: &str) -> (String, Mark) {
+        (text.to_string(), Mark::default())
+    }
+
+    fn marked(text: &str, mark: Mark) -> (String, Mark) {
+        (text.to_string(), mark)
+    }
+
+    #[test]
+    fn parse_inline_marks() {
+        let bold = Mark { bold: true, ..Mark::default() };
+        let italic = Mark { italic: true, ..Mark::default() };
+        let code = Mark { code: true, ..Mark::default() };
+        assert_eq!(parse("a **b** *c* `d`"), vec![vec![
+            plain("a "), marked("b", bold), plain(" "), marked("c", italic), plain(" "), marked("d", code),
+        ]]);
+        // markers inside code are kept
+        assert_eq!(parse("`*x*`"), vec![vec![marked("*x*", code)]]);
+    }
+
+    #[test]
+    fn parse_code_blocks() {
+        let code = Mark { code: true, ..Mark::default() };
+        assert_eq!(parse("a\n```\n**b**\n```\nc"), vec![
+            vec![plain("a")],
+            vec![marked("**b**", code)],
+            vec![plain("c")],
+        ]);
+        assert_eq!(parse("```\n```"), vec![Vec::new()]);
+    }
+
+    #[test]
+    fn parse_quotes() {
+        let quote = Mark { quote: true, ..Mark::default() };
+        let bold_quote = Mark { bold: true, ..quote };
+        assert_eq!(parse("> hi **you**"), vec![vec![
+            marked("│ ", quote), marked("hi ", quote), marked("you", bold_quote),
+        ]]);
+    }
+
+    #[test]
+    fn parse_urls() {
+        let url = Mark { url: true, ..Mark::default() };
+        assert_eq!(parse("see https://a.org/x."), vec![vec![plain("see "), marked("https://a.org/x", url), plain(".")]]);
+        assert_eq!(parse("(http://a.org)"), vec![vec![plain("("), marked("http://a.org", url), plain(")")]]);
+        // not at the start of a word, or nothing after the scheme
+        assert_eq!(parse("xhttp://a.org"), vec![vec![plain("xhttp://a.org")]]);
+        assert_eq!(parse("https://"), vec![vec![plain("https://")]]);
+    }
+
+    #[test]
+    fn parse_keeps_unbalanced_markers() {
+        assert_eq!(parse("**a"), vec![vec![plain("**a")]]);
+        assert_eq!(parse("2 * 3 * 4"), vec![vec![plain("2 * 3 * 4")]]);
+        assert_eq!(parse("``"), vec![vec![plain("``")]]);
+    }
+
+    #[test]
+    fn wrap_to_width() {
+        let line = vec![(String::from("abcdef"), ())];
+        assert_eq!(wrap(&line, 4), vec![vec![(String::from("abcd"), ())], vec![(String::from("ef"), ())]]);
+        assert_eq!(wrap(&line, 6).len(), 1);
+    }
+
+    #[test]
+    fn wrap_after_space() {
+        let line = vec![(String::from("ab "), 1), (String::from("cd ef"), 2)];
+        assert_eq!(wrap(&line, 6), vec![
+            vec![(String::from("ab "), 1), (String::from("cd "), 2)],
+            vec![(String::from("ef"), 2)],
+        ]);
+    }
+
+    #[test]
+    fn wrap_wide_chars() {
+        let line = vec![(String::from("日本語"), ())];
+        assert_eq!(wrap(&line, 4), vec![vec![(String::from("日本"), ())], vec![(String::from("語"), ())]]);
+        // a char wider than the row still gets a row of its own
+        assert_eq!(wrap(&line, 1).len(), 3);
+    }
+
+    #[test]
+    fn wrap_empty_line() {
+        let line: Vec<(String, ())> = Vec::new();
+        assert_eq!(wrap(&line, 10), vec![Vec::new()]);
+    }
+}
@@ -10,11 +10,19 @@ use tui::layout::{Alignment, Rect};
 use tui::style::{Modifier, Style};
 use tui::text::{Span, Spans};
 use tui::widgets::{Block, Borders, Paragraph, Wrap};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use sophia_core::model::MessageKind;
 
 use crate::{config, view_model};
+use crate::ui::markup::{self, Line, Mark};
 use crate::ui::theme::Theme;
 use crate::view_model::{AppViewModel, Message, SomeUser};
 
+const EDITED_MARKER: &str = " (edited)";
+/// as wide as the `%H:%M:%S` time in front of every message
+const DATE_PLACEHOLDER: &str = "00:00:00";
+
 pub fn draw(
     frame: &mut Frame<CrosstermBackend<impl Write>>,
     conf: &config::Config,
@@ -28,42 +36,85 @@ pub fn draw(
 
     let scroll_messages_view_pos = state.scroll_pos;
     let mut msg_list: Vec<Spans> = Vec::new();
+    let inner_width = chunk.width.saturating_sub(2) as usize;
 
 
+    let first_unread = state.first_unread();
     for msg in state.messages.iter() {
         let date = get_time_string_with_custom(msg.time, "%H:%M:%S");
 
+        if msg.id.is_some() && msg.id == first_unread {
+            msg_list.push(Spans::from(Span::styled(divider(NEW_MESSAGES, inner_width),
+                                                   Style::default().fg(theme.system_info_color.0))));
+        }
+
         match &msg.user {
             SomeUser::User(u) => {
                 let idx = u.login_time as usize;
                 let mut color = theme.message_colors[idx % theme.message_colors.len()];
 
                 let remote = format!(" ({}) ", u.address);
-                let mut name = u.user_name.to_string();
-
-                if name == conf.user_name {
-                    name = format!("{}(me)", conf.user_name);
+                let name = display_name(msg, &u.user_name, &conf.user_name);
+                if u.user_name == conf.user_name {
                     color = theme.my_user_color
                 }
 
-                msg_list.push(
-                    Spans::from(vec![
-                        Span::styled(date, Style::default().fg(theme.date_color)),
-                        Span::styled(remote, Style::default().fg(theme.address_color)),
-                        Span::styled(format!("{} :", name), Style::default().fg(color)),
-                    ]));
-
-                msg_list.push(
-                    Spans::from(vec![
-                        Span::raw(format!("  {}", &msg.content)),
-                    ]));
+                if let Some(reply_to) = msg.reply_to {
+                    let preview = reply_preview(state, reply_to, inner_width);
+                    msg_list.push(Spans::from(Span::styled(preview, Style::default()
+                        .fg(theme.date_color)
+                        .add_modifier(Modifier::ITALIC))));
+                }
+
+                let mut header_style = Style::default();
+                if msg.id.is_some() && msg.id == state.selected {
+                    header_style = header_style.add_modifier(Modifier::REVERSED);
+                }
+
+                // an emote follows the date and address in the color of its author
+                if msg.kind == MessageKind::Emote {
+                    let mut line = vec![
+                        (date, header_style.fg(theme.date_color)),
+                        (remote, header_style.fg(theme.address_color)),
+                        (emote_text(msg), header_style.fg(color).add_modifier(Modifier::ITALIC)),
+                    ];
+                    if msg.edited {
+                        line.push((EDITED_MARKER.to_string(), Style::default().fg(theme.date_color)));
+                    }
+                    msg_list.extend(rows(&line, inner_width, |style| style));
+                } else {
+                    let header = vec![
+                        (date, header_style.fg(theme.date_color)),
+                        (remote, header_style.fg(theme.address_color)),
+                        (format!("{} :", name), header_style.fg(color)),
+                    ];
+                    msg_list.extend(rows(&header, inner_width, |style| style));
+
+                    let content_style = match msg.mentions(&conf.user_name) {
+                        true => Style::default().fg(theme.mention_color).add_modifier(Modifier::BOLD),
+                        false => Style::default(),
+                    };
+                    for line in content_lines(msg) {
+                        msg_list.extend(rows(&line, inner_width, |mark| markup::style(mark, content_style, theme)));
+                    }
+                }
+
+                if !msg.reactions.is_empty() {
+                    let reactions = reaction_labels(msg, &conf.user_name)
+                        .into_iter()
+                        .map(|(label, mine)| match mine {
+                            true => (label, Style::default().fg(theme.my_user_color).add_modifier(Modifier::BOLD)),
+                            false => (label, Style::default().fg(theme.date_color)),
+                        });
+                    let line: Vec<_> = std::iter::once(("  ".to_string(), Style::default())).chain(reactions).collect();
+                    msg_list.extend(rows(&line, inner_width, |style| style));
+                }
             }
             SomeUser::System => {
-                msg_list.push(
-                    Spans::from(vec![
-                        Span::styled(date, Style::default().fg(theme.date_color)),
-                        Span::styled(format!("  {}", &msg.content), Style::default().fg(theme.system_info_color.0)),
-                    ]));
+                let date_style = Style::default().fg(theme.date_color);
+                for line in notice_lines(date, &msg.content, date_style, Style::default().fg(theme.system_info_color.0)) {
+                    msg_list.extend(rows(&line, inner_width, |style| style));
+                }
             }
         };
     }
@@ -73,8 +124,7 @@ pub fn draw(
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title(Span::styled(format!("Chat Room : {}", conf.chat_id),
-                                    Style::default().add_modifier(Modifier::BOLD))),
+                .title(Span::styled(title(conf, state), Style::default().add_modifier(Modifier::BOLD))),
         )
         .style(Style::default().fg(theme.panel_border_color))
         .alignment(Alignment::Left)
@@ -82,9 +132,224 @@ pub fn draw(
         .wrap(Wrap { trim: false });
 
     frame.render_widget(msg_list_panel, chunk);
+    draw_typists(frame, state, chunk, theme);
+    match state.follow {
+        true => draw_seen_by(frame, conf, state, chunk, theme),
+        false => draw_more_below(frame, state, chunk, theme),
+    }
+}
+
+
+/// the content of a message as marked lines, indented under the header
+fn content_lines(msg: &Message) -> Vec<Line<Mark>> {
+    let mut lines = markup::parse(&msg.content);
+    if msg.edited {
+        if let Some(last) = lines.last_mut() {
+            last.push((EDITED_MARKER.to_string(), Mark { muted: true, ..Mark::default() }));
+        }
+    }
+
+    lines.into_iter()
+        .map(|line| std::iter::once(("  ".to_string(), Mark::default())).chain(line).collect())
+        .collect()
 }
 
-fn get_time_string_with_custom(timestamp: i64, str: &str) -> String {
+/// the first line follows the date, the others are aligned below it
+fn notice_lines<T: Copy>(date: String, content: &str, date_tag: T, tag: T) -> Vec<Line<T>> {
+    let indent = " ".repeat(date.width());
+    content.split('\n')
+        .enumerate()
+        .map(|(i, line)| match i {
+            0 => vec![(date.clone(), date_tag), (format!("  {}", line), tag)],
+            _ => vec![(indent.clone(), date_tag), (format!("  {}", line), tag)],
+        })
+        .collect()
+}
+
+/// an emote is kept on the header line
+fn emote_text(msg: &Message) -> String {
+    msg.text().replace('\n', " ")
+}
+
+/// `line` wrapped to `width`, one `Spans` per row
+fn rows<T: Copy + PartialEq>(line: &[(String, T)], width: usize, style: impl Fn(T) -> Style) -> Vec<Spans<'static>> {
+    markup::wrap(line, width).into_iter()
+        .map(|row| Spans::from(row.into_iter().map(|(text, tag)| Span::styled(text, style(tag))).collect::<Vec<_>>()))
+        .collect()
+}
+
+
+const NEW_MESSAGES: &str = " new messages ";
+
+/// `label` centered in a line of `─` filling `width`
+fn divider(label: &str, width: usize) -> String {
+    let rest = width.saturating_sub(label.width());
+    let left = rest / 2;
+    format!("{}{}{}", "─".repeat(left), label, "─".repeat(rest - left))
+}
+
+
+/// "more below ↓ (3 new)" right aligned over the bottom border while reading the history
+fn draw_more_below(
+    frame: &mut Frame<CrosstermBackend<impl Write>>,
+    state: &view_model::ChatMessageViewModel,
+    chunk: Rect,
+    theme: &Theme,
+) {
+    let (text, style) = match state.unseen {
+        0 => (" more below ↓ ".to_string(), Style::default().fg(theme.date_color)),
+        n => (format!(" more below ↓ ({} new) ", n),
+              Style::default().fg(theme.mention_color).add_modifier(Modifier::BOLD)),
+    };
+
+    let width = text.width() as u16;
+    if chunk.height < 2 || width + 4 > chunk.width / 2 {
+        return;
+    }
+
+    let area = Rect::new(chunk.x + chunk.width - 2 - width, chunk.y + chunk.height - 1, width, 1);
+    frame.render_widget(Paragraph::new(Span::styled(text, style)), area);
+}
+
+
+/// "✓ seen by alice, bob" right aligned over the bottom border of the message panel
+fn draw_seen_by(
+    frame: &mut Frame<CrosstermBackend<impl Write>>,
+    conf: &config::Config,
+    state: &view_model::ChatMessageViewModel,
+    chunk: Rect,
+    theme: &Theme,
+) {
+    let seen_by = state.seen_by(&conf.user_name);
+    if seen_by.is_empty() || chunk.height < 2 {
+        return;
+    }
+
+    // leave the left half of the border to the typing indicator
+    let max_width = (chunk.width / 2).saturating_sub(2) as usize;
+    let mut text = format!(" ✓ seen by {} ", seen_by.join(", "));
+    if text.width() > max_width {
+        text = format!(" ✓ seen by {} ", seen_by.len());
+    }
+    if text.width() > max_width {
+        return;
+    }
+
+    let width = text.width() as u16;
+    let area = Rect::new(chunk.x + chunk.width - 2 - width, chunk.y + chunk.height - 1, width, 1);
+    frame.render_widget(Paragraph::new(Span::styled(text, Style::default().fg(theme.date_color))), area);
+}
+
+
+fn title(conf: &config::Config, state: &view_model::ChatMessageViewModel) -> String {
+    let mut title = format!("Chat Room : {}", conf.chat_id);
+    if !state.topic.is_empty() {
+        title.push_str(&format!(" · {}", state.topic));
+    }
+
+    let mentions = state.mention_ids(&conf.user_name).len();
+    match mentions {
+        0 => {}
+        1 => title.push_str(" · 1 mention (Alt+M)"),
+        _ => title.push_str(&format!(" · {} mentions (Alt+M)", mentions)),
+    }
+
+    let mut unread: Vec<_> = state.unread_chats.iter().collect();
+    unread.sort();
+    if !unread.is_empty() {
+        let chats: Vec<String> = unread.iter().map(|(chat_id, count)| format!("{} ({})", chat_id, count)).collect();
+        title.push_str(&format!(" · unread: {}", chats.join(", ")));
+    }
+
+    title
+}
+
+
+/// `👍 2 ` per reaction, flagged if we are one of the reacting users
+/// the name in the header, marked as ours, as a direct message or as a bot
+fn display_name(msg: &Message, user_name: &str, me: &str) -> String {
+    let mut name = user_name.to_string();
+    if name == me {
+        name = format!("{}(me)", me);
+    }
+
+    if let Some(to) = &msg.direct_to {
+        name = format!("{} → {} (DM)", name, to);
+    }
+
+    if msg.kind == MessageKind::Bot {
+        name = format!("{} [bot]", name);
+    }
+
+    name
+}
+
+fn reaction_labels(msg: &Message, me: &str) -> Vec<(String, bool)> {
+    msg.reactions.iter()
+        .map(|r| (format!("{} {} ", r.emoji, r.users.len()), r.users.iter().any(|u| u == me)))
+        .collect()
+}
+
+
+/// one line quote of the replied message, cut to `width`
+fn reply_preview(state: &view_model::ChatMessageViewModel, reply_to: i64, width: usize) -> String {
+    let quote = match state.find(reply_to) {
+        Some(msg @ Message { user: SomeUser::User(u), .. }) => {
+            format!("  ┌ {}: {}", u.user_name, msg.text().lines().next().unwrap_or_default())
+        }
+        _ => "  ┌ (message deleted)".to_string(),
+    };
+
+    truncate_to_width(&quote, width)
+}
+
+pub(super) fn truncate_to_width(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+
+    let mut result = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let char_width = c.width().unwrap_or(0);
+        if used + char_width + 1 > width {
+            break;
+        }
+        result.push(c);
+        used += char_width;
+    }
+    result.push('…');
+
+    result
+}
+
+
+/// "alice is typing…" drawn over the bottom border of the message panel
+fn draw_typists(
+    frame: &mut Frame<CrosstermBackend<impl Write>>,
+    state: &view_model::ChatMessageViewModel,
+    chunk: Rect,
+    theme: &Theme,
+) {
+    let typists = state.typists();
+    if typists.is_empty() || chunk.width <= 4 || chunk.height < 2 {
+        return;
+    }
+
+    let text = match typists.as_slice() {
+        [one] => format!(" {} is typing… ", one),
+        [one, two] => format!(" {} and {} are typing… ", one, two),
+        _ => " several people are typing… ".to_string(),
+    };
+
+    let area = Rect::new(chunk.x + 2, chunk.y + chunk.height - 1, chunk.width - 4, 1);
+    let typing = Paragraph::new(Span::styled(text, Style::default()
+        .fg(theme.date_color)
+        .add_modifier(Modifier::ITALIC)));
+    frame.render_widget(typing, area);
+}
+
+pub(super) fn get_time_string_with_custom(timestamp: i64, str: &str) -> String {
     // 将 i64 时间戳转换为 NaiveDateTime
     let naive_datetime = NaiveDateTime::from_timestamp_opt(timestamp, 0).unwrap();
     // 将 NaiveDateTime 转换为 DateTime<Utc>
@@ -97,70 +362,163 @@ fn get_time_string_with_custom(timestamp: i64, str: &str) -> String {
 }
 
 
+/// keep the newest message in view while following, otherwise stay where the user scrolled to,
+/// following resumes once the view is back at the bottom
 pub async fn adjust_scroll_pos(state: Arc<RwLock<AppViewModel>>, rect: Rect) {
-    if rect.height == 0 || rect.width == 0 {
+    if rect.height < 3 || rect.width < 3 {
         return;
     }
 
     let mut state = state.write().await;
-    let scroll_messages_view_pos = calculate_scroll_pos(&mut state, &rect);
-    if scroll_messages_view_pos > 0 {
-        state.msg_vm.scroll_pos = scroll_messages_view_pos
-    }
-}
+    let height = rect.height as usize - 2;
+    let width = rect.width as usize - 2;
+    let bottom = calculate_message_lines(width, &state.conf.user_name, &state.msg_vm).saturating_sub(height);
 
-pub fn calculate_scroll_pos(state: &mut AppViewModel, chunk: &Rect) -> usize {
-    if state.msg_vm.messages.is_empty() {
-        return 0;
+    let vm = &mut state.msg_vm;
+    vm.page_height = height;
+    vm.scroll_pos = match vm.follow {
+        true => bottom,
+        false => vm.scroll_pos.min(bottom),
+    };
+
+    scroll_to_selected(&mut state, &rect);
+
+    let vm = &mut state.msg_vm;
+    vm.follow = vm.scroll_pos >= bottom;
+    if vm.follow {
+        vm.unseen = 0;
     }
+}
 
+fn calculate_message_lines(width: usize, me: &str, state: &view_model::ChatMessageViewModel) -> usize {
+    let divider = state.first_unread().is_some() as usize;
+    state.messages.iter().map(|message| message_lines(width, me, message)).sum::<usize>() + divider
+}
 
-    let curr_pos = state.msg_vm.scroll_pos;
-    let mut new_pos: usize = 0;
-    let height = chunk.height as usize - 4;
-    let width = chunk.width as usize - 2;
-    let lines: usize = calculate_message_lines(width, &state.msg_vm.messages);
+/// wraps the same text as `draw` does, the styles don't change the widths
+fn message_lines(width: usize, me: &str, message: &Message) -> usize {
+    if let SomeUser::User(u) = message.user.clone() {
+        let remote = format!(" ({}) ", u.address);
+        let (content, header) = match message.kind {
+            MessageKind::Emote => {
+                let mut line = vec![
+                    (DATE_PLACEHOLDER.to_string(), ()),
+                    (remote, ()),
+                    (emote_text(message), ()),
+                ];
+                if message.edited {
+                    line.push((EDITED_MARKER.to_string(), ()));
+                }
+                (markup::wrap(&line, width).len(), 0)
+            }
+            _ => {
+                let header = vec![
+                    (DATE_PLACEHOLDER.to_string(), ()),
+                    (remote, ()),
+                    (format!("{} :", display_name(message, &u.user_name, me)), ()),
+                ];
+                let rows: usize = content_lines(message).iter().map(|line| markup::wrap(line, width).len()).sum();
+                (rows, markup::wrap(&header, width).len())
+            }
+        };
 
-    if lines > height && curr_pos < lines - height {
-        new_pos = lines - height;
-    } else if lines > 2 && curr_pos >= lines - 2 {
-        return lines - 2;
-    }
+        // header rows, plus the quote line of a reply
+        let header = header + message.reply_to.is_some() as usize;
 
-    let mut need_update_pos = false;
-    if state.msg_vm.scroll_to_pos > 0
-        && state.msg_vm.scroll_to_pos.le(&state.msg_vm.messages.len()) {
-        state.msg_vm.scroll_to_pos = 0;
-        need_update_pos = true;
-    }
+        let reactions = match message.reactions.is_empty() {
+            true => 0,
+            false => {
+                let line: Vec<_> = std::iter::once(("  ".to_string(), ()))
+                    .chain(reaction_labels(message, me).into_iter().map(|(label, _)| (label, ())))
+                    .collect();
+                markup::wrap(&line, width).len()
+            }
+        };
 
-    if need_update_pos {
-        new_pos
+        content + header + reactions
     } else {
-        0
+        notice_lines(DATE_PLACEHOLDER.to_string(), &message.content, (), ())
+            .iter()
+            .map(|line| markup::wrap(line, width).len())
+            .sum()
     }
 }
 
-fn calculate_message_lines(width: usize, message_list: &Vec<Message>) -> usize {
-    let mut lines: usize = 0;
-    for message in message_list {
-        if let SomeUser::User(_) = message.user.clone() {
-            let content = format!("  {}", &message.content);
-            let mut content_with: usize = 0;
-            for c in content.chars() {
-                let char_width = unicode_width::UnicodeWidthChar::width(c).unwrap_or(0);
-                content_with += char_width;
+
+/// scroll so that the selected message is inside the panel
+fn scroll_to_selected(state: &mut AppViewModel, chunk: &Rect) {
+    let selected = match state.msg_vm.selected {
+        Some(selected) => selected,
+        None => return,
+    };
+
+    let height = chunk.height.saturating_sub(2) as usize;
+    let width = chunk.width as usize - 2;
+    let first_unread = state.msg_vm.first_unread();
+    let mut top = 0;
+    for message in state.msg_vm.messages.iter() {
+        if message.id.is_some() && message.id == first_unread {
+            top += 1;
+        }
+
+        let lines = message_lines(width, &state.conf.user_name, message);
+        if message.id == Some(selected) {
+            let pos = &mut state.msg_vm.scroll_pos;
+            if top < *pos {
+                *pos = top;
+            } else if top + lines > *pos + height {
+                *pos = (top + lines).saturating_sub(height);
             }
+            return;
+        }
+        top += lines;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sophia_core::model::{MessageKind, Reaction, User, UserInfo};
 
-            let result = content_with as f64 / width as f64;
-            let ceil_result = result.ceil();
-            let result_usize = ceil_result as usize;
+    use crate::view_model::{Message, SomeUser};
 
-            lines += result_usize + 1
-        } else {
-            lines += 1;
+    use super::message_lines;
+
+    fn message(address: &str, content: &str) -> Message {
+        let user = UserInfo::new("alice".to_string(), address.to_string(), String::new(), 1, 0);
+        Message {
+            id: Some(1),
+            time: 0,
+            content: content.to_string(),
+            user: SomeUser::User(User::from_user_info(&user)),
+            edited: false,
+            reply_to: None,
+            reactions: Vec::new(),
+            mentions: Vec::new(),
+            direct_to: None,
+            file: None,
+            kind: MessageKind::Text,
         }
     }
 
-    lines
-}
\ No newline at end of file
+    #[test]
+    fn header_rows_are_counted() {
+        // "00:00:00 (1.2.3.4:5) alice :" fits in 40 columns, the content row follows
+        assert_eq!(message_lines(40, "bob", &message("1.2.3.4:5", "hi")), 2);
+
+        let mut msg = message("[2001:db8::1]:4433", "hi");
+        msg.direct_to = Some("bob".to_string());
+        // "00:00:00 ([2001:db8::1]:4433) " | "alice → bob (DM) :"
+        assert_eq!(message_lines(30, "bob", &msg), 3);
+        assert_eq!(message_lines(60, "bob", &msg), 2);
+    }
+
+    #[test]
+    fn reaction_rows_are_counted() {
+        let mut msg = message("1.2.3.4:5", "hi");
+        msg.reactions = (0..6).map(|i| Reaction { emoji: format!(":r{}:", i), users: vec!["bob".to_string()] }).collect();
+        // two columns of indent and six labels of 7 columns
+        assert_eq!(message_lines(80, "bob", &msg), 3);
+        // four labels fill a row of 30
+        assert_eq!(message_lines(30, "bob", &msg), 2 + 2);
+    }
+}
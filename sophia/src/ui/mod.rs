@@ -2,8 +2,13 @@ pub use app_view::AppView;
 
 mod theme;
 mod app_view;
+mod completion_view;
 mod log_view;
+mod markup;
 mod input_view;
 mod message_view;
+mod mouse_view;
+mod reaction_picker_view;
+mod search_view;
 mod user_list_view;
 
@@ -0,0 +1,130 @@
+use tui::buffer::Buffer;
+use tui::layout::Rect;
+use tui::style::{Modifier, Style};
+use tui::widgets::Widget;
+use unicode_width::UnicodeWidthStr;
+
+use crate::view_model::TextSelection;
+
+/// recolors the border of the focused panel, leaving its title and content as they were drawn
+pub struct FocusFrame {
+    pub style: Style,
+}
+
+impl Widget for FocusFrame {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.width < 2 || area.height < 2 {
+            return;
+        }
+
+        for x in area.left()..area.right() {
+            buf.get_mut(x, area.top()).set_style(self.style);
+            buf.get_mut(x, area.bottom() - 1).set_style(self.style);
+        }
+        for y in area.top()..area.bottom() {
+            buf.get_mut(area.left(), y).set_style(self.style);
+            buf.get_mut(area.right() - 1, y).set_style(self.style);
+        }
+    }
+}
+
+/// highlights the selected cells after everything else is drawn,
+/// and reads their text into `copied` when the selection is to be copied
+pub struct Selection<'a> {
+    pub selection: TextSelection,
+    pub copied: &'a mut String,
+}
+
+impl<'a> Widget for Selection<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let selection = self.selection;
+        let (start, end) = ordered(selection.anchor, selection.head);
+        // made before the terminal shrank
+        if selection.area.right() > area.right() || selection.area.bottom() > area.bottom() {
+            return;
+        }
+
+        let mut lines = Vec::new();
+        for y in start.1..=end.1 {
+            let first = if y == start.1 { start.0 } else { selection.area.left() };
+            let last = if y == end.1 { end.0 } else { selection.area.right() - 1 };
+
+            let mut line = String::new();
+            let mut x = first;
+            while x <= last {
+                let cell = buf.get_mut(x, y);
+                cell.set_style(Style::default().add_modifier(Modifier::REVERSED));
+                line.push_str(&cell.symbol);
+                // the cells covered by a wide char hold a filler
+                x += cell.symbol.width().max(1) as u16;
+            }
+            lines.push(line.trim_end().to_string());
+        }
+
+        if selection.copy {
+            *self.copied = lines.join("\n");
+        }
+    }
+}
+
+/// `a` and `b` in reading order, as (column, row)
+fn ordered(a: (u16, u16), b: (u16, u16)) -> ((u16, u16), (u16, u16)) {
+    match (a.1, a.0) <= (b.1, b.0) {
+        true => (a, b),
+        false => (b, a),
+    }
+}
+
+/// the terminal puts `text` on the system clipboard (OSC 52)
+pub fn clipboard_sequence(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", base64(text.as_bytes()))
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => encoded.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char),
+                false => encoded.push('='),
+            }
+        }
+    }
+
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{base64, clipboard_sequence, ordered};
+
+    #[test]
+    fn base64_rfc4648_vectors() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foob"), "Zm9vYg==");
+        assert_eq!(base64(b"fooba"), "Zm9vYmE=");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        // every bit set, the last chars of the alphabet
+        assert_eq!(base64(&[0xff, 0xff, 0xfe]), "///+");
+    }
+
+    #[test]
+    fn clipboard_sequence_wraps_base64() {
+        assert_eq!(clipboard_sequence("hi"), "\x1b]52;c;aGk=\x07");
+    }
+
+    #[test]
+    fn ordered_by_row_then_column() {
+        assert_eq!(ordered((5, 1), (2, 3)), ((5, 1), (2, 3)));
+        assert_eq!(ordered((2, 3), (5, 1)), ((5, 1), (2, 3)));
+        assert_eq!(ordered((7, 2), (3, 2)), ((3, 2), (7, 2)));
+        assert_eq!(ordered((3, 2), (3, 2)), ((3, 2), (3, 2)));
+    }
+}
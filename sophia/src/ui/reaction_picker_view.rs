@@ -0,0 +1,53 @@
+use std::io::Write;
+
+use tui::backend::CrosstermBackend;
+use tui::Frame;
+use tui::layout::Rect;
+use tui::style::{Modifier, Style};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, Borders, Clear, Paragraph};
+
+use crate::ui::theme::Theme;
+use crate::view_model;
+use crate::view_model::PICKER_EMOJIS;
+
+const PICKER_HEIGHT: u16 = 3;
+
+/// emoji bar drawn over the bottom of the message panel while the picker is open
+pub fn draw(
+    frame: &mut Frame<CrosstermBackend<impl Write>>,
+    state: &view_model::ChatMessageViewModel,
+    chunk: Rect,
+    theme: &Theme,
+) {
+    let cursor = match state.picker {
+        Some(cursor) => cursor,
+        None => return,
+    };
+
+    // 5 columns per emoji: " 1👍 "
+    let width = (PICKER_EMOJIS.len() as u16 * 5 + 2).min(chunk.width);
+    if chunk.height < PICKER_HEIGHT + 2 {
+        return;
+    }
+    let area = Rect::new(chunk.x + 1, chunk.y + chunk.height - PICKER_HEIGHT - 1, width, PICKER_HEIGHT);
+
+    let emojis: Vec<Span> = PICKER_EMOJIS.iter().enumerate()
+        .map(|(idx, emoji)| {
+            let style = match idx == cursor {
+                true => Style::default().add_modifier(Modifier::REVERSED),
+                false => Style::default(),
+            };
+            Span::styled(format!(" {}{} ", idx + 1, emoji), style)
+        })
+        .collect();
+
+    let picker = Paragraph::new(Spans::from(emojis))
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .title(Span::styled("React", Style::default().add_modifier(Modifier::BOLD))))
+        .style(Style::default().fg(theme.panel_border_color));
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(picker, area);
+}
@@ -0,0 +1,70 @@
+use std::io::Write;
+
+use tui::backend::CrosstermBackend;
+use tui::Frame;
+use tui::layout::Rect;
+use tui::style::{Modifier, Style};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, Borders, Clear, Paragraph};
+
+use crate::ui::message_view::{get_time_string_with_custom, truncate_to_width};
+use crate::ui::theme::Theme;
+use crate::view_model;
+use crate::view_model::SomeUser;
+
+/// list of `/search` hits drawn over the top of the message panel, one line per hit
+pub fn draw(
+    frame: &mut Frame<CrosstermBackend<impl Write>>,
+    state: &view_model::ChatMessageViewModel,
+    chunk: Rect,
+    theme: &Theme,
+) {
+    let search = match &state.search {
+        Some(search) => search,
+        None => return,
+    };
+    if chunk.height < 5 || chunk.width < 10 {
+        return;
+    }
+
+    let rows = search.hits.len().max(1) as u16;
+    let height = (rows + 2).min(chunk.height - 2);
+    let area = Rect::new(chunk.x + 1, chunk.y + 1, chunk.width - 2, height);
+    let width = area.width.saturating_sub(2) as usize;
+
+    // keep the cursor inside the visible rows
+    let visible = (height - 2) as usize;
+    let first = (search.cursor + 1).saturating_sub(visible);
+
+    let lines: Vec<Spans> = match search.hits.is_empty() {
+        true => vec![Spans::from(Span::styled("no messages found", Style::default().fg(theme.date_color)))],
+        false => search.hits.iter().enumerate().skip(first).take(visible)
+            .map(|(idx, hit)| {
+                let name = match &hit.user {
+                    SomeUser::User(u) => u.user_name.to_string(),
+                    SomeUser::System => String::new(),
+                };
+                let date = get_time_string_with_custom(hit.time, "%m-%d %H:%M");
+                let text = hit.text();
+                let content = text.lines().next().unwrap_or_default();
+                let line = truncate_to_width(&format!("{} {}: {}", date, name, content), width);
+
+                let style = match idx == search.cursor {
+                    true => Style::default().add_modifier(Modifier::REVERSED),
+                    false => Style::default(),
+                };
+                Spans::from(Span::styled(line, style))
+            })
+            .collect(),
+    };
+
+    let title = format!("Search \"{}\" ({} hits, Enter jumps, Esc closes)", search.query, search.hits.len());
+    let list = Paragraph::new(lines)
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .title(Span::styled(truncate_to_width(&title, width), Style::default().add_modifier(Modifier::BOLD))))
+        .style(Style::default().fg(theme.panel_border_color));
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(list, area);
+}
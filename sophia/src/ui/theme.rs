@@ -7,6 +7,17 @@ pub struct Theme {
     pub address_color: Color,
     pub system_info_color: (Color, Color),
     pub panel_border_color: Color,
+    /// online, away, busy
+    pub status_colors: (Color, Color, Color),
+    /// content of messages mentioning us
+    pub mention_color: Color,
+    /// `code` and code blocks
+    pub code_color: Color,
+    /// > quoted lines
+    pub quote_color: Color,
+    pub url_color: Color,
+    /// border of the panel clicked last
+    pub focus_color: Color,
 }
 
 impl Default for Theme {
@@ -32,6 +43,12 @@ impl Theme {
             address_color: Color::DarkGray,
             system_info_color: (Color::LightRed, Color::LightCyan),
             panel_border_color: Color::White,
+            status_colors: (Color::Green, Color::Yellow, Color::Red),
+            mention_color: Color::LightYellow,
+            code_color: Color::LightCyan,
+            quote_color: Color::Gray,
+            url_color: Color::LightBlue,
+            focus_color: Color::LightCyan,
         }
     }
 
@@ -43,6 +60,12 @@ impl Theme {
             address_color: Color::Black,
             system_info_color: (Color::LightRed, Color::LightCyan),
             panel_border_color: Color::Black,
+            status_colors: (Color::Green, Color::Rgb(255, 165, 0), Color::Red),
+            mention_color: Color::Blue,
+            code_color: Color::Magenta,
+            quote_color: Color::DarkGray,
+            url_color: Color::Blue,
+            focus_color: Color::Blue,
         }
     }
 }
\ No newline at end of file
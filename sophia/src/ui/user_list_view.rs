@@ -7,7 +7,13 @@ use tui::style::{Modifier, Style};
 use tui::text::{Span, Spans};
 use tui::widgets::{Block, Borders, Paragraph, Wrap};
 
+use chrono::Utc;
+
+use sophia_core::duration::format_duration;
+use sophia_core::model::{Role, Status, User};
+
 use crate::{config, view_model};
+use crate::ui::markup::{self, Line};
 use crate::ui::theme::Theme;
 
 pub fn draw(
@@ -16,24 +22,14 @@ pub fn draw(
     state: &view_model::UserViewModel,
     chunk: Rect,
     theme: &Theme) {
-    let mut users: Vec<Spans> = Vec::new();
-
-    for user in state.users.iter() {
-        let idx = user.login_time as usize;
-        let mut color = theme.message_colors[idx % theme.message_colors.len()];
-        let mut user_name = user.user_name.to_string();
-        if user_name == conf.user_name {
-            user_name = format!("{}(me)", user_name);
-            color = theme.my_user_color;
-        }
+    let now = Utc::now().timestamp();
+    let width = chunk.width.saturating_sub(2) as usize;
 
-        users.push(
-            Spans::from(vec![
-                Span::styled(user_name, Style::default().fg(color)),
-                Span::styled(format!("-{}", &user.address), Style::default().fg(theme.address_color)),
-            ])
-        )
-    }
+    // wrapped here, so that `user_rows` knows the rows of every user
+    let users: Vec<Spans> = state.users.iter()
+        .flat_map(|user| markup::wrap(&user_line(conf, user, theme, now), width))
+        .map(|row| Spans::from(row.into_iter().map(|(text, style)| Span::styled(text, style)).collect::<Vec<_>>()))
+        .collect();
 
 
     let user_list_panel = Paragraph::new(users)
@@ -48,3 +44,65 @@ pub fn draw(
 
     frame.render_widget(user_list_panel, chunk);
 }
+
+/// screen row -> user name of the rows `draw` fills in `chunk`, for clicks on the list
+pub fn user_rows(conf: &config::Config, state: &view_model::UserViewModel, chunk: Rect) -> Vec<(u16, String)> {
+    let now = Utc::now().timestamp();
+    let width = chunk.width.saturating_sub(2) as usize;
+    let theme = Theme::default();
+
+    let mut rows = Vec::new();
+    let mut row = chunk.y + 1;
+    for user in state.users.iter() {
+        for _ in markup::wrap(&user_line(conf, user, &theme, now), width) {
+            if row + 1 >= chunk.bottom() {
+                return rows;
+            }
+            rows.push((row, user.user_name.to_string()));
+            row += 1;
+        }
+    }
+
+    rows
+}
+
+fn user_line(conf: &config::Config, user: &User, theme: &Theme, now: i64) -> Line<Style> {
+    let idx = user.login_time as usize;
+    let mut color = theme.message_colors[idx % theme.message_colors.len()];
+    let mut user_name = user.user_name.to_string();
+    if user_name == conf.user_name {
+        user_name = format!("{}(me)", user_name);
+        color = theme.my_user_color;
+    }
+
+    // irc style prefix for room operators
+    let prefix = match user.role {
+        Role::Owner => "~",
+        Role::Moderator => "@",
+        Role::Member => "",
+    };
+
+    let (icon, status_color) = match user.status {
+        Status::Online => ("● ", theme.status_colors.0),
+        Status::Away => ("◐ ", theme.status_colors.1),
+        Status::Busy => ("⊘ ", theme.status_colors.2),
+    };
+
+    let mut line = vec![
+        (icon.to_string(), Style::default().fg(status_color)),
+        (prefix.to_string(), Style::default().fg(theme.system_info_color.1).add_modifier(Modifier::BOLD)),
+        (user_name, Style::default().fg(color)),
+        (format!("-{}", &user.address), Style::default().fg(theme.address_color)),
+    ];
+
+    let mut status = user.status_text.to_string();
+    if user.status == Status::Away {
+        let idle = format!("idle {}", format_duration(now - user.last_active));
+        status = if status.is_empty() { idle } else { format!("{}, {}", status, idle) };
+    }
+    if !status.is_empty() {
+        line.push((format!(" ({})", status), Style::default().fg(status_color).add_modifier(Modifier::ITALIC)));
+    }
+
+    line
+}
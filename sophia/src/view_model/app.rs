@@ -2,6 +2,7 @@ use crate::config::Config;
 use crate::view_model::input::InputViewModel;
 use crate::view_model::log::LogViewModel;
 use crate::view_model::messages::ChatMessageViewModel;
+use crate::view_model::mouse::MouseViewModel;
 use crate::view_model::user_list::UserViewModel;
 
 #[derive(Clone, Debug)]
@@ -10,6 +11,7 @@ pub struct AppViewModel {
     pub input_vm: InputViewModel,
     pub user_vm: UserViewModel,
     pub msg_vm: ChatMessageViewModel,
+    pub mouse_vm: MouseViewModel,
     pub conf: Config,
 }
 
@@ -21,6 +23,7 @@ impl AppViewModel {
             user_vm: UserViewModel::new(),
             log_vm: LogViewModel::new(),
             input_vm: InputViewModel::new(),
+            mouse_vm: MouseViewModel::new(),
             conf,
         }
     }
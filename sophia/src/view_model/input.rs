@@ -1,9 +1,42 @@
+use std::time::{Duration, Instant};
+
 use crossterm::event::KeyCode;
 
 #[derive(Clone, Debug)]
 pub struct InputViewModel {
     pub text: Vec<char>,
     pub cursor: usize,
+    /// set when the server rate limited us, sending is blocked until then
+    pub throttled_until: Option<Instant>,
+    /// message the next message replies to
+    pub reply_to: Option<ReplyTarget>,
+    /// text removed by the last kill, Ctrl+Y puts it back
+    pub kill_buffer: Vec<char>,
+    /// sent input, oldest first
+    pub history: Vec<String>,
+    /// entry shown while going through the history with Up / Down
+    history_pos: Option<usize>,
+    /// the unsent input from before going through the history
+    draft: Vec<char>,
+    /// open while Tab goes through more than one candidate
+    pub completion: Option<Completion>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Completion {
+    /// where the completed word starts in the text, the candidate replaces it up to the cursor
+    pub start: usize,
+    pub candidates: Vec<String>,
+    pub selected: usize,
+}
+
+/// sent messages and commands kept for Up / Down
+pub const MAX_HISTORY: usize = 500;
+
+#[derive(Clone, Debug)]
+pub struct ReplyTarget {
+    pub id: i64,
+    pub user_name: String,
 }
 
 
@@ -12,6 +45,13 @@ impl InputViewModel {
         Self {
             text: Vec::new(),
             cursor: 0,
+            throttled_until: None,
+            reply_to: None,
+            kill_buffer: Vec::new(),
+            history: Vec::new(),
+            history_pos: None,
+            draft: Vec::new(),
+            completion: None,
         }
     }
 
@@ -19,6 +59,34 @@ impl InputViewModel {
         &self.text
     }
 
+    /// block sending until `retry_after` from now, or later if already blocked for longer,
+    /// returns `true` if sending was not blocked before
+    pub fn throttle(&mut self, retry_after: Duration) -> bool {
+        let until = Instant::now() + retry_after;
+        match self.throttled_until {
+            Some(current) => {
+                self.throttled_until = Some(current.max(until));
+                false
+            }
+            None => {
+                self.throttled_until = Some(until);
+                true
+            }
+        }
+    }
+
+    /// remaining throttle time, clears the throttle once it has expired
+    pub fn throttle_remaining(&mut self) -> Option<Duration> {
+        let until = self.throttled_until?;
+        let now = Instant::now();
+        if now >= until {
+            self.throttled_until = None;
+            return None;
+        }
+
+        Some(until - now)
+    }
+
     pub fn input_write(&mut self, character: char) {
         self.text.insert(self.cursor, character);
         self.cursor += 1;
@@ -33,6 +101,9 @@ impl InputViewModel {
     pub fn clean_input(&mut self) {
         self.cursor = 0;
         self.text = Vec::new();
+        self.reply_to = None;
+        self.history_pos = None;
+        self.completion = None;
     }
 
 
@@ -65,5 +136,177 @@ impl InputViewModel {
             _ => {}
         }
     }
+
+    /// Alt+B / Alt+F, to the start of this or the previous word, or past the end of the next one
+    pub fn input_move_word(&mut self, forward: bool) {
+        self.cursor = match forward {
+            true => self.word_end(char::is_alphanumeric),
+            false => self.word_start(char::is_alphanumeric),
+        };
+    }
+
+    /// Ctrl+W kills the whitespace separated word before the cursor, Alt+D the word after it
+    pub fn input_kill_word(&mut self, forward: bool) {
+        match forward {
+            true => {
+                let end = self.word_end(char::is_alphanumeric);
+                self.kill(self.cursor, end);
+            }
+            false => {
+                let start = self.word_start(|c| !c.is_whitespace());
+                self.kill(start, self.cursor);
+            }
+        }
+    }
+
+    /// Ctrl+K kills to the end of the line, Ctrl+U to its start,
+    /// at the end of a line Ctrl+K joins it with the next one
+    pub fn input_kill_line(&mut self, to_end: bool) {
+        match to_end {
+            true => {
+                let end = self.text[self.cursor..].iter()
+                    .position(|c| *c == '\n')
+                    .map_or(self.text.len(), |i| self.cursor + i.max(1));
+                self.kill(self.cursor, end);
+            }
+            false => {
+                let start = self.text[..self.cursor].iter()
+                    .rposition(|c| *c == '\n')
+                    .map_or(0, |i| i + 1);
+                self.kill(start, self.cursor);
+            }
+        }
+    }
+
+    /// `@name ` at the cursor, apart from the word before it
+    pub fn insert_mention(&mut self, name: &str) {
+        let mut mention = format!("@{} ", name);
+        if self.cursor > 0 && !self.text[self.cursor - 1].is_whitespace() {
+            mention.insert(0, ' ');
+        }
+
+        self.text.splice(self.cursor..self.cursor, mention.chars());
+        self.cursor += mention.chars().count();
+    }
+
+    /// turn the input into a direct message to `name`, replacing a command typed so far
+    pub fn start_direct_message(&mut self, name: &str) {
+        if self.text.first() == Some(&'/') {
+            self.text.clear();
+        }
+
+        let prefix = format!("/msg {} ", name);
+        self.text.splice(0..0, prefix.chars());
+        self.cursor = self.text.len();
+    }
+
+    /// Ctrl+Y
+    pub fn input_yank(&mut self) {
+        let yanked = self.kill_buffer.clone();
+        self.text.splice(self.cursor..self.cursor, yanked.iter().copied());
+        self.cursor += yanked.len();
+    }
+
+    /// Up shows the previous sent input, Down the next one and finally the unsent input again
+    pub fn input_history(&mut self, movement: KeyCode) {
+        let pos = match (movement, self.history_pos) {
+            (KeyCode::Up, None) if !self.history.is_empty() => {
+                self.draft = self.text.clone();
+                self.history.len() - 1
+            }
+            (KeyCode::Up, Some(pos)) if pos > 0 => pos - 1,
+            (KeyCode::Down, Some(pos)) if pos + 1 < self.history.len() => pos + 1,
+            (KeyCode::Down, Some(_)) => {
+                self.history_pos = None;
+                self.text = std::mem::take(&mut self.draft);
+                self.cursor = self.text.len();
+                return;
+            }
+            _ => return,
+        };
+
+        self.history_pos = Some(pos);
+        self.text = self.history[pos].chars().collect();
+        self.cursor = self.text.len();
+    }
+
+    /// add sent input to the history, returns false for a repeat of the newest entry
+    pub fn remember(&mut self, entry: &str) -> bool {
+        self.history_pos = None;
+        if self.history.last().map(String::as_str) == Some(entry) {
+            return false;
+        }
+
+        self.history.push(entry.to_string());
+        if self.history.len() > MAX_HISTORY {
+            self.history.remove(0);
+        }
+        true
+    }
+
+    /// put the first candidate in place of the word at `start`, a single candidate needs no list
+    pub fn complete(&mut self, start: usize, candidates: Vec<String>) {
+        let completion = Completion { start, candidates, selected: 0 };
+        self.replace_word(start, &completion.candidates[0]);
+        if completion.candidates.len() > 1 {
+            self.completion = Some(completion);
+        }
+    }
+
+    /// repeated Tab (or Shift+Tab) replaces the candidate with the next (or previous) one,
+    /// returns false when no completion is open
+    pub fn complete_next(&mut self, backwards: bool) -> bool {
+        let (start, candidate) = match &mut self.completion {
+            Some(completion) => {
+                let count = completion.candidates.len();
+                completion.selected = match backwards {
+                    true => (completion.selected + count - 1) % count,
+                    false => (completion.selected + 1) % count,
+                };
+                (completion.start, completion.candidates[completion.selected].to_string())
+            }
+            None => return false,
+        };
+
+        self.replace_word(start, &candidate);
+        true
+    }
+
+    fn replace_word(&mut self, start: usize, word: &str) {
+        let len = self.text.len();
+        self.text.splice(start..self.cursor.min(len), word.chars());
+        self.cursor = start + word.chars().count();
+    }
+
+    fn kill(&mut self, start: usize, end: usize) {
+        if start < end {
+            self.kill_buffer = self.text.drain(start..end).collect();
+            self.cursor = start;
+        }
+    }
+
+    /// skip the non-word chars before the cursor, then the word
+    fn word_start(&self, is_word: impl Fn(char) -> bool) -> usize {
+        let mut pos = self.cursor;
+        while pos > 0 && !is_word(self.text[pos - 1]) {
+            pos -= 1;
+        }
+        while pos > 0 && is_word(self.text[pos - 1]) {
+            pos -= 1;
+        }
+        pos
+    }
+
+    /// skip the non-word chars after the cursor, then the word
+    fn word_end(&self, is_word: impl Fn(char) -> bool) -> usize {
+        let mut pos = self.cursor;
+        while pos < self.text.len() && !is_word(self.text[pos]) {
+            pos += 1;
+        }
+        while pos < self.text.len() && is_word(self.text[pos]) {
+            pos += 1;
+        }
+        pos
+    }
 }
 
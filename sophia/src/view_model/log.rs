@@ -1,14 +1,41 @@
 use chrono::{DateTime, Local};
+use crossterm::event::KeyCode;
 use log::Level;
 
 #[derive(Clone, Debug)]
 pub struct LogViewModel {
     pub contents: Vec<Log>,
+    /// lines scrolled down from the newest log, which is drawn first
+    pub scroll_pos: usize,
+    /// lines the log panel shows, set at every render
+    pub page_height: usize,
+    /// lines of all logs wrapped to the panel width, set at every render
+    pub lines: usize,
 }
 
 impl LogViewModel {
     pub fn new() -> Self {
-        Self { contents: Vec::new() }
+        Self { contents: Vec::new(), scroll_pos: 0, page_height: 0, lines: 0 }
+    }
+
+    /// like the message panel, Home shows the newest log and End the oldest ones
+    pub fn log_scroll(&mut self, movement: KeyCode) {
+        let page = self.page_height.saturating_sub(1).max(1);
+        let bottom = self.bottom();
+        self.scroll_pos = match movement {
+            KeyCode::Up => self.scroll_pos.saturating_sub(1),
+            KeyCode::Down => self.scroll_pos + 1,
+            KeyCode::PageUp => self.scroll_pos.saturating_sub(page),
+            KeyCode::PageDown => self.scroll_pos + page,
+            KeyCode::Home => 0,
+            KeyCode::End => bottom,
+            _ => self.scroll_pos,
+        }.min(bottom);
+    }
+
+    /// scroll position showing the oldest logs at the bottom of the panel
+    pub fn bottom(&self) -> usize {
+        self.lines.saturating_sub(self.page_height)
     }
 }
 
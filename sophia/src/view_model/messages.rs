@@ -1,21 +1,73 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
 use crossterm::event::KeyCode;
 
 use sophia_core::model::Message as ModelMessage;
-use sophia_core::model::User;
+use sophia_core::model::{FileOffer, MessageKind, Reaction, User};
 
 #[derive(Clone, Debug)]
 pub struct ChatMessageViewModel {
     pub messages: Vec<Message>,
+    /// first line shown in the message panel
     pub scroll_pos: usize,
-    pub scroll_to_pos: usize,
+    /// keep the newest message in view, off while reading the history
+    pub follow: bool,
+    /// messages that arrived while not following
+    pub unseen: usize,
+    /// lines the message panel shows, set at every render
+    pub page_height: usize,
+    /// user name -> last typing event, typing events are not repeated while idle
+    pub typists: HashMap<String, Instant>,
+    /// message picked in the scrollback, e.g. to reply to it
+    pub selected: Option<i64>,
+    /// cursor in `PICKER_EMOJIS` while the reaction picker of the selected message is open
+    pub picker: Option<usize>,
+    /// mentions of us that still have to be signalled to the terminal
+    pub notifications: Vec<String>,
+    /// user_name -> last read message id, of the other members
+    pub read: HashMap<String, i64>,
+    /// our own read position when we joined, the "new messages" divider goes after it
+    pub last_read: Option<i64>,
+    /// chat_id -> unread messages, of the other chats we read before
+    pub unread_chats: HashMap<i64, i64>,
+    /// hits of the last `/search`, listed over the messages until one is chosen
+    pub search: Option<SearchResults>,
+    /// topic of the chat, empty if it has none
+    pub topic: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct SearchResults {
+    pub query: String,
+    /// newest first
+    pub hits: Vec<Message>,
+    pub cursor: usize,
 }
 
+/// reactions offered by the picker, any other emoji can still be sent with `/react`
+pub const PICKER_EMOJIS: [&str; 8] = ["👍", "👎", "😂", "😮", "😢", "🎉", "👀", "🔥"];
+
+/// a typist is dropped when no typing event arrived for this long
+pub const TYPING_EXPIRE: Duration = Duration::from_secs(4);
+
 
 #[derive(Clone, Debug)]
 pub struct Message {
+    /// server assigned id, `None` for system messages
+    pub id: Option<i64>,
     pub time: i64,
     pub content: String,
     pub user: SomeUser,
+    pub edited: bool,
+    pub reply_to: Option<i64>,
+    pub reactions: Vec<Reaction>,
+    pub mentions: Vec<String>,
+    /// recipient of a direct message, these are not part of the chat history
+    pub direct_to: Option<String>,
+    /// shared file, the content is its label
+    pub file: Option<FileOffer>,
+    pub kind: MessageKind,
 }
 
 
@@ -26,12 +78,82 @@ pub enum SomeUser {
 }
 
 impl Message {
-    pub fn new(time: i64, content: String, user: SomeUser) -> Self {
-        Message { time, content, user }
+    /// a `SystemNotice` of the server, or a line of the client itself like the `/help` output
+    pub fn notice(time: i64, content: String) -> Self {
+        Message {
+            id: None,
+            time,
+            content,
+            user: SomeUser::System,
+            edited: false,
+            reply_to: None,
+            reactions: Vec::new(),
+            mentions: Vec::new(),
+            direct_to: None,
+            file: None,
+            kind: MessageKind::Notice,
+        }
+    }
+
+    pub fn from_direct(to: String, msg: ModelMessage) -> Self {
+        Message {
+            id: None,
+            direct_to: Some(to),
+            ..Self::from_message(msg)
+        }
     }
 
     pub fn from_message(msg: ModelMessage) -> Self {
-        Message { time: msg.time, content: msg.content, user: SomeUser::User(msg.user) }
+        let content = match &msg.file {
+            Some(file) => format!("📎 {} ({}), /download {} <dest>", file.name, format_size(file.size), file.id),
+            None => msg.content,
+        };
+
+        Message {
+            id: Some(msg.id),
+            time: msg.time,
+            content,
+            user: SomeUser::User(msg.user),
+            edited: msg.edited_at.is_some(),
+            reply_to: msg.reply_to,
+            reactions: msg.reactions,
+            mentions: msg.mentions,
+            direct_to: None,
+            file: msg.file,
+            kind: msg.kind,
+        }
+    }
+
+    /// `* alice waves` for an emote, else the content
+    pub fn text(&self) -> String {
+        match (&self.user, self.kind) {
+            (SomeUser::User(user), MessageKind::Emote) => format!("* {} {}", user.user_name, self.content),
+            _ => self.content.to_string(),
+        }
+    }
+
+    /// mentions `user_name` and was written by someone else
+    pub fn mentions(&self, user_name: &str) -> bool {
+        match &self.user {
+            SomeUser::User(u) => u.user_name != user_name && self.mentions.iter().any(|m| m == user_name),
+            SomeUser::System => false,
+        }
+    }
+}
+
+/// e.g. `1.5 MiB`
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    match unit {
+        0 => format!("{} B", bytes),
+        _ => format!("{:.1} {}", size, UNITS[unit]),
     }
 }
 
@@ -40,29 +162,209 @@ impl ChatMessageViewModel {
         Self {
             messages: Vec::new(),
             scroll_pos: 0,
-            scroll_to_pos: 0,
+            follow: true,
+            unseen: 0,
+            page_height: 0,
+            typists: HashMap::new(),
+            selected: None,
+            picker: None,
+            notifications: Vec::new(),
+            read: HashMap::new(),
+            last_read: None,
+            unread_chats: HashMap::new(),
+            search: None,
+            topic: String::new(),
         }
     }
 
+    /// the first message after our read position at join, if any
+    pub fn first_unread(&self) -> Option<i64> {
+        let last_read = self.last_read?;
+        self.messages.iter().filter_map(|m| m.id).find(|id| *id > last_read)
+    }
 
-    pub fn scroll_to_end(&mut self) {
-        self.scroll_to_pos = self.messages.len();
+    pub fn newest_id(&self) -> Option<i64> {
+        self.messages.iter().rev().find_map(|m| m.id)
     }
 
-    pub fn messages_scroll(&mut self, movement: KeyCode) {
-        match movement {
-            KeyCode::Up => {
-                if self.scroll_pos > 0 {
-                    self.scroll_pos -= 1;
-                }
+    /// members other than `me` that read the newest message, sorted by name
+    pub fn seen_by(&self, me: &str) -> Vec<&str> {
+        let newest = match self.newest_id() {
+            Some(newest) => newest,
+            None => return Vec::new(),
+        };
+
+        let mut names: Vec<&str> = self.read.iter()
+            .filter(|(name, seq)| name.as_str() != me && **seq >= newest)
+            .map(|(name, _)| name.as_str())
+            .collect();
+        names.sort();
+
+        names
+    }
+
+    /// ids of the messages mentioning `user_name`, oldest first
+    pub fn mention_ids(&self, user_name: &str) -> Vec<i64> {
+        self.messages.iter()
+            .filter(|m| m.mentions(user_name))
+            .filter_map(|m| m.id)
+            .collect()
+    }
+
+    /// select the mention before the selected one, starting from the newest and wrapping around
+    pub fn select_previous_mention(&mut self, user_name: &str) {
+        let ids = self.mention_ids(user_name);
+        let before = self.selected.and_then(|selected| ids.iter().rev().find(|id| **id < selected));
+
+        self.selected = before.or(ids.last()).copied();
+        self.picker = None;
+    }
+
+    pub fn set_reactions(&mut self, id: i64, reactions: Vec<Reaction>) {
+        if let Some(m) = self.messages.iter_mut().find(|m| m.id == Some(id)) {
+            m.reactions = reactions;
+        }
+    }
+
+    pub fn open_picker(&mut self) {
+        if self.selected.is_some() {
+            self.picker = Some(0);
+        }
+    }
+
+    pub fn picker_move(&mut self, movement: KeyCode) {
+        if let Some(idx) = self.picker.as_mut() {
+            match movement {
+                KeyCode::Left => *idx = idx.checked_sub(1).unwrap_or(PICKER_EMOJIS.len() - 1),
+                KeyCode::Right => *idx = (*idx + 1) % PICKER_EMOJIS.len(),
+                _ => {}
             }
-            KeyCode::Down => {
-                self.scroll_pos += 1;
+        }
+    }
+
+    pub fn search_move(&mut self, movement: KeyCode) {
+        if let Some(search) = self.search.as_mut() {
+            match movement {
+                KeyCode::Up => search.cursor = search.cursor.saturating_sub(1),
+                KeyCode::Down => search.cursor = (search.cursor + 1).min(search.hits.len().saturating_sub(1)),
+                _ => {}
             }
-            KeyCode::PageUp => {
-                self.scroll_pos += 0;
+        }
+    }
+
+    /// close the search and select the hit under the cursor, returns `false`
+    /// if the hit is not in the loaded messages
+    pub fn search_jump(&mut self) -> bool {
+        let id = match self.search.take() {
+            Some(search) => search.hits.get(search.cursor).and_then(|hit| hit.id),
+            None => return true,
+        };
+
+        match id {
+            Some(id) if self.find(id).is_some() => {
+                self.selected = Some(id);
+                true
             }
+            Some(_) => false,
+            None => true,
+        }
+    }
+
+    pub fn find(&self, id: i64) -> Option<&Message> {
+        self.messages.iter().find(|m| m.id == Some(id))
+    }
+
+    /// move the selection to an older (`Up`) or newer (`Down`) message, system
+    /// messages are skipped, moving down past the newest message clears the selection
+    pub fn select(&mut self, movement: KeyCode) {
+        let ids: Vec<i64> = self.messages.iter().filter_map(|m| m.id).collect();
+        let pos = self.selected.and_then(|id| ids.iter().position(|i| *i == id));
+
+        self.selected = match (movement, pos) {
+            (KeyCode::Up, None) => ids.last().copied(),
+            (KeyCode::Up, Some(pos)) => ids.get(pos.saturating_sub(1)).copied(),
+            (KeyCode::Down, Some(pos)) => ids.get(pos + 1).copied(),
+            _ => None,
+        };
+    }
+
+    /// replace an edited message in place
+    pub fn edit(&mut self, msg: ModelMessage) {
+        if let Some(m) = self.messages.iter_mut().find(|m| m.id == Some(msg.id)) {
+            *m = Message::from_message(msg);
+        }
+    }
+
+    pub fn delete(&mut self, id: i64) {
+        self.messages.retain(|m| m.id != Some(id));
+        if self.selected == Some(id) {
+            self.selected = None;
+            self.picker = None;
+        }
+    }
+
+    /// id of the last message sent by `user_name`
+    pub fn last_message_of(&self, user_name: &str) -> Option<i64> {
+        self.messages.iter().rev().find_map(|m| match &m.user {
+            SomeUser::User(u) if u.user_name == user_name => m.id,
+            _ => None,
+        })
+    }
+
+    pub fn typing(&mut self, user_name: &str) {
+        self.typists.insert(user_name.to_string(), Instant::now());
+    }
+
+    pub fn stop_typing(&mut self, user_name: &str) {
+        self.typists.remove(user_name);
+    }
+
+    /// users typing right now, sorted by name
+    pub fn typists(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.typists.iter()
+            .filter(|(_, time)| time.elapsed() < TYPING_EXPIRE)
+            .map(|(name, _)| name.as_str())
+            .collect();
+        names.sort();
+
+        names
+    }
+
+
+    pub fn scroll_to_end(&mut self) {
+        self.follow = true;
+        self.unseen = 0;
+    }
+
+    /// add a message at the end, while reading the history it is counted for "more below",
+    /// unless it is our own, which brings the view back to the newest message
+    pub fn push(&mut self, msg: Message, me: &str) {
+        let own = matches!(&msg.user, SomeUser::User(u) if u.user_name == me);
+        self.messages.push(msg);
+        match own {
+            true => self.scroll_to_end(),
+            false if !self.follow => self.unseen += 1,
+            false => {}
+        }
+    }
+
+    /// Up / Down by a line, PageUp / PageDown by a page, Home / End to the oldest / newest message.
+    /// Scrolling down is limited at render, which also resumes following at the bottom
+    pub fn messages_scroll(&mut self, movement: KeyCode) {
+        let page = self.page_height.saturating_sub(1).max(1);
+        match movement {
+            KeyCode::Up => self.scroll_up(1),
+            KeyCode::Down => self.scroll_pos += 1,
+            KeyCode::PageUp => self.scroll_up(page),
+            KeyCode::PageDown => self.scroll_pos += page,
+            KeyCode::Home => self.scroll_up(self.scroll_pos),
+            KeyCode::End => self.scroll_to_end(),
             _ => {}
         }
     }
+
+    fn scroll_up(&mut self, lines: usize) {
+        self.follow = false;
+        self.scroll_pos = self.scroll_pos.saturating_sub(lines);
+    }
 }
\ No newline at end of file
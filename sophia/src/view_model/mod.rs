@@ -1,8 +1,15 @@
 pub use app::AppViewModel;
 pub use input::InputViewModel;
+pub use input::MAX_HISTORY;
+pub use input::ReplyTarget;
 pub use messages::Message;
 pub use messages::ChatMessageViewModel;
 pub use messages::SomeUser;
+pub use messages::TYPING_EXPIRE;
+pub use messages::PICKER_EMOJIS;
+pub use messages::SearchResults;
+pub use messages::format_size;
+pub use mouse::{Focus, Panels, TextSelection};
 pub use user_list::UserViewModel;
 
 pub use self::log::LogViewModel;
@@ -10,6 +17,7 @@ pub use self::log::LogViewModel;
 mod app;
 mod log;
 mod messages;
+mod mouse;
 mod input;
 mod user_list;
 
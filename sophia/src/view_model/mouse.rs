@@ -0,0 +1,119 @@
+use tui::layout::Rect;
+
+/// panel the scroll keys act on, picked by clicking it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Focus {
+    Messages,
+    Input,
+    Log,
+    Users,
+}
+
+/// where the panels were drawn last, set at every render
+#[derive(Clone, Debug, Default)]
+pub struct Panels {
+    pub messages: Rect,
+    pub input: Rect,
+    pub log: Rect,
+    pub users: Rect,
+    /// screen row -> user name, a wrapped user takes several rows
+    pub user_rows: Vec<(u16, String)>,
+}
+
+/// cells dragged over with the left button, in reading order from `anchor` to `head`
+#[derive(Clone, Copy, Debug)]
+pub struct TextSelection {
+    /// inside of the panel the drag started in, the selection stays in it
+    pub area: Rect,
+    pub anchor: (u16, u16),
+    pub head: (u16, u16),
+    /// the button was released, the text goes to the clipboard at the next render
+    pub copy: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct MouseViewModel {
+    pub focus: Focus,
+    pub panels: Panels,
+    pub selection: Option<TextSelection>,
+}
+
+
+impl MouseViewModel {
+    pub fn new() -> Self {
+        Self {
+            focus: Focus::Input,
+            panels: Panels::default(),
+            selection: None,
+        }
+    }
+
+    pub fn panel_at(&self, column: u16, row: u16) -> Option<Focus> {
+        let panels = &self.panels;
+        [
+            (panels.messages, Focus::Messages),
+            (panels.input, Focus::Input),
+            (panels.log, Focus::Log),
+            (panels.users, Focus::Users),
+        ].iter()
+            .find(|(rect, _)| contains(rect, column, row))
+            .map(|(_, focus)| *focus)
+    }
+
+    pub fn panel(&self, focus: Focus) -> Rect {
+        match focus {
+            Focus::Messages => self.panels.messages,
+            Focus::Input => self.panels.input,
+            Focus::Log => self.panels.log,
+            Focus::Users => self.panels.users,
+        }
+    }
+
+    pub fn user_at(&self, row: u16) -> Option<&str> {
+        self.panels.user_rows.iter().find(|(r, _)| *r == row).map(|(_, name)| name.as_str())
+    }
+
+    /// a drag starts inside the borders of a panel
+    pub fn start_selection(&mut self, focus: Focus, column: u16, row: u16) {
+        let area = inner(self.panel(focus));
+        self.selection = match contains(&area, column, row) {
+            true => Some(TextSelection { area, anchor: (column, row), head: (column, row), copy: false }),
+            false => None,
+        };
+    }
+
+    pub fn drag_selection(&mut self, column: u16, row: u16) {
+        if let Some(selection) = &mut self.selection {
+            let area = selection.area;
+            selection.head = (
+                column.clamp(area.left(), area.right() - 1),
+                row.clamp(area.top(), area.bottom() - 1),
+            );
+        }
+    }
+
+    /// copy what was dragged over, a click without a drag selects nothing
+    pub fn end_selection(&mut self) {
+        match self.selection {
+            Some(selection) if selection.anchor != selection.head => {
+                self.selection = Some(TextSelection { copy: true, ..selection });
+            }
+            _ => self.selection = None,
+        }
+    }
+}
+
+
+fn contains(rect: &Rect, column: u16, row: u16) -> bool {
+    rect.width > 0 && rect.height > 0
+        && column >= rect.left() && column < rect.right()
+        && row >= rect.top() && row < rect.bottom()
+}
+
+/// the rect inside the borders, empty for a rect without room for them
+fn inner(rect: Rect) -> Rect {
+    match rect.width > 2 && rect.height > 2 {
+        true => Rect::new(rect.x + 1, rect.y + 1, rect.width - 2, rect.height - 2),
+        false => Rect::default(),
+    }
+}